@@ -0,0 +1,26 @@
+fn main() {
+    // Record the locked revm version for run manifests.
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let revm_version = lock
+        .split("[[package]]")
+        .find(|package| package.contains("\nname = \"revm\"\n"))
+        .and_then(|package| {
+            package
+                .lines()
+                .find_map(|line| line.strip_prefix("version = "))
+        })
+        .map_or("unknown", |version| version.trim_matches('"'));
+    println!("cargo:rustc-env=LIB_KECCAK_REVM_VERSION={revm_version}");
+
+    // Compile the XKCP reference implementation and its FFI shim.
+    #[cfg(feature = "xkcp")]
+    {
+        println!("cargo:rerun-if-changed=ffi/xkcp");
+        cc::Build::new()
+            .file("ffi/xkcp/Keccak-readable-and-compact.c")
+            .file("ffi/xkcp/shim.c")
+            .include("ffi/xkcp")
+            .compile("xkcp");
+    }
+}
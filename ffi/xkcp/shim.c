@@ -0,0 +1,7 @@
+#include "xkcp.h"
+
+/* Keccak256 as used by the EVM: rate 1088, capacity 512, and the original `0x01` domain suffix. */
+void lib_keccak_xkcp_keccak256(const uint8_t *input, size_t input_len, uint8_t *output)
+{
+    Keccak(1088, 512, input, input_len, 0x01, output, 32);
+}
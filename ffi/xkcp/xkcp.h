@@ -0,0 +1,13 @@
+#ifndef LIB_KECCAK_XKCP_H
+#define LIB_KECCAK_XKCP_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+/* The XKCP compact Keccak sponge over Keccak-f[1600]. */
+void Keccak(unsigned int rate, unsigned int capacity, const unsigned char *input, unsigned long long int inputByteLen, unsigned char delimitedSuffix, unsigned char *output, unsigned long long int outputByteLen);
+
+/* Computes the original Keccak256 digest of `input`, writing 32 bytes to `output`. */
+void lib_keccak_xkcp_keccak256(const uint8_t *input, size_t input_len, uint8_t *output);
+
+#endif
@@ -0,0 +1,29 @@
+//! libFuzzer target that feeds arbitrary bytes through the `StatefulSponge` contract and every
+//! reference backend, panicking on any digest mismatch.
+
+#![no_main]
+
+use lib_keccak::{available_backends, DiffEngine};
+use libfuzzer_sys::fuzz_target;
+use std::cell::RefCell;
+
+thread_local! {
+    /// The diff engine, reused across executions so the sponge is only deployed once.
+    static ENGINE: RefCell<DiffEngine> = RefCell::new(
+        DiffEngine::new(&available_backends(), false).expect("Failed to initialize the diff engine"),
+    );
+}
+
+fuzz_target!(|data: &[u8]| {
+    ENGINE.with(|engine| {
+        let mut engine = engine.borrow_mut();
+        let verdict = engine
+            .diff(data)
+            .expect("Failed to hash the input in the EVM");
+        assert!(
+            verdict.is_unanimous(),
+            "Hash mismatch ({})",
+            engine.describe(&verdict)
+        );
+    });
+});
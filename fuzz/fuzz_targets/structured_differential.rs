@@ -0,0 +1,33 @@
+//! libFuzzer target that derives a structured [FuzzInput] from the fuzzer's bytes, and diffs the
+//! message absorbed across the planned `absorb` calls against every reference backend.
+
+#![no_main]
+
+use lib_keccak::{available_backends, structured::FuzzInput, DiffEngine};
+use libfuzzer_sys::fuzz_target;
+use std::cell::RefCell;
+
+thread_local! {
+    /// The diff engine, reused across executions so the sponge is only deployed once.
+    static ENGINE: RefCell<DiffEngine> = RefCell::new(
+        DiffEngine::new(&available_backends(), false).expect("Failed to initialize the diff engine"),
+    );
+}
+
+fuzz_target!(|input: FuzzInput| {
+    let message = input.message();
+    let chunks = input.split(&message);
+
+    ENGINE.with(|engine| {
+        let mut engine = engine.borrow_mut();
+        let verdict = engine
+            .diff_chunked(&chunks)
+            .expect("Failed to hash the input in the EVM");
+        assert!(
+            verdict.is_unanimous(),
+            "Hash mismatch over {} chunks ({})",
+            chunks.len(),
+            engine.describe(&verdict)
+        );
+    });
+});
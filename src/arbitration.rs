@@ -0,0 +1,163 @@
+//! Majority-vote arbitration over the digests produced by the contract and the reference backends.
+//!
+//! The builds of the contract (sessions, specs, variant and matrix builds, targets) can outnumber
+//! the reference backends, and a bug shared by all of them must not outvote the references. So the
+//! reference backends are arbitrated among themselves first, and every contract digest is then
+//! judged against their consensus (see [arbitrate_contracts]).
+
+use alloy_primitives::hex;
+
+/// The outcome of arbitrating a set of digests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Every implementation produced the same digest.
+    Unanimous,
+    /// A strict majority of implementations agreed on `digest`, and the implementations at the
+    /// indices in `dissenters` did not.
+    Majority {
+        /// The digest that the majority agreed on.
+        digest: [u8; 32],
+        /// The indices of the implementations that disagreed with the majority.
+        dissenters: Vec<usize>,
+    },
+    /// No digest was produced by a strict majority of implementations.
+    NoConsensus,
+}
+
+impl Verdict {
+    /// Returns `true` if every implementation agreed.
+    pub fn is_unanimous(&self) -> bool {
+        matches!(self, Self::Unanimous)
+    }
+}
+
+/// Arbitrates the given digests by majority vote. A dissenting implementation can only be singled
+/// out when at least three digests take part in the vote.
+pub fn arbitrate(digests: &[[u8; 32]]) -> Verdict {
+    let Some(first) = digests.first() else {
+        return Verdict::Unanimous;
+    };
+    if digests.iter().all(|digest| digest == first) {
+        return Verdict::Unanimous;
+    }
+
+    // Tally the votes for each distinct digest, keeping the first-seen order for determinism.
+    let mut tally: Vec<([u8; 32], usize)> = Vec::new();
+    for digest in digests {
+        match tally.iter_mut().find(|(candidate, _)| candidate == digest) {
+            Some((_, votes)) => *votes += 1,
+            None => tally.push((*digest, 1)),
+        }
+    }
+
+    let (digest, votes) = tally
+        .into_iter()
+        .max_by_key(|&(_, votes)| votes)
+        .expect("at least one digest");
+    if votes * 2 <= digests.len() {
+        return Verdict::NoConsensus;
+    }
+
+    let dissenters = digests
+        .iter()
+        .enumerate()
+        .filter_map(|(i, candidate)| (*candidate != digest).then_some(i))
+        .collect();
+    Verdict::Majority { digest, dissenters }
+}
+
+/// Arbitrates the given digests, of which the first `contracts` were produced by builds of the
+/// contract and the rest by reference backends. The reference backends are arbitrated by majority
+/// vote (see [arbitrate]), and every contract digest that differs from their consensus dissents,
+/// no matter how many contract digests agree with it. Without reference backends, every digest
+/// takes part in a single vote.
+pub fn arbitrate_contracts(digests: &[[u8; 32]], contracts: usize) -> Verdict {
+    let references = &digests[contracts.min(digests.len())..];
+    let consensus = match arbitrate(references) {
+        _ if references.is_empty() => return arbitrate(digests),
+        Verdict::Unanimous => references[0],
+        Verdict::Majority { digest, .. } => digest,
+        Verdict::NoConsensus => return Verdict::NoConsensus,
+    };
+
+    let dissenters = digests
+        .iter()
+        .enumerate()
+        .filter_map(|(i, digest)| (*digest != consensus).then_some(i))
+        .collect::<Vec<_>>();
+    if dissenters.is_empty() {
+        return Verdict::Unanimous;
+    }
+    Verdict::Majority {
+        digest: consensus,
+        dissenters,
+    }
+}
+
+/// Describes a non-unanimous vote for a mismatch report, naming the dissenting implementations and
+/// listing every implementation's digest. `names` and `digests` are indexed alike.
+pub fn describe(verdict: &Verdict, names: &[&str], digests: &[[u8; 32]]) -> String {
+    let culprit = match verdict {
+        Verdict::Majority { dissenters, .. } => {
+            let dissenters = dissenters.iter().map(|&i| names[i]).collect::<Vec<_>>();
+            format!("dissenting: {}", dissenters.join(", "))
+        }
+        _ => "no majority".to_string(),
+    };
+    let matrix = names
+        .iter()
+        .zip(digests.iter())
+        .map(|(name, digest)| format!("{}: {}", name, hex::encode(digest)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{culprit} - digests: [{matrix}]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contract_builds_cannot_outvote_the_references() {
+        let (good, bad) = ([1u8; 32], [2u8; 32]);
+        let digests = [bad, bad, bad, bad, bad, good, good, good];
+        assert_eq!(
+            arbitrate(&digests),
+            Verdict::Majority {
+                digest: bad,
+                dissenters: vec![5, 6, 7],
+            }
+        );
+        assert_eq!(
+            arbitrate_contracts(&digests, 5),
+            Verdict::Majority {
+                digest: good,
+                dissenters: vec![0, 1, 2, 3, 4],
+            }
+        );
+    }
+
+    #[test]
+    fn references_are_arbitrated_among_themselves() {
+        let (good, bad) = ([1u8; 32], [2u8; 32]);
+        assert_eq!(
+            arbitrate_contracts(&[good, good, bad, good], 1),
+            Verdict::Majority {
+                digest: good,
+                dissenters: vec![2],
+            }
+        );
+        assert_eq!(
+            arbitrate_contracts(&[good, good, bad], 1),
+            Verdict::NoConsensus
+        );
+        assert_eq!(
+            arbitrate_contracts(&[good, bad, good], 3),
+            Verdict::Majority {
+                digest: good,
+                dissenters: vec![1],
+            }
+        );
+        assert!(arbitrate_contracts(&[good, good, good], 1).is_unanimous());
+    }
+}
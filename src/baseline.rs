@@ -0,0 +1,197 @@
+//! Gas regression baselines, so that campaigns double as a gas-regression gate for the contract.
+//!
+//! A baseline records the most gas used to hash an input of every number of blocks (see
+//! [crate::gas::GasPoint::blocks]) in a profiled campaign, along with the hash of the contract bytecode that it
+//! was measured on. A later campaign fails if the most gas it measured at any number of blocks
+//! exceeds the baseline by more than a tolerance.
+//!
+//! Baselines are line-based text files:
+//!
+//! ```text
+//! lib-keccak gas baseline v1
+//! code_hash 0x2bce...
+//! blocks 1 219045
+//! blocks 2 344027
+//! ```
+
+use crate::{permutation::RATE_BYTES, report::Report, reporter::Reporter, DiffFuzzer};
+use alloy_primitives::hex;
+use anyhow::{bail, Context, Result};
+use std::{collections::BTreeMap, fmt, fmt::Write, fs, path::Path, path::PathBuf};
+
+/// The first line of every baseline file.
+const HEADER: &str = "lib-keccak gas baseline v1";
+
+/// The most gas used to hash an input of every number of blocks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GasBaseline {
+    /// The hash of the contract's runtime bytecode that the baseline was measured on, if known.
+    pub code_hash: Option<[u8; 32]>,
+    /// The most gas used by an input, by number of blocks.
+    pub blocks: BTreeMap<usize, u64>,
+}
+
+/// A number of blocks at which the measured gas exceeds the baseline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasRegression {
+    /// The number of blocks.
+    pub blocks: usize,
+    /// The most gas used in the baseline.
+    pub baseline: u64,
+    /// The most gas used in the campaign.
+    pub measured: u64,
+}
+
+impl GasRegression {
+    /// Returns the increase in gas over the baseline, in percent.
+    pub fn percent(&self) -> f64 {
+        (self.measured as f64 / self.baseline as f64 - 1.0) * 100.0
+    }
+}
+
+impl fmt::Display for GasRegression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} blocks: {} gas -> {} gas ({:+.2}%)",
+            self.blocks,
+            self.baseline,
+            self.measured,
+            self.percent()
+        )
+    }
+}
+
+impl GasBaseline {
+    /// Returns the baseline of a profiled campaign (see [DiffFuzzer::profile]), measured on the
+    /// contract under test.
+    pub fn of(report: &Report) -> Result<Self> {
+        let mut blocks = BTreeMap::new();
+        for (len, profile) in &report.lengths {
+            if profile.gas.samples == 0 {
+                continue;
+            }
+            let max = blocks.entry(len / RATE_BYTES + 1).or_default();
+            *max = profile.gas.max.max(*max);
+        }
+
+        #[cfg(feature = "evm")]
+        let code_hash = Some(crate::harness::contract_code_hash()?);
+        #[cfg(not(feature = "evm"))]
+        let code_hash = None;
+        Ok(Self { code_hash, blocks })
+    }
+
+    /// Reads a baseline file written by [GasBaseline::write].
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read gas baseline {}", path.display()))?;
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            bail!("{} is not a gas baseline file", path.display());
+        }
+
+        let mut baseline = Self::default();
+        for (i, line) in lines.enumerate() {
+            match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["code_hash", hash] => {
+                    let mut code_hash = [0u8; 32];
+                    hex::decode_to_slice(hash, &mut code_hash)?;
+                    baseline.code_hash = Some(code_hash);
+                }
+                ["blocks", blocks, gas] => {
+                    baseline.blocks.insert(blocks.parse()?, gas.parse()?);
+                }
+                [] => {}
+                _ => bail!(
+                    "Unexpected line {} of gas baseline {}: {line}",
+                    i + 2,
+                    path.display()
+                ),
+            }
+        }
+        Ok(baseline)
+    }
+
+    /// Writes the baseline to `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut text = format!("{HEADER}\n");
+        if let Some(code_hash) = self.code_hash {
+            writeln!(text, "code_hash 0x{}", hex::encode(code_hash))?;
+        }
+        for (blocks, gas) in &self.blocks {
+            writeln!(text, "blocks {blocks} {gas}")?;
+        }
+        fs::write(path, text)
+            .with_context(|| format!("Failed to write gas baseline {}", path.display()))
+    }
+
+    /// Returns every number of blocks measured in both baselines at which `measured` exceeds this
+    /// baseline by more than `tolerance_percent`.
+    pub fn regressions(&self, measured: &Self, tolerance_percent: f64) -> Vec<GasRegression> {
+        measured
+            .blocks
+            .iter()
+            .filter_map(|(blocks, measured)| {
+                let regression = GasRegression {
+                    blocks: *blocks,
+                    baseline: *self.blocks.get(blocks)?,
+                    measured: *measured,
+                };
+                (regression.percent() > tolerance_percent).then_some(regression)
+            })
+            .collect()
+    }
+}
+
+/// Records the gas baseline of every campaign to a file when it finishes successfully.
+#[derive(Debug, Clone)]
+pub struct GasBaselineRecorder {
+    /// The file to write the baseline to.
+    pub path: PathBuf,
+}
+
+impl Reporter for GasBaselineRecorder {
+    fn on_finish(&self, _: &DiffFuzzer, outcome: &Result<Report>) -> Result<()> {
+        match outcome {
+            Ok(report) => GasBaseline::of(report)?.write(&self.path),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// Fails every campaign whose gas regresses beyond a tolerance of a baseline.
+#[derive(Debug, Clone)]
+pub struct GasBaselineCheck {
+    /// The baseline to compare against.
+    pub baseline: GasBaseline,
+    /// The increase over the baseline, in percent, above which a number of blocks regresses.
+    pub tolerance_percent: f64,
+}
+
+impl Reporter for GasBaselineCheck {
+    fn on_finish(&self, _: &DiffFuzzer, outcome: &Result<Report>) -> Result<()> {
+        let Ok(report) = outcome else {
+            return Ok(());
+        };
+        let measured = GasBaseline::of(report)?;
+        let regressions = self.baseline.regressions(&measured, self.tolerance_percent);
+        if regressions.is_empty() {
+            return Ok(());
+        }
+
+        let mut message = format!("Gas regressed by more than {}%", self.tolerance_percent);
+        if let Some(code_hash) = self.baseline.code_hash {
+            write!(
+                message,
+                " over the baseline of 0x{}",
+                hex::encode(code_hash)
+            )?;
+        }
+        message.push(':');
+        for regression in regressions {
+            write!(message, "\n- {regression}")?;
+        }
+        bail!(message)
+    }
+}
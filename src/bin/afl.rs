@@ -0,0 +1,30 @@
+//! AFL++ persistent-mode entrypoint for the `StatefulSponge` differential.
+//!
+//! One EVM with the sponge deployed, plus every reference backend, is shared across all executions
+//! of the persistent loop. Build and run with:
+//!
+//! ```sh
+//! cargo afl build --release --features afl --bin afl-keccak
+//! cargo afl fuzz -i <seeds> -o <findings> target/release/afl-keccak
+//! ```
+
+use lib_keccak::{available_backends, DiffEngine};
+use std::panic::AssertUnwindSafe;
+
+fn main() {
+    let mut engine = AssertUnwindSafe(
+        DiffEngine::new(&available_backends(), false)
+            .expect("Failed to initialize the diff engine"),
+    );
+
+    afl::fuzz!(|data: &[u8]| {
+        let verdict = engine
+            .diff(data)
+            .expect("Failed to hash the input in the EVM");
+        assert!(
+            verdict.is_unanimous(),
+            "Hash mismatch ({})",
+            engine.describe(&verdict)
+        );
+    });
+}
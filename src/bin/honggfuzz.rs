@@ -0,0 +1,28 @@
+//! honggfuzz entrypoint for the `StatefulSponge` differential.
+//!
+//! Shares the same [DiffEngine] as the AFL++ and libFuzzer harnesses: one EVM with the sponge
+//! deployed, plus every reference backend, reused across iterations. Build and run with:
+//!
+//! ```sh
+//! cargo hfuzz run hfuzz-keccak
+//! ```
+
+use lib_keccak::{available_backends, DiffEngine};
+
+fn main() {
+    let mut engine = DiffEngine::new(&available_backends(), false)
+        .expect("Failed to initialize the diff engine");
+
+    loop {
+        honggfuzz::fuzz!(|data: &[u8]| {
+            let verdict = engine
+                .diff(data)
+                .expect("Failed to hash the input in the EVM");
+            assert!(
+                verdict.is_unanimous(),
+                "Hash mismatch ({})",
+                engine.describe(&verdict)
+            );
+        });
+    }
+}
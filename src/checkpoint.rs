@@ -0,0 +1,144 @@
+//! Campaign checkpoints, so that multi-day campaigns survive restarts of the host.
+//!
+//! Since every input is a function of the campaign seed, its coordinates and the shard's corpus (see
+//! [crate::DiffFuzzer::regenerate]), a checkpoint only records the seed, plus the next iteration and
+//! the corpus of every shard. Since the shards don't depend on the thread count, a campaign can be
+//! resumed with a different one. The coverage map of coverage-guided campaigns is not persisted, so
+//! such a campaign would keep other inputs after resuming than it did uninterrupted, and generate
+//! another input stream: resuming it is refused instead.
+//!
+//! Checkpoints are line-based text files:
+//!
+//! ```text
+//! lib-keccak checkpoint v1
+//! seed 4687673050821352035
+//! shard 0 20000
+//! corpus 0 00ff...
+//! ```
+
+use alloy_primitives::hex;
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The first line of every checkpoint file.
+const HEADER: &str = "lib-keccak checkpoint v1";
+
+/// The progress of a campaign.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The master seed of the campaign.
+    pub seed: u64,
+    /// The progress of every shard, indexed by shard.
+    pub shards: Vec<ShardCheckpoint>,
+}
+
+/// The progress of a single shard of the campaign.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShardCheckpoint {
+    /// The next iteration that the shard runs.
+    pub iteration: u64,
+    /// The shard's corpus: its seed inputs, plus every novel input it generated.
+    pub corpus: Vec<Vec<u8>>,
+}
+
+impl Checkpoint {
+    /// Reads a checkpoint file written by [Checkpoint::write].
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read checkpoint {}", path.display()))?;
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            bail!("{} is not a checkpoint file", path.display());
+        }
+
+        let mut seed = None;
+        let mut shards: Vec<ShardCheckpoint> = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let line_no = i + 2;
+            match fields.as_slice() {
+                ["seed", value] => seed = Some(value.parse()?),
+                ["shard", shard, iteration] => {
+                    let shard = shard.parse::<usize>()?;
+                    if shard != shards.len() {
+                        bail!("Unexpected shard {shard} on line {line_no} of the checkpoint");
+                    }
+                    shards.push(ShardCheckpoint {
+                        iteration: iteration.parse()?,
+                        corpus: Vec::new(),
+                    });
+                }
+                // Empty corpus entries have no hex field.
+                ["corpus", shard, entry @ ..] if entry.len() <= 1 => {
+                    let entry = entry.first().copied().unwrap_or_default();
+                    let Some(shard) = shards.get_mut(shard.parse::<usize>()?) else {
+                        bail!(
+                            "Corpus entry of an unknown shard on line {line_no} of the checkpoint"
+                        );
+                    };
+                    shard.corpus.push(hex::decode(entry)?);
+                }
+                _ => bail!("Malformed line {line_no} of the checkpoint: `{line}`"),
+            }
+        }
+
+        let Some(seed) = seed else {
+            bail!("The checkpoint {} is missing the seed", path.display());
+        };
+        Ok(Self { seed, shards })
+    }
+
+    /// Writes the checkpoint to `path`, atomically replacing any previous checkpoint.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut text = format!("{HEADER}\nseed {}\n", self.seed);
+        for (i, shard) in self.shards.iter().enumerate() {
+            text.push_str(&format!("shard {i} {}\n", shard.iteration));
+            for entry in &shard.corpus {
+                text.push_str(&format!("corpus {i} {}\n", hex::encode(entry)));
+            }
+        }
+
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, text)
+            .with_context(|| format!("Failed to write checkpoint {}", tmp.display()))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write checkpoint {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// A checkpoint shared by the tasks of a campaign, each of which periodically updates the progress
+/// of its own shards.
+#[derive(Debug)]
+pub(crate) struct CheckpointWriter {
+    /// The path that the checkpoint is written to.
+    path: PathBuf,
+    /// The progress of the campaign.
+    checkpoint: Mutex<Checkpoint>,
+}
+
+impl CheckpointWriter {
+    /// Creates a writer that writes `checkpoint` to `path`.
+    pub(crate) fn new(path: PathBuf, checkpoint: Checkpoint) -> Self {
+        Self {
+            path,
+            checkpoint: Mutex::new(checkpoint),
+        }
+    }
+
+    /// Records the progress of the given shards, by index, and rewrites the checkpoint.
+    pub(crate) fn update(
+        &self,
+        shards: impl IntoIterator<Item = (u64, ShardCheckpoint)>,
+    ) -> Result<()> {
+        let mut checkpoint = self.checkpoint.lock().expect("checkpoint lock poisoned");
+        for (index, shard) in shards {
+            checkpoint.shards[index as usize] = shard;
+        }
+        checkpoint.write(&self.path)
+    }
+}
@@ -0,0 +1,278 @@
+//! A/B comparison of two builds of the `StatefulSponge` contract, e.g. the current main branch
+//! against an optimization PR.
+//!
+//! Both builds hash an identical seeded input stream (see [DiffFuzzer::generate_fresh]), each in an
+//! EVM of its own under the latest spec. Every digest of one build must equal that of the other, and
+//! the gas of both is aggregated per number of permutations of the input, so that the gas delta of
+//! the optimization can be read off per input size.
+//!
+//! A history of builds, e.g. one per commit, can also be bisected for the first build that
+//! regresses on a failing input (see [bisect_builds]), to automate the "when did this break"
+//! investigation.
+
+use crate::{engine::MatrixBuild, report::GasStats, DiffFuzzer};
+use anyhow::Result;
+use std::{collections::BTreeMap, fmt::Write};
+
+/// The outcome of comparing two builds (see [compare_builds]).
+#[derive(Debug, Clone, Default)]
+pub struct Comparison {
+    /// The number of inputs that both builds hashed.
+    pub inputs: u64,
+    /// Every input that the builds squeezed different digests for, up to [MAX_MISMATCHES], along
+    /// with which of the builds agrees with the reference backend.
+    pub mismatches: Vec<Mismatch>,
+    /// The number of inputs that the builds squeezed different digests for.
+    pub mismatch_count: u64,
+    /// The gas used by both builds per input, by the number of permutations of the input.
+    pub blocks: BTreeMap<usize, (GasStats, GasStats)>,
+}
+
+/// An input that two builds squeezed different digests for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The input.
+    pub input: Vec<u8>,
+    /// The digest squeezed by build A.
+    pub digest_a: [u8; 32],
+    /// The digest squeezed by build B.
+    pub digest_b: [u8; 32],
+    /// The digest of the reference backend.
+    pub reference: [u8; 32],
+}
+
+/// The largest number of mismatches that a [Comparison] keeps.
+pub const MAX_MISMATCHES: usize = 16;
+
+impl Comparison {
+    /// Returns `true` if the builds squeezed the same digest for every input.
+    pub fn is_equivalent(&self) -> bool {
+        self.mismatch_count == 0
+    }
+
+    /// Returns the gas used by both builds over all inputs.
+    pub fn total_gas(&self) -> (GasStats, GasStats) {
+        let (mut a, mut b) = (GasStats::default(), GasStats::default());
+        for (gas_a, gas_b) in self.blocks.values() {
+            a.merge(*gas_a);
+            b.merge(*gas_b);
+        }
+        (a, b)
+    }
+
+    /// Renders the mean gas of both builds per number of permutations, and overall, as a table
+    /// with the relative delta of B against A.
+    pub fn gas_table(&self) -> String {
+        let mut table = String::new();
+        writeln!(
+            table,
+            "{:>7} {:>8} {:>12} {:>12} {:>12} {:>9}",
+            "blocks", "inputs", "gas A", "gas B", "delta", "delta %"
+        )
+        .unwrap();
+        let (total_a, total_b) = self.total_gas();
+        let rows = self
+            .blocks
+            .iter()
+            .map(|(blocks, gas)| (blocks.to_string(), *gas))
+            .chain(std::iter::once(("all".to_string(), (total_a, total_b))));
+        for (blocks, (a, b)) in rows {
+            let (Some(mean_a), Some(mean_b)) = (a.mean(), b.mean()) else {
+                continue;
+            };
+            writeln!(
+                table,
+                "{blocks:>7} {:>8} {mean_a:>12.0} {mean_b:>12.0} {:>+12.0} {:>+8.2}%",
+                a.samples,
+                mean_b - mean_a,
+                (mean_b / mean_a - 1.0) * 100.0
+            )
+            .unwrap();
+        }
+        table
+    }
+}
+
+/// Hashes the first `inputs` inputs of the seeded input stream of `fuzzer`, split into its absorb
+/// chunks, with both `code_a` and `code_b`, the runtime bytecode of two builds of the contract.
+/// Requires the `evm` feature, and the fuzzer's seed.
+pub fn compare_builds(
+    fuzzer: &DiffFuzzer,
+    code_a: &[u8],
+    code_b: &[u8],
+    inputs: u64,
+) -> Result<Comparison> {
+    if fuzzer.shards == 0 {
+        anyhow::bail!("The inputs are dealt over the shards, so at least one shard is required");
+    }
+    #[cfg(feature = "evm")]
+    {
+        use crate::{
+            engine::{absorb_chunks, reference_message},
+            harness::{hash_chunks_evm_metered, init_evm_with_code},
+            hashing::hash_input_tiny,
+            permutation::RATE_BYTES,
+        };
+        use revm::primitives::SpecId;
+
+        let mut evm_a = init_evm_with_code(SpecId::LATEST, code_a)?;
+        let mut evm_b = init_evm_with_code(SpecId::LATEST, code_b)?;
+        let mut comparison = Comparison::default();
+        for i in 0..inputs {
+            // Deal the inputs over the shards like a campaign does.
+            let input = fuzzer.generate_fresh(i % fuzzer.shards, i / fuzzer.shards)?;
+            let chunks = absorb_chunks(&input, fuzzer.absorb_chunk_bytes);
+            let (digest_a, gas_a) = hash_chunks_evm_metered(&mut evm_a, &chunks)?;
+            let (digest_b, gas_b) = hash_chunks_evm_metered(&mut evm_b, &chunks)?;
+
+            comparison.inputs += 1;
+            let blocks = comparison
+                .blocks
+                .entry(input.len() / RATE_BYTES + 1)
+                .or_default();
+            blocks.0.record(gas_a.total());
+            blocks.1.record(gas_b.total());
+            if digest_a != digest_b {
+                comparison.mismatch_count += 1;
+                if comparison.mismatches.len() < MAX_MISMATCHES {
+                    let mut reference = [0u8; 32];
+                    hash_input_tiny(reference_message(&chunks).as_ref(), reference.as_mut());
+                    comparison.mismatches.push(Mismatch {
+                        input,
+                        digest_a,
+                        digest_b,
+                        reference,
+                    });
+                }
+            }
+        }
+        Ok(comparison)
+    }
+    #[cfg(not(feature = "evm"))]
+    {
+        let _ = (fuzzer, code_a, code_b, inputs);
+        anyhow::bail!("Comparing builds of the contract requires the `evm` feature")
+    }
+}
+
+/// What makes a build of the contract regress on an input (see [bisect_builds]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Regression {
+    /// The build squeezes another digest than the reference backend.
+    Mismatch,
+    /// The build uses more than `tolerance_percent` percent more gas than the oldest build.
+    Gas {
+        /// The increase over the oldest build, in percent, above which the gas regresses.
+        tolerance_percent: f64,
+    },
+}
+
+/// A build that an input was hashed with during a bisection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Probe {
+    /// The index of the build in the history.
+    pub build: usize,
+    /// The digest squeezed by the build.
+    pub digest: [u8; 32],
+    /// The gas used by the build.
+    pub gas: u64,
+    /// Whether the build regresses.
+    pub regressed: bool,
+}
+
+/// The outcome of bisecting a history of builds (see [bisect_builds]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bisection {
+    /// The index of the first build that regresses, whose predecessor doesn't.
+    pub first_regressed: usize,
+    /// The digest of the reference backend.
+    pub reference: [u8; 32],
+    /// The builds that the input was hashed with, in the order of the bisection: the oldest and
+    /// newest build first.
+    pub probes: Vec<Probe>,
+}
+
+/// Binary-searches `builds`, ordered from oldest to newest, for the first one that regresses on
+/// `input`, split into absorb chunks of at most `absorb_chunk_bytes` bytes. The oldest build must
+/// not regress and the newest must, and every build in between is assumed to regress if and only
+/// if it's at least as new as the first one that does. Each build is hashed with in an EVM of its
+/// own under the latest spec. Requires the `evm` feature.
+pub fn bisect_builds(
+    builds: &[MatrixBuild],
+    input: &[u8],
+    absorb_chunk_bytes: Option<usize>,
+    regression: Regression,
+) -> Result<Bisection> {
+    #[cfg(feature = "evm")]
+    {
+        use crate::{
+            engine::{absorb_chunks, reference_message},
+            harness::{hash_chunks_evm_metered, init_evm_with_code},
+            hashing::hash_input_tiny,
+        };
+        use anyhow::{bail, Context};
+        use revm::primitives::SpecId;
+
+        if builds.len() < 2 {
+            bail!("Bisecting requires at least 2 builds, got {}", builds.len());
+        }
+        let chunks = absorb_chunks(input, absorb_chunk_bytes);
+        let mut reference = [0u8; 32];
+        hash_input_tiny(reference_message(&chunks).as_ref(), reference.as_mut());
+
+        let mut probes = Vec::new();
+        let mut oldest_gas = None;
+        let mut probe = |build: usize| -> Result<bool> {
+            let label = &builds[build].label;
+            let mut evm = init_evm_with_code(SpecId::LATEST, &builds[build].code)
+                .with_context(|| format!("Failed to deploy {label}"))?;
+            let (digest, gas) = hash_chunks_evm_metered(&mut evm, &chunks)
+                .with_context(|| format!("Failed to hash the input with {label}"))?;
+            let gas = gas.total();
+            let oldest_gas = *oldest_gas.get_or_insert(gas);
+            let regressed = match regression {
+                Regression::Mismatch => digest != reference,
+                Regression::Gas { tolerance_percent } => {
+                    (gas as f64 / oldest_gas as f64 - 1.0) * 100.0 > tolerance_percent
+                }
+            };
+            probes.push(Probe {
+                build,
+                digest,
+                gas,
+                regressed,
+            });
+            Ok(regressed)
+        };
+
+        let newest = builds.len() - 1;
+        if probe(0)? {
+            bail!("The oldest build, {}, already regresses", builds[0].label);
+        }
+        if !probe(newest)? {
+            bail!(
+                "The newest build, {}, doesn't regress",
+                builds[newest].label
+            );
+        }
+        let (mut good, mut regressed) = (0, newest);
+        while regressed - good > 1 {
+            let build = good + (regressed - good) / 2;
+            if probe(build)? {
+                regressed = build;
+            } else {
+                good = build;
+            }
+        }
+        Ok(Bisection {
+            first_regressed: regressed,
+            reference,
+            probes,
+        })
+    }
+    #[cfg(not(feature = "evm"))]
+    {
+        let _ = (builds, input, absorb_chunk_bytes, regression);
+        anyhow::bail!("Bisecting builds of the contract requires the `evm` feature")
+    }
+}
@@ -0,0 +1,270 @@
+//! On-the-fly compilation of the contract under test, so that a single command covers the
+//! edit-compile-fuzz loop of `LibKeccak` development.
+//!
+//! A Foundry project is built with `forge build`, and the contract is read from its artifact (see
+//! [Artifact::read_target]). A single Solidity source is compiled with `solc` instead, whose
+//! combined JSON output carries the same bytecode and source map. Either way, the compiler's
+//! diagnostics are surfaced verbatim when the build fails.
+//!
+//! The contract can also be compiled under other [CompilerSettings], such as the via-IR pipeline
+//! or other optimizer runs, to diff the builds of both codegen pipelines side by side in a build
+//! matrix (see [crate::engine::DiffEngine::with_build_matrix]).
+//!
+//! Gas-golfed alternative implementations written in Huff are compiled with `huffc` (see
+//! [compile_huff]), and pure-Yul objects with `solc --strict-assembly` (see [compile_yul]), to be
+//! diffed as targets (see [crate::engine::DiffEngine::with_targets]).
+
+use crate::sourcemap::{parse_source_map, Artifact};
+use alloy_primitives::hex;
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::{
+    fmt,
+    path::Path,
+    process::{Command, Output},
+    str::FromStr,
+    time::Instant,
+};
+
+/// The optimizer settings that a contract is compiled with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompilerSettings {
+    /// The number of optimizer runs, if the optimizer is enabled.
+    pub optimizer_runs: Option<u64>,
+    /// Whether the contract is compiled through the Yul IR pipeline, rather than the legacy one.
+    pub via_ir: bool,
+}
+
+impl CompilerSettings {
+    /// Returns the settings as a file name component, e.g. `runs-200-via-ir`.
+    pub fn slug(&self) -> String {
+        let mut slug = match self.optimizer_runs {
+            Some(runs) => format!("runs-{runs}"),
+            None => "unoptimized".to_string(),
+        };
+        if self.via_ir {
+            slug.push_str("-via-ir");
+        }
+        slug
+    }
+}
+
+impl fmt::Display for CompilerSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.optimizer_runs {
+            Some(runs) => write!(f, "{runs} runs")?,
+            None => f.write_str("unoptimized")?,
+        }
+        f.write_str(if self.via_ir { ", via-IR" } else { ", legacy" })
+    }
+}
+
+impl FromStr for CompilerSettings {
+    type Err = anyhow::Error;
+
+    /// Parses `+`-separated settings, `runs=<n>` and `via-ir`, e.g. `runs=200+via-ir`. The
+    /// optimizer is disabled unless the runs are given.
+    fn from_str(settings: &str) -> Result<Self> {
+        let mut parsed = Self::default();
+        for setting in settings.split('+').map(str::trim) {
+            match setting.split_once('=') {
+                Some(("runs", runs)) => {
+                    parsed.optimizer_runs = Some(
+                        runs.parse()
+                            .with_context(|| format!("Invalid optimizer runs `{runs}`"))?,
+                    );
+                }
+                None if setting == "via-ir" => parsed.via_ir = true,
+                _ => bail!("Unknown compiler setting `{setting}`, expected `runs=<n>` or `via-ir`"),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Compiles the contract named `contract` at `path`, which is either the root of a Foundry project,
+/// built with `forge build`, or a Solidity source file, compiled with `solc` from the current
+/// directory (so that imports such as `contracts/lib/LibKeccak.sol` resolve from the repository
+/// root).
+pub fn compile(path: &Path, contract: &str) -> Result<Artifact> {
+    compile_with(path, contract, None)
+}
+
+/// Like [compile], but overrides the optimizer settings of the project (or the `solc` defaults)
+/// with `settings`, if given. A Foundry project then writes its artifacts to `out-<settings>`
+/// instead of `out`, so that the builds of several settings don't overwrite each other.
+pub fn compile_with(
+    path: &Path,
+    contract: &str,
+    settings: Option<&CompilerSettings>,
+) -> Result<Artifact> {
+    let started = Instant::now();
+    let artifact = if path.is_dir() {
+        forge_build(path, contract, settings)?
+    } else {
+        solc(path, contract, settings)?
+    };
+    tracing::info!(
+        elapsed = ?started.elapsed(),
+        settings = %settings.map_or_else(|| "default".to_string(), ToString::to_string),
+        "Compiled {contract} from {}",
+        path.display()
+    );
+    Ok(artifact)
+}
+
+/// Builds the Foundry project at `root` and reads the artifact of `contract`, which must be
+/// defined in `<contract>.sol`.
+fn forge_build(
+    root: &Path,
+    contract: &str,
+    settings: Option<&CompilerSettings>,
+) -> Result<Artifact> {
+    let mut command = Command::new("forge");
+    command.arg("build").arg("--root").arg(root);
+    let out = match settings {
+        Some(settings) => {
+            let out = root.join(format!("out-{}", settings.slug()));
+            command.arg("--out").arg(&out);
+            if let Some(runs) = settings.optimizer_runs {
+                command
+                    .arg("--optimize")
+                    .arg("--optimizer-runs")
+                    .arg(runs.to_string());
+            }
+            if settings.via_ir {
+                command.arg("--via-ir");
+            }
+            out
+        }
+        None => root.join("out"),
+    };
+    run(command, "forge build")?;
+
+    let path = out
+        .join(format!("{contract}.sol"))
+        .join(format!("{contract}.json"));
+    if !path.is_file() {
+        bail!(
+            "forge build succeeded, but wrote no artifact to {}: is the `out` directory of the \
+             project configured elsewhere?",
+            path.display()
+        );
+    }
+    Artifact::read_target(&path)
+}
+
+/// Compiles the Solidity source at `source` with `solc`, with the optimizer enabled unless
+/// `settings` disable it, and reads the output of `contract`.
+fn solc(source: &Path, contract: &str, settings: Option<&CompilerSettings>) -> Result<Artifact> {
+    let mut command = Command::new("solc");
+    command
+        .args(["--base-path", "."])
+        .args(["--combined-json", "bin,bin-runtime,srcmap-runtime"]);
+    match settings {
+        Some(settings) => {
+            if let Some(runs) = settings.optimizer_runs {
+                command.args(["--optimize", "--optimize-runs", &runs.to_string()]);
+            }
+            if settings.via_ir {
+                command.arg("--via-ir");
+            }
+        }
+        None => {
+            command.arg("--optimize");
+        }
+    }
+    command.arg(source);
+    let output = run(command, "solc")?;
+    let json: Value =
+        serde_json::from_slice(&output.stdout).context("Invalid combined JSON output of solc")?;
+
+    let Some((_, compiled)) = json["contracts"].as_object().and_then(|contracts| {
+        contracts
+            .iter()
+            .find(|(name, _)| name.rsplit(':').next() == Some(contract))
+    }) else {
+        bail!(
+            "solc compiled no contract named {contract} from {}",
+            source.display()
+        );
+    };
+    let decode = |field: &str| -> Result<Vec<u8>> {
+        hex::decode(compiled[field].as_str().unwrap_or_default())
+            .with_context(|| format!("Invalid {field} in the output of solc"))
+    };
+    let code = decode("bin-runtime")?;
+    if code.is_empty() {
+        bail!("solc compiled no runtime bytecode for {contract}, is it abstract?");
+    }
+
+    Ok(Artifact {
+        code,
+        creation_code: Some(decode("bin")?),
+        compiler: json["version"].as_str().map(str::to_string),
+        settings: settings.copied(),
+        source_map: parse_source_map(compiled["srcmap-runtime"].as_str().unwrap_or_default())?,
+        functions: Vec::new(),
+    })
+}
+
+/// Compiles the Huff contract at `source` with `huffc`, returning its runtime bytecode.
+pub fn compile_huff(source: &Path) -> Result<Vec<u8>> {
+    let started = Instant::now();
+    let mut command = Command::new("huffc");
+    command.arg(source).arg("--bin-runtime");
+    let output = run(command, "huffc")?;
+    let code = hex::decode(String::from_utf8_lossy(&output.stdout).trim())
+        .context("Invalid runtime bytecode in the output of huffc")?;
+    if code.is_empty() {
+        bail!(
+            "huffc compiled no runtime bytecode from {}",
+            source.display()
+        );
+    }
+    tracing::info!(elapsed = ?started.elapsed(), "Compiled {} with huffc", source.display());
+    Ok(code)
+}
+
+/// Compiles the Yul object at `source` with `solc --strict-assembly`, returning the bytecode of the
+/// object. Unlike that of a Solidity contract, it's the code of the outer object, which deploys its
+/// runtime sub-object when executed as initcode (see [crate::harness::deployed_code]).
+pub fn compile_yul(source: &Path) -> Result<Vec<u8>> {
+    let started = Instant::now();
+    let mut command = Command::new("solc");
+    command.arg("--strict-assembly").arg("--bin").arg(source);
+    let output = run(command, "solc --strict-assembly")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let Some(bytecode) = stdout
+        .lines()
+        .skip_while(|line| !line.starts_with("Binary representation"))
+        .skip(1)
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+    else {
+        bail!(
+            "solc printed no bytecode for the Yul object {}",
+            source.display()
+        );
+    };
+    let code = hex::decode(bytecode).context("Invalid bytecode in the output of solc")?;
+    tracing::info!(elapsed = ?started.elapsed(), "Compiled the Yul object {}", source.display());
+    Ok(code)
+}
+
+/// Runs `command`, failing with its output if it can't be spawned or exits unsuccessfully.
+fn run(mut command: Command, name: &str) -> Result<Output> {
+    tracing::debug!(?command, "Running {name}");
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {name}, is it installed?"))?;
+    if !output.status.success() {
+        bail!(
+            "{name} failed ({}):\n{}{}",
+            output.status,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(output)
+}
@@ -1,6 +1,6 @@
 use revm::primitives::{address, Address};
 
-pub(crate) static STATEFUL_SPONGE_BYTECODE: &str = include_str!("../testdata/stateful_sponge");
+/// The hex-encoded runtime bytecode of the stateful sponge contract.
+pub static STATEFUL_SPONGE_BYTECODE: &str = include_str!("../testdata/stateful_sponge");
 /// The deployed address of the stateful sponge contract.
-pub(crate) static STATEFUL_SPONGE_ADDR: Address =
-    address!("dead00000000000000000000000000000000beef");
+pub static STATEFUL_SPONGE_ADDR: Address = address!("dead00000000000000000000000000000000beef");
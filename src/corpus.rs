@@ -0,0 +1,52 @@
+//! On-disk fuzzing corpora.
+
+use crate::{hashing::hash_input_tiny, permutation::RATE_BYTES};
+use alloy_primitives::hex;
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Loads every regular file in `dir` as a raw fuzz input, sorted by path.
+pub fn load_corpus(dir: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read corpus directory {}", dir.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let input = fs::read(&path)
+                .with_context(|| format!("Failed to read corpus entry {}", path.display()))?;
+            Ok((path, input))
+        })
+        .collect()
+}
+
+/// Writes `input` into the corpus directory `dir`, named after its Keccak256 digest so that
+/// duplicate inputs collapse into a single entry. Returns the path of the entry.
+pub fn write_corpus_entry(dir: &Path, input: &[u8]) -> Result<PathBuf> {
+    let mut digest = [0u8; 32];
+    hash_input_tiny(input, digest.as_mut());
+
+    let path = dir.join(hex::encode(digest));
+    fs::write(&path, input)
+        .with_context(|| format!("Failed to write corpus entry {}", path.display()))?;
+    Ok(path)
+}
+
+/// Returns the length bucket of an input: the number of full blocks it absorbs, and the class of its
+/// final block (empty, a single byte of padding, or anything else). Each bucket exercises a
+/// distinct path through the `10*1` padding logic.
+pub fn length_bucket(len: usize) -> (usize, u8) {
+    let class = match len % RATE_BYTES {
+        0 => 0,
+        rem if rem == RATE_BYTES - 1 => 1,
+        _ => 2,
+    };
+    (len / RATE_BYTES, class)
+}
@@ -0,0 +1,82 @@
+//! Program-counter coverage feedback for coverage-guided fuzzing.
+//!
+//! The [PcCoverage] inspector counts how often each bytecode PC executes while an input is hashed.
+//! Hit counts are bucketed AFL-style (1, 2-3, 4-7, 8-15, ...), so that inputs which drive a loop
+//! through a new number of iterations (i.e. absorb a new number of blocks) also count as novel.
+
+use crate::harness::HarnessDB;
+use revm::{
+    interpreter::{InstructionResult, Interpreter},
+    EVMData, Inspector,
+};
+use std::collections::HashSet;
+
+/// An [Inspector] that records the program counters executed during a transaction.
+#[derive(Debug, Default, Clone)]
+pub struct PcCoverage {
+    /// Hit counts for the current input, indexed by program counter.
+    hits: Vec<u32>,
+}
+
+impl PcCoverage {
+    /// Clears the hit counts recorded for the previous input.
+    pub fn reset(&mut self) {
+        self.hits.iter_mut().for_each(|count| *count = 0);
+    }
+
+    /// Returns the `(pc, bucket)` pairs hit by the current input.
+    pub fn features(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
+        self.hits
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(pc, &count)| (pc, hit_bucket(count)))
+    }
+}
+
+impl Inspector<HarnessDB> for PcCoverage {
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, HarnessDB>,
+    ) -> InstructionResult {
+        let pc = interp.program_counter();
+        if pc >= self.hits.len() {
+            self.hits.resize(pc + 1, 0);
+        }
+        self.hits[pc] = self.hits[pc].saturating_add(1);
+        InstructionResult::Continue
+    }
+}
+
+/// The cumulative coverage of a fuzzing task.
+#[derive(Debug, Default, Clone)]
+pub struct CoverageMap {
+    /// Every `(pc, bucket)` pair observed so far.
+    seen: HashSet<(usize, u8)>,
+}
+
+impl CoverageMap {
+    /// Merges the coverage of the last input into the map, returning `true` if it hit any
+    /// `(pc, bucket)` pair that had not been observed before.
+    pub fn merge(&mut self, coverage: &PcCoverage) -> bool {
+        coverage
+            .features()
+            .fold(false, |novel, feature| self.seen.insert(feature) | novel)
+    }
+
+    /// Returns the number of distinct `(pc, bucket)` pairs observed so far.
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Returns `true` if no coverage has been observed yet.
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+/// Buckets a hit count into a log2 class.
+fn hit_bucket(count: u32) -> u8 {
+    (u32::BITS - count.leading_zeros()) as u8
+}
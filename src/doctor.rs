@@ -0,0 +1,223 @@
+//! Preflight checks of the artifacts and the environment, as a one-shot sanity check before a long
+//! campaign is kicked off.
+//!
+//! Every check runs regardless of the outcome of the others, so a single run surfaces every
+//! problem: the contract's bytecode decodes (with the pinned code hash, if any), the contract
+//! deploys (with the fuzzer's addresses, deployment, specs and variant build), the absorb and
+//! squeeze calls reproduce the known-answer vectors, every backend agrees with the others (see
+//! [crate::selftest]), and the engine as a whole is unanimous on inputs around the rate. The checks
+//! that require the `evm` feature are skipped without it.
+
+use crate::{manifest::REVM_VERSION, permutation::RATE_BYTES, selftest::self_test, DiffFuzzer};
+use anyhow::{bail, Result};
+use std::fmt;
+
+/// The outcome of a preflight check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    /// The check passed, with a description of what was checked.
+    Passed(String),
+    /// The check failed, with the reason.
+    Failed(String),
+    /// The check could not run in this build, with the reason.
+    Skipped(String),
+}
+
+/// A preflight check and its outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    /// The name of the check, e.g. `bytecode`.
+    pub name: &'static str,
+    /// The outcome of the check.
+    pub outcome: CheckOutcome,
+}
+
+impl Check {
+    /// Returns `true` if the check failed.
+    pub fn failed(&self) -> bool {
+        matches!(self.outcome, CheckOutcome::Failed(_))
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (status, detail) = match &self.outcome {
+            CheckOutcome::Passed(detail) => ("ok", detail),
+            CheckOutcome::Failed(detail) => ("FAIL", detail),
+            CheckOutcome::Skipped(detail) => ("skip", detail),
+        };
+        write!(f, "{status:<4}  {:<10} {detail}", self.name)
+    }
+}
+
+/// Returns the versions of the tool and of revm, and the enabled cargo features.
+pub fn versions() -> String {
+    let features = [
+        ("evm", cfg!(feature = "evm")),
+        ("json", cfg!(feature = "json")),
+        ("trace", cfg!(feature = "trace")),
+        ("xkcp", cfg!(feature = "xkcp")),
+        ("keccak-asm", cfg!(feature = "keccak-asm")),
+        ("rpc", cfg!(feature = "rpc")),
+        ("tui", cfg!(feature = "tui")),
+        ("proptest", cfg!(feature = "proptest")),
+        ("arbitrary", cfg!(feature = "arbitrary")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect::<Vec<_>>();
+    format!(
+        "evm-keccak-fuzz {}, {}, features: {}",
+        env!("CARGO_PKG_VERSION"),
+        if cfg!(feature = "evm") {
+            format!("revm {REVM_VERSION}")
+        } else {
+            "without revm".to_string()
+        },
+        if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.join(", ")
+        }
+    )
+}
+
+/// Runs every preflight check against the contract and the backends of `fuzzer`.
+pub fn run_checks(fuzzer: &DiffFuzzer) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let mut check = |name, outcome: Result<String>| {
+        checks.push(Check {
+            name,
+            outcome: match outcome {
+                Ok(detail) => CheckOutcome::Passed(detail),
+                Err(e) => CheckOutcome::Failed(format!("{e:#}")),
+            },
+        });
+    };
+
+    #[cfg(feature = "evm")]
+    {
+        check("bytecode", evm::check_bytecode(fuzzer.expected_code_hash));
+        check("deploy", evm::check_deploy(fuzzer));
+        check("vectors", evm::check_vectors());
+    }
+    check(
+        "backends",
+        self_test(&fuzzer.backends).map(|()| format!("{} backends agree", fuzzer.backends.len())),
+    );
+    check("engine", check_engine(fuzzer));
+
+    #[cfg(not(feature = "evm"))]
+    for (i, name) in ["bytecode", "deploy", "vectors"].into_iter().enumerate() {
+        checks.insert(
+            i,
+            Check {
+                name,
+                outcome: CheckOutcome::Skipped("requires the `evm` feature".to_string()),
+            },
+        );
+    }
+    checks
+}
+
+/// Fails with the number of failed checks if any of `checks` failed.
+pub fn ensure_passed(checks: &[Check]) -> Result<()> {
+    let failed = checks.iter().filter(|check| check.failed()).count();
+    if failed > 0 {
+        bail!("{failed} of {} preflight checks failed", checks.len());
+    }
+    Ok(())
+}
+
+/// Diffs inputs around the rate with the fuzzer's engine, which must be unanimous on every one.
+fn check_engine(fuzzer: &DiffFuzzer) -> Result<String> {
+    let mut engine = fuzzer.engine(false)?;
+    let lengths = [0, 1, RATE_BYTES - 1, RATE_BYTES, RATE_BYTES + 1, 1000];
+    for len in lengths {
+        let input = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+        let verdict = match fuzzer.absorb_chunk_bytes {
+            Some(chunk_bytes) => engine.diff_in_chunks(&input, chunk_bytes)?,
+            None => engine.diff(&input)?,
+        };
+        if !verdict.is_unanimous() {
+            bail!(
+                "The engine disagrees on the {len} byte input ({})",
+                engine.describe(&verdict)
+            );
+        }
+    }
+    Ok(format!(
+        "{} implementations agree on {} inputs",
+        engine.names().len(),
+        lengths.len()
+    ))
+}
+
+#[cfg(feature = "evm")]
+mod evm {
+    use crate::{
+        harness::{contract_code, contract_code_hash, ensure_not_eof, hash_input_evm, init_evm},
+        selftest::KNOWN_ANSWERS,
+        DiffFuzzer,
+    };
+    use alloy_primitives::hex;
+    use anyhow::{bail, Result};
+
+    /// Checks that the contract's runtime bytecode decodes, isn't EOF, and has the `expected` code
+    /// hash, if pinned.
+    pub(super) fn check_bytecode(expected: Option<[u8; 32]>) -> Result<String> {
+        let code = contract_code()?;
+        if code.is_empty() {
+            bail!("The contract's bytecode is empty");
+        }
+        ensure_not_eof(code)?;
+        let code_hash = contract_code_hash()?;
+        if let Some(expected) = expected.filter(|expected| *expected != code_hash) {
+            bail!(
+                "The code hash is 0x{}, expected 0x{}",
+                hex::encode(code_hash),
+                hex::encode(expected)
+            );
+        }
+        Ok(format!(
+            "{} bytes, code hash 0x{}{}",
+            code.len(),
+            hex::encode(code_hash),
+            if expected.is_some() { " (pinned)" } else { "" }
+        ))
+    }
+
+    /// Checks that the contract deploys as configured for the campaign.
+    pub(super) fn check_deploy(fuzzer: &DiffFuzzer) -> Result<String> {
+        let engine = fuzzer.engine(false)?;
+        let evms = engine
+            .names()
+            .iter()
+            .filter(|name| name.starts_with("evm"))
+            .count();
+        Ok(format!(
+            "deployed in {evms} EVM{}",
+            if evms == 1 { "" } else { "s" }
+        ))
+    }
+
+    /// Checks that the absorb and squeeze calls reproduce the known-answer vectors.
+    pub(super) fn check_vectors() -> Result<String> {
+        let mut evm = init_evm()?;
+        for (input, expected) in KNOWN_ANSWERS {
+            let (digest, _) = hash_input_evm(&mut evm, input)?;
+            if digest != expected {
+                bail!(
+                    "The contract hashed the known-answer vector 0x{} to 0x{}, expected 0x{}",
+                    hex::encode(input),
+                    hex::encode(digest),
+                    hex::encode(expected)
+                );
+            }
+        }
+        Ok(format!(
+            "absorb and squeeze reproduce {} known answers",
+            KNOWN_ANSWERS.len()
+        ))
+    }
+}
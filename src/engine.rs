@@ -0,0 +1,961 @@
+//! The differential engine, which hashes an input with every implementation and arbitrates the
+//! resulting digests.
+
+use crate::{
+    arbitration::{arbitrate_contracts, describe, Verdict},
+    gas::CallGas,
+    hashing::{BackendFactory, HashBackend},
+    spec::EvmSpec,
+    sponge::SpongeModel,
+};
+use alloy_primitives::hex;
+use rand::Rng;
+use std::{borrow::Cow, fmt, str::FromStr};
+
+/// Splits `input` into the chunks that [DiffEngine::diff_in_chunks] absorbs in separate calls: a
+/// single chunk, unless `chunk_bytes` is set and the input is longer than it.
+pub fn absorb_chunks(input: &[u8], chunk_bytes: Option<usize>) -> Vec<&[u8]> {
+    match chunk_bytes {
+        Some(size) if input.len() > size => input.chunks(size).collect(),
+        _ => vec![input],
+    }
+}
+
+/// Returns the lengths of the chunks that [absorb_chunks] splits a `len` byte input into.
+pub fn absorb_chunk_lengths(len: usize, chunk_bytes: Option<usize>) -> Vec<usize> {
+    match chunk_bytes {
+        Some(size) if len > size => (0..len)
+            .step_by(size)
+            .map(|start| size.min(len - start))
+            .collect(),
+        _ => vec![len],
+    }
+}
+
+/// Interleaves empty and 1 byte chunks among the chunks of `lengths` at random. An empty chunk is
+/// inserted before every chunk, and after the last one, with a probability of 1/4 each, and the
+/// first byte of every chunk of 2 bytes or more is split off into a chunk of its own with the same
+/// probability. At least one empty chunk is inserted either way. The lengths still sum up to the
+/// same input length.
+pub fn interleave_tiny_chunks<R: Rng>(rng: &mut R, lengths: &[usize]) -> Vec<usize> {
+    let mut interleaved = Vec::with_capacity(lengths.len() * 3 + 1);
+    for &len in lengths {
+        if rng.gen_bool(0.25) {
+            interleaved.push(0);
+        }
+        if len >= 2 && rng.gen_bool(0.25) {
+            interleaved.extend([1, len - 1]);
+        } else {
+            interleaved.push(len);
+        }
+    }
+    if rng.gen_bool(0.25) || !interleaved.contains(&0) {
+        let at = rng.gen_range(0..=interleaved.len());
+        interleaved.insert(at, 0);
+    }
+    interleaved
+}
+
+/// Splits a `len` byte input at random into 1 to `max_chunks` chunks, returning their lengths.
+/// Every cut is drawn uniformly from the input, so that chunks of any length are absorbed,
+/// including empty ones and ones that end mid-block.
+pub fn random_split<R: Rng>(rng: &mut R, len: usize, max_chunks: usize) -> Vec<usize> {
+    let chunks = rng.gen_range(1..=max_chunks.max(1));
+    let mut cuts = (1..chunks)
+        .map(|_| rng.gen_range(0..=len))
+        .collect::<Vec<_>>();
+    cuts.sort_unstable();
+    cuts.push(len);
+    let mut start = 0;
+    cuts.into_iter()
+        .map(|cut| {
+            let chunk = cut - start;
+            start = cut;
+            chunk
+        })
+        .collect()
+}
+
+/// Splits `input` into consecutive chunks of the given `lengths`, which must sum up to its length.
+pub fn split_at_lengths<'a>(input: &'a [u8], lengths: &[usize]) -> Vec<&'a [u8]> {
+    let mut rest = input;
+    lengths
+        .iter()
+        .map(|&len| {
+            let (chunk, tail) = rest.split_at(len);
+            rest = tail;
+            chunk
+        })
+        .collect()
+}
+
+/// Returns the message whose Keccak256 digest the `StatefulSponge` contract squeezes after
+/// absorbing `chunks` in separate calls: every chunk but the last with the `10*1` padding applied,
+/// followed by the last chunk as is, as buffered by the [SpongeModel] of the contract.
+pub fn reference_message<'a>(chunks: &[&'a [u8]]) -> Cow<'a, [u8]> {
+    match chunks {
+        [] => Cow::Borrowed(&[]),
+        [chunk] => Cow::Borrowed(chunk),
+        chunks => Cow::Owned(SpongeModel::absorbing(chunks).into_message()),
+    }
+}
+
+/// The block and transaction environment that the contract calls execute in. The digest must not
+/// depend on any of it, which campaigns check by randomizing it (see
+/// [crate::DiffFuzzer::randomize_env]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvmEnv {
+    /// The block number.
+    pub block_number: u64,
+    /// The block timestamp.
+    pub timestamp: u64,
+    /// The block's beneficiary.
+    pub coinbase: [u8; 20],
+    /// The block's base fee.
+    pub basefee: u64,
+    /// The sender, and so the origin, of the transactions.
+    pub origin: [u8; 20],
+    /// The gas price of the transactions.
+    pub gas_price: u64,
+}
+
+impl EvmEnv {
+    /// Returns a random environment.
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        Self {
+            block_number: rng.gen(),
+            timestamp: rng.gen(),
+            coinbase: rng.gen(),
+            basefee: rng.gen(),
+            origin: rng.gen(),
+            gas_price: rng.gen(),
+        }
+    }
+}
+
+impl fmt::Display for EvmEnv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block {} at {}, coinbase 0x{}, basefee {}, origin 0x{}, gas price {}",
+            self.block_number,
+            self.timestamp,
+            hex::encode(self.coinbase),
+            self.basefee,
+            hex::encode(self.origin),
+            self.gas_price
+        )
+    }
+}
+
+/// How the contract diffed under the latest spec is deployed, so that the tools that re-execute its
+/// calls in an EVM of their own run them against the same deployment as the engine (see
+/// [DiffEngine::deployment]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deployment {
+    /// The initcode of the creation transaction that deploys the contract, if it isn't inserted as
+    /// runtime bytecode (see [DiffEngine::with_initcode]).
+    pub initcode: Option<Vec<u8>>,
+    /// The addresses that the contract is inserted at, the calls going to the first one, or none
+    /// for [crate::constants::STATEFUL_SPONGE_ADDR] (see [DiffEngine::with_addresses]).
+    pub addresses: Vec<[u8; 20]>,
+}
+
+impl Deployment {
+    /// Creates a fresh EVM under the latest spec with the contract deployed as described.
+    #[cfg(feature = "evm")]
+    pub fn init_evm(&self) -> anyhow::Result<revm::EVM<crate::harness::HarnessDB>> {
+        use revm::primitives::{Address, SpecId};
+
+        if let Some(initcode) = self.initcode.as_deref() {
+            return crate::harness::init_evm_with_initcode(SpecId::LATEST, initcode);
+        }
+        if self.addresses.is_empty() {
+            return crate::harness::init_evm();
+        }
+        let addresses = self
+            .addresses
+            .iter()
+            .map(|&address| Address::from(address))
+            .collect::<Vec<_>>();
+        crate::harness::init_evm_at(SpecId::LATEST, crate::harness::contract_code()?, &addresses)
+    }
+}
+
+/// A build of the `StatefulSponge` contract that is diffed against the others of a build matrix,
+/// e.g. one compiled by another solc version (see [DiffEngine::with_build_matrix]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatrixBuild {
+    /// The label of the build in reports, e.g. the compiler version that built it.
+    pub label: String,
+    /// The runtime bytecode of the build.
+    pub code: Vec<u8>,
+}
+
+/// The interface that a target contract computes Keccak256 digests through (see [Target]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TargetAbi {
+    /// The `absorb(bytes)` and `squeeze()` calls of the `StatefulSponge` contract.
+    Sponge,
+    /// A single `hash(bytes) returns (bytes32)` call, as of a one-shot wrapper of `LibKeccak`.
+    HashBytes,
+    /// A single call with the input as its calldata, returning the raw 32 byte digest, as of a
+    /// Huff build.
+    Raw,
+}
+
+impl TargetAbi {
+    /// Every interface.
+    pub const ALL: [Self; 3] = [Self::Sponge, Self::HashBytes, Self::Raw];
+
+    /// Returns the name of the interface on the command line, e.g. `hash-bytes`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sponge => "sponge",
+            Self::HashBytes => "hash-bytes",
+            Self::Raw => "raw",
+        }
+    }
+}
+
+impl fmt::Display for TargetAbi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TargetAbi {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> anyhow::Result<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        match Self::ALL.into_iter().find(|abi| abi.name() == name) {
+            Some(abi) => Ok(abi),
+            None => anyhow::bail!(
+                "Unknown target interface `{name}`, expected one of {}",
+                Self::ALL.map(Self::name).join(", ")
+            ),
+        }
+    }
+}
+
+/// Another contract of the `LibKeccak` family that is diffed against the `StatefulSponge` contract
+/// on every input, e.g. a one-shot wrapper or a Huff build (see [DiffEngine::with_targets]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    /// The label of the target in logs, e.g. the file that its bytecode was read from.
+    pub label: String,
+    /// The interface that the target hashes inputs through.
+    pub abi: TargetAbi,
+    /// The runtime bytecode of the target.
+    pub code: Vec<u8>,
+}
+
+/// The number of digest slots occupied by the `StatefulSponge` contract.
+const EVM_SLOTS: usize = if cfg!(feature = "evm") { 1 } else { 0 };
+
+/// The names of the digest slots of the sessions past the first (see [DiffEngine::with_addresses]).
+#[cfg(feature = "evm")]
+const SESSION_SLOTS: [&str; 7] = [
+    "evm#2", "evm#3", "evm#4", "evm#5", "evm#6", "evm#7", "evm#8",
+];
+
+/// The names of the digest slots of the targets (see [DiffEngine::with_targets]).
+#[cfg(feature = "evm")]
+const TARGET_SLOTS: [&str; 8] = [
+    "target#1", "target#2", "target#3", "target#4", "target#5", "target#6", "target#7", "target#8",
+];
+
+/// The names of the digest slots of the builds of the build matrix (see
+/// [DiffEngine::with_build_matrix]).
+#[cfg(feature = "evm")]
+const MATRIX_SLOTS: [&str; 8] = [
+    "build#1", "build#2", "build#3", "build#4", "build#5", "build#6", "build#7", "build#8",
+];
+
+/// A differential engine, holding one EVM instance with the `StatefulSponge` contract deployed
+/// (with the `evm` feature) plus an instance of every reference backend.
+///
+/// The digests of the last input are kept around for reporting. The EVM digest occupies the first
+/// slot when enabled, followed by the digests of the extra sessions, those of every extra spec,
+/// that of the variant build, those of the build matrix and those of the targets, if any, and then
+/// each backend's digest in registration order.
+pub struct DiffEngine {
+    /// The EVM that the `StatefulSponge` contract is deployed in.
+    #[cfg(feature = "evm")]
+    evm: revm::EVM<crate::harness::HarnessDB>,
+    /// How the contract is deployed in `evm`.
+    deployment: Deployment,
+    /// The coverage inspector and cumulative coverage, if coverage feedback is enabled.
+    #[cfg(feature = "evm")]
+    coverage: Option<(crate::coverage::PcCoverage, crate::coverage::CoverageMap)>,
+    /// Whether the message of every chunked input is also absorbed in a single call (see
+    /// [DiffEngine::with_chunking_invariance]).
+    #[cfg(feature = "evm")]
+    chunking_invariance: bool,
+    /// Whether the contract is squeezed a second time after every input (see
+    /// [DiffEngine::with_squeeze_idempotence]).
+    #[cfg(feature = "evm")]
+    squeeze_idempotence: bool,
+    /// The digest of the second squeeze of the last input, if the contract is squeezed twice.
+    resqueezed: Option<[u8; 32]>,
+    /// The addresses that the contract is deployed at, if it is hashed in interleaved sessions (see
+    /// [DiffEngine::with_addresses]).
+    #[cfg(feature = "evm")]
+    session_addresses: Vec<revm::primitives::Address>,
+    /// An EVM for every extra spec that the contract is diffed under (see [DiffEngine::with_specs]).
+    #[cfg(feature = "evm")]
+    spec_evms: Vec<(EvmSpec, revm::EVM<crate::harness::HarnessDB>)>,
+    /// The EVM that a variant build of the contract is deployed in, if any (see
+    /// [DiffEngine::with_variant]).
+    #[cfg(feature = "evm")]
+    variant_evm: Option<revm::EVM<crate::harness::HarnessDB>>,
+    /// The gas used by the variant's contract calls of the last input.
+    variant_gas: Option<CallGas>,
+    /// An EVM for every build of the build matrix (see
+    /// [DiffEngine::with_build_matrix]).
+    #[cfg(feature = "evm")]
+    matrix_evms: Vec<revm::EVM<crate::harness::HarnessDB>>,
+    /// The gas used by the contract calls of every build of the matrix of the last input.
+    matrix_gas: Vec<CallGas>,
+    /// An EVM for every target, with the interface of the target (see [DiffEngine::with_targets]).
+    #[cfg(feature = "evm")]
+    target_evms: Vec<(TargetAbi, revm::EVM<crate::harness::HarnessDB>)>,
+    /// The total gas used by the calls of every target of the last input.
+    target_gas: Vec<u64>,
+    /// The environment that the contract calls execute in, if it was set with
+    /// [DiffEngine::set_env].
+    env: Option<EvmEnv>,
+    /// The number of digest slots occupied by the contract, including those of the extra specs.
+    evm_slots: usize,
+    /// The reference backends.
+    backends: Vec<Box<dyn HashBackend>>,
+    /// The names of the implementations, indexed alike with `digests`.
+    names: Vec<&'static str>,
+    /// The digests of the last input.
+    digests: Vec<[u8; 32]>,
+    /// Whether the last input expanded coverage.
+    novel: bool,
+    /// The gas used by the contract calls of the last input.
+    gas: CallGas,
+}
+
+impl DiffEngine {
+    /// Creates a new [DiffEngine] with the given reference backends. If `coverage_guided` is set,
+    /// the EVM executes under a program-counter coverage inspector.
+    pub fn new(backends: &[BackendFactory], coverage_guided: bool) -> anyhow::Result<Self> {
+        if !cfg!(feature = "evm") && coverage_guided {
+            anyhow::bail!("Coverage-guided fuzzing requires the `evm` feature");
+        }
+
+        let backends = backends.iter().map(|factory| factory()).collect::<Vec<_>>();
+        let names = ["evm"][..EVM_SLOTS]
+            .iter()
+            .copied()
+            .chain(backends.iter().map(|backend| backend.name()))
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            #[cfg(feature = "evm")]
+            evm: crate::harness::init_evm()?,
+            deployment: Deployment::default(),
+            #[cfg(feature = "evm")]
+            coverage: coverage_guided.then(Default::default),
+            #[cfg(feature = "evm")]
+            chunking_invariance: false,
+            #[cfg(feature = "evm")]
+            squeeze_idempotence: false,
+            resqueezed: None,
+            #[cfg(feature = "evm")]
+            session_addresses: Vec::new(),
+            #[cfg(feature = "evm")]
+            spec_evms: Vec::new(),
+            #[cfg(feature = "evm")]
+            variant_evm: None,
+            variant_gas: None,
+            #[cfg(feature = "evm")]
+            matrix_evms: Vec::new(),
+            matrix_gas: Vec::new(),
+            #[cfg(feature = "evm")]
+            target_evms: Vec::new(),
+            target_gas: Vec::new(),
+            env: None,
+            evm_slots: EVM_SLOTS,
+            digests: vec![[0u8; 32]; names.len()],
+            backends,
+            names,
+            novel: false,
+            gas: CallGas::default(),
+        })
+    }
+
+    /// Deploys the contract diffed under the latest spec with a creation transaction that executes
+    /// `initcode`, so that its constructor runs as it would on chain, instead of inserting the
+    /// runtime bytecode. The extra specs and the variant build still insert theirs.
+    #[allow(unused_mut)]
+    pub fn with_initcode(mut self, initcode: &[u8]) -> anyhow::Result<Self> {
+        #[cfg(not(feature = "evm"))]
+        {
+            let _ = initcode;
+            anyhow::bail!(
+                "Deploying the contract with a creation transaction requires the `evm` feature"
+            );
+        }
+        #[cfg(feature = "evm")]
+        {
+            self.deployment.initcode = Some(initcode.to_vec());
+            self.evm = self.deployment.init_evm()?;
+            Ok(self)
+        }
+    }
+
+    /// Deploys the contract diffed under the latest spec at every one of `addresses`, instead of at
+    /// [crate::constants::STATEFUL_SPONGE_ADDR]. With several addresses, every input is hashed in
+    /// interleaved sessions of the contracts, all in the same EVM: every chunk is absorbed into
+    /// every contract in turn, and then every contract is squeezed in turn. The digest of the first
+    /// session takes the `evm` slot and the others `evm#2` and on, so that any state leaking
+    /// between the instances is reported as a dissenting digest. The gas is that of the first
+    /// session. Must be called before the extra specs and the variant build are added.
+    #[allow(unused_mut)]
+    pub fn with_addresses(mut self, addresses: &[[u8; 20]]) -> anyhow::Result<Self> {
+        if addresses.is_empty() {
+            return Ok(self);
+        }
+        #[cfg(not(feature = "evm"))]
+        anyhow::bail!("Configuring the contract address requires the `evm` feature");
+        #[cfg(feature = "evm")]
+        {
+            use revm::primitives::Address;
+
+            if addresses.len() > SESSION_SLOTS.len() + 1 {
+                anyhow::bail!(
+                    "At most {} contract addresses are supported, got {}",
+                    SESSION_SLOTS.len() + 1,
+                    addresses.len()
+                );
+            }
+            if let Some(address) = addresses
+                .iter()
+                .enumerate()
+                .find_map(|(i, address)| addresses[..i].contains(address).then_some(address))
+            {
+                anyhow::bail!("Duplicate contract address 0x{}", hex::encode(address));
+            }
+            if self.evm_slots > EVM_SLOTS {
+                anyhow::bail!(
+                    "The contract addresses must be set before the extra specs and the variant build"
+                );
+            }
+
+            self.deployment.addresses = addresses.to_vec();
+            self.evm = self.deployment.init_evm()?;
+            let addresses = addresses
+                .iter()
+                .map(|&address| Address::from(address))
+                .collect::<Vec<_>>();
+            for &name in &SESSION_SLOTS[..addresses.len() - 1] {
+                self.names.insert(self.evm_slots, name);
+                self.digests.push([0u8; 32]);
+                self.evm_slots += 1;
+            }
+            if addresses.len() > 1 {
+                self.session_addresses = addresses;
+            }
+            Ok(self)
+        }
+    }
+
+    /// Additionally absorbs the message that the backends hash for every input into the contract
+    /// diffed under the latest spec in a single call, after hashing the input as usual, and has the
+    /// digest take the `evm-1call` slot. Split across several calls, every chunk but the last is
+    /// padded by its own call, so both digests must match each other as well as the backends': a
+    /// digest that only the chunked calls get wrong points at state carried between calls, and one
+    /// that both get wrong at the padding or the permutation. Must be called after the contract
+    /// addresses are set and before the extra specs and the variant build are added.
+    #[allow(unused_mut)]
+    pub fn with_chunking_invariance(mut self) -> anyhow::Result<Self> {
+        #[cfg(not(feature = "evm"))]
+        anyhow::bail!("Checking chunking invariance requires the `evm` feature");
+        #[cfg(feature = "evm")]
+        {
+            if self.evm_slots > EVM_SLOTS + self.extra_sessions() || self.chunking_invariance {
+                anyhow::bail!(
+                    "Chunking invariance must be checked before the extra specs and the variant \
+                     build are added, and only once"
+                );
+            }
+            self.names.insert(self.evm_slots, "evm-1call");
+            self.digests.push([0u8; 32]);
+            self.chunking_invariance = true;
+            self.evm_slots += 1;
+            Ok(self)
+        }
+    }
+
+    /// Squeezes the contract diffed under the latest spec a second time after hashing every input,
+    /// recording the digest of the second squeeze (see [DiffEngine::resqueezed_digest]). As the
+    /// first squeeze resets the sponge, the second one must return [crate::sponge::RESET_DIGEST],
+    /// or else the squeeze path corrupted the state. With several contract addresses, only the
+    /// first one is squeezed again.
+    #[allow(unused_mut)]
+    pub fn with_squeeze_idempotence(mut self) -> anyhow::Result<Self> {
+        #[cfg(not(feature = "evm"))]
+        anyhow::bail!("Checking squeeze idempotence requires the `evm` feature");
+        #[cfg(feature = "evm")]
+        {
+            self.squeeze_idempotence = true;
+            Ok(self)
+        }
+    }
+
+    /// Additionally diffs the contract under every one of `specs`, each in its own EVM whose digest
+    /// is arbitrated like that of any other implementation. Gas and coverage are only measured under
+    /// the latest spec. Must be called before the variant build, the build matrix and the targets
+    /// are added.
+    #[allow(unused_mut)]
+    pub fn with_specs(mut self, specs: &[EvmSpec]) -> anyhow::Result<Self> {
+        if !cfg!(feature = "evm") && !specs.is_empty() {
+            anyhow::bail!("Diffing the contract under other EVM specs requires the `evm` feature");
+        }
+        #[cfg(feature = "evm")]
+        if !specs.is_empty()
+            && (self.variant_evm.is_some()
+                || !self.matrix_evms.is_empty()
+                || !self.target_evms.is_empty())
+        {
+            anyhow::bail!(
+                "The extra specs must be added before the variant build, the build matrix and the \
+                 targets"
+            );
+        }
+        #[cfg(feature = "evm")]
+        for &spec in specs {
+            let evm = crate::harness::init_evm_at(
+                spec.spec_id(),
+                crate::harness::contract_code()?,
+                &[self.primary_address()],
+            )?;
+            // The spec slots follow those of the sessions and the single call, and precede that of
+            // the variant build.
+            self.names
+                .insert(self.spec_slots() + self.spec_evms.len(), spec.slot_name());
+            self.digests.push([0u8; 32]);
+            self.spec_evms.push((spec, evm));
+            self.evm_slots += 1;
+        }
+        Ok(self)
+    }
+
+    /// Additionally diffs `code`, another build of the `StatefulSponge` contract (e.g. one that
+    /// copies memory with `MCOPY`), under the Cancun spec, in an EVM of its own whose digest takes
+    /// the `evm-variant` slot. The gas of the variant's calls is measured alongside that of the
+    /// contract (see [DiffEngine::variant_call_gas]). Must be called after the extra specs are
+    /// added and before the build matrix and the targets, and only once.
+    #[allow(unused_mut)]
+    pub fn with_variant(mut self, code: &[u8]) -> anyhow::Result<Self> {
+        #[cfg(not(feature = "evm"))]
+        {
+            let _ = code;
+            anyhow::bail!("Diffing a variant build of the contract requires the `evm` feature");
+        }
+        #[cfg(feature = "evm")]
+        {
+            if self.variant_evm.is_some()
+                || !self.matrix_evms.is_empty()
+                || !self.target_evms.is_empty()
+            {
+                anyhow::bail!(
+                    "The variant build must be added before the build matrix and the targets, and \
+                     only once"
+                );
+            }
+            let evm = crate::harness::init_evm_at(
+                revm::primitives::SpecId::CANCUN,
+                code,
+                &[self.primary_address()],
+            )?;
+            self.names.insert(self.evm_slots, "evm-variant");
+            self.digests.push([0u8; 32]);
+            self.variant_evm = Some(evm);
+            self.variant_gas = Some(CallGas::default());
+            self.evm_slots += 1;
+            Ok(self)
+        }
+    }
+
+    /// Additionally diffs every one of `builds`, other builds of the `StatefulSponge` contract such
+    /// as those of other solc versions, under the latest spec, each in an EVM of its own whose
+    /// digest takes the `build#1`, `build#2`, ... slot. The gas of every build's calls is measured
+    /// alongside that of the contract (see [DiffEngine::matrix_call_gas]). Must be called before
+    /// the targets are added, and only once.
+    #[allow(unused_mut)]
+    pub fn with_build_matrix(mut self, builds: &[MatrixBuild]) -> anyhow::Result<Self> {
+        if builds.is_empty() {
+            return Ok(self);
+        }
+        #[cfg(not(feature = "evm"))]
+        anyhow::bail!("Diffing a build matrix of the contract requires the `evm` feature");
+        #[cfg(feature = "evm")]
+        {
+            if !self.matrix_evms.is_empty() || !self.target_evms.is_empty() {
+                anyhow::bail!("The build matrix must be added before the targets, and only once");
+            }
+            if builds.len() > MATRIX_SLOTS.len() {
+                anyhow::bail!(
+                    "At most {} builds can be diffed in a build matrix, got {}",
+                    MATRIX_SLOTS.len(),
+                    builds.len()
+                );
+            }
+            for (build, name) in builds.iter().zip(MATRIX_SLOTS) {
+                let evm = crate::harness::init_evm_at(
+                    revm::primitives::SpecId::LATEST,
+                    &build.code,
+                    &[self.primary_address()],
+                )?;
+                self.names.insert(self.evm_slots, name);
+                self.digests.push([0u8; 32]);
+                self.matrix_evms.push(evm);
+                self.matrix_gas.push(CallGas::default());
+                self.evm_slots += 1;
+            }
+            Ok(self)
+        }
+    }
+
+    /// Additionally diffs every one of `targets`, other contracts of the `LibKeccak` family such as
+    /// a one-shot wrapper or a Huff build, under the latest spec, each in an EVM of its own whose
+    /// digest takes the `target#1`, `target#2`, ... slot. A target with a one-shot interface hashes
+    /// the whole message that the backends hash, in a single call. The gas of every target's calls
+    /// is measured alongside that of the contract (see [DiffEngine::target_gas]). Must be called
+    /// after the build matrix is added, and only once.
+    #[allow(unused_mut)]
+    pub fn with_targets(mut self, targets: &[Target]) -> anyhow::Result<Self> {
+        if targets.is_empty() {
+            return Ok(self);
+        }
+        #[cfg(not(feature = "evm"))]
+        anyhow::bail!("Diffing other target contracts requires the `evm` feature");
+        #[cfg(feature = "evm")]
+        {
+            if !self.target_evms.is_empty() {
+                anyhow::bail!("The targets must be added only once");
+            }
+            if targets.len() > TARGET_SLOTS.len() {
+                anyhow::bail!(
+                    "At most {} targets can be diffed, got {}",
+                    TARGET_SLOTS.len(),
+                    targets.len()
+                );
+            }
+            for (target, name) in targets.iter().zip(TARGET_SLOTS) {
+                let evm = crate::harness::init_evm_at(
+                    revm::primitives::SpecId::LATEST,
+                    &target.code,
+                    &[self.primary_address()],
+                )?;
+                self.names.insert(self.evm_slots, name);
+                self.digests.push([0u8; 32]);
+                self.target_evms.push((target.abi, evm));
+                self.target_gas.push(0);
+                self.evm_slots += 1;
+            }
+            Ok(self)
+        }
+    }
+
+    /// Executes the contract calls of the following inputs in `env`, in every EVM of the engine.
+    pub fn set_env(&mut self, env: EvmEnv) {
+        #[cfg(feature = "evm")]
+        {
+            let evms = std::iter::once(&mut self.evm)
+                .chain(self.spec_evms.iter_mut().map(|(_, evm)| evm))
+                .chain(self.variant_evm.as_mut())
+                .chain(self.matrix_evms.iter_mut())
+                .chain(self.target_evms.iter_mut().map(|(_, evm)| evm));
+            for evm in evms {
+                let block = &mut evm.env.block;
+                block.number = revm::primitives::U256::from(env.block_number);
+                block.timestamp = revm::primitives::U256::from(env.timestamp);
+                block.coinbase = env.coinbase.into();
+                block.basefee = revm::primitives::U256::from(env.basefee);
+                evm.env.tx.caller = env.origin.into();
+                evm.env.tx.gas_price = revm::primitives::U256::from(env.gas_price);
+            }
+        }
+        self.env = Some(env);
+    }
+
+    /// Returns the environment that the contract calls execute in, if it was set with
+    /// [DiffEngine::set_env], or `None` for the default environment.
+    pub fn env(&self) -> Option<EvmEnv> {
+        self.env
+    }
+
+    /// Returns the number of sessions past the first (see [DiffEngine::with_addresses]).
+    #[cfg(feature = "evm")]
+    fn extra_sessions(&self) -> usize {
+        self.session_addresses.len().saturating_sub(1)
+    }
+
+    /// Returns the index of the first digest slot of the extra specs, past those of the sessions and
+    /// the single call (see [DiffEngine::with_chunking_invariance]).
+    #[cfg(feature = "evm")]
+    fn spec_slots(&self) -> usize {
+        EVM_SLOTS + self.extra_sessions() + usize::from(self.chunking_invariance)
+    }
+
+    /// Returns the address that the contract diffed under the latest spec is called at first.
+    #[cfg(feature = "evm")]
+    fn primary_address(&self) -> revm::primitives::Address {
+        match self.evm.env.tx.transact_to {
+            revm::primitives::TransactTo::Call(address) => address,
+            revm::primitives::TransactTo::Create(_) => crate::constants::STATEFUL_SPONGE_ADDR,
+        }
+    }
+
+    /// Hashes `input` with every implementation and arbitrates the digests.
+    pub fn diff(&mut self, input: &[u8]) -> anyhow::Result<Verdict> {
+        self.diff_chunked(&[input])
+    }
+
+    /// Like [DiffEngine::diff], but splits `input` across absorb calls of at most `chunk_bytes`
+    /// bytes each.
+    pub fn diff_in_chunks(&mut self, input: &[u8], chunk_bytes: usize) -> anyhow::Result<Verdict> {
+        self.diff_chunked(&absorb_chunks(input, Some(chunk_bytes)))
+    }
+
+    /// Absorbs each of `chunks` into the `StatefulSponge` contract in its own transaction and
+    /// arbitrates the squeezed digest against the reference backends.
+    ///
+    /// Every `absorb` call pads its chunk to a whole number of blocks, so the backends hash every
+    /// chunk but the last with the `10*1` padding applied, followed by the last chunk as is.
+    pub fn diff_chunked(&mut self, chunks: &[&[u8]]) -> anyhow::Result<Verdict> {
+        if chunks.is_empty() {
+            anyhow::bail!("At least one chunk is required to diff");
+        }
+        let len = chunks.iter().map(|chunk| chunk.len()).sum::<usize>();
+        let _span = tracing::debug_span!("diff", len, chunks = chunks.len()).entered();
+        let message = reference_message(chunks);
+
+        #[cfg(feature = "evm")]
+        {
+            use crate::harness::{
+                hash_chunks_evm_inspected_metered, hash_chunks_evm_metered,
+                hash_sessions_evm_inspected_metered, hash_sessions_evm_metered,
+            };
+            use anyhow::Context;
+
+            self.novel = match self.coverage.as_mut() {
+                Some((coverage, coverage_map)) if !self.session_addresses.is_empty() => {
+                    coverage.reset();
+                    let sessions = hash_sessions_evm_inspected_metered(
+                        &mut self.evm,
+                        &self.session_addresses,
+                        chunks,
+                        coverage,
+                    )?;
+                    self.gas = sessions[0].1;
+                    for (digest, (session, _)) in self.digests.iter_mut().zip(sessions) {
+                        *digest = session;
+                    }
+                    coverage_map.merge(coverage)
+                }
+                None if !self.session_addresses.is_empty() => {
+                    let sessions =
+                        hash_sessions_evm_metered(&mut self.evm, &self.session_addresses, chunks)?;
+                    self.gas = sessions[0].1;
+                    for (digest, (session, _)) in self.digests.iter_mut().zip(sessions) {
+                        *digest = session;
+                    }
+                    false
+                }
+                Some((coverage, coverage_map)) => {
+                    coverage.reset();
+                    (self.digests[0], self.gas) =
+                        hash_chunks_evm_inspected_metered(&mut self.evm, chunks, coverage)?;
+                    coverage_map.merge(coverage)
+                }
+                None => {
+                    (self.digests[0], self.gas) = hash_chunks_evm_metered(&mut self.evm, chunks)?;
+                    false
+                }
+            };
+            if self.squeeze_idempotence {
+                self.resqueezed = Some(
+                    crate::harness::squeeze_evm(&mut self.evm)
+                        .context("Failed to squeeze the contract a second time")?,
+                );
+            }
+            let spec_slots = self.spec_slots();
+            if self.chunking_invariance {
+                self.digests[spec_slots - 1] =
+                    crate::harness::hash_chunks_evm(&mut self.evm, &[message.as_ref()])
+                        .context("Failed to absorb the message in a single call")?;
+            }
+            for ((spec, evm), digest) in self
+                .spec_evms
+                .iter_mut()
+                .zip(&mut self.digests[spec_slots..])
+            {
+                *digest = crate::harness::hash_chunks_evm(evm, chunks)
+                    .with_context(|| format!("Failed to hash the input under the {spec} spec"))?;
+            }
+            if let Some(evm) = self.variant_evm.as_mut() {
+                let (digest, gas) = hash_chunks_evm_metered(evm, chunks)
+                    .context("Failed to hash the input with the variant build")?;
+                self.digests[spec_slots + self.spec_evms.len()] = digest;
+                self.variant_gas = Some(gas);
+            }
+            let target_slots = self.evm_slots - self.target_evms.len();
+            let matrix_slots = target_slots - self.matrix_evms.len();
+            for (i, evm) in self.matrix_evms.iter_mut().enumerate() {
+                let (digest, gas) = hash_chunks_evm_metered(evm, chunks)
+                    .with_context(|| format!("Failed to hash the input with build #{}", i + 1))?;
+                self.digests[matrix_slots + i] = digest;
+                self.matrix_gas[i] = gas;
+            }
+            for (i, (abi, evm)) in self.target_evms.iter_mut().enumerate() {
+                let hashed = match abi {
+                    TargetAbi::Sponge => hash_chunks_evm_metered(evm, chunks)
+                        .map(|(digest, gas)| (digest, gas.total())),
+                    TargetAbi::HashBytes => crate::harness::call_digest_evm(
+                        evm,
+                        crate::harness::hash_calldata(&message),
+                    ),
+                    TargetAbi::Raw => crate::harness::call_digest_evm(evm, message.to_vec()),
+                };
+                (self.digests[target_slots + i], self.target_gas[i]) = hashed
+                    .with_context(|| format!("Failed to hash the input with target #{}", i + 1))?;
+            }
+        }
+
+        for (backend, digest) in self
+            .backends
+            .iter_mut()
+            .zip(self.digests[self.evm_slots..].iter_mut())
+        {
+            backend.hash(&message, digest.as_mut());
+        }
+
+        let verdict = arbitrate_contracts(&self.digests, self.evm_slots);
+        tracing::debug!(
+            unanimous = verdict.is_unanimous(),
+            gas = self.gas.total(),
+            "Diffed input"
+        );
+        Ok(verdict)
+    }
+
+    /// Returns the names of the implementations, indexed alike with [DiffEngine::digests].
+    pub fn names(&self) -> &[&'static str] {
+        &self.names
+    }
+
+    /// Returns the digests of the last input.
+    pub fn digests(&self) -> &[[u8; 32]] {
+        &self.digests
+    }
+
+    /// Returns how the contract diffed under the latest spec is deployed.
+    pub fn deployment(&self) -> &Deployment {
+        &self.deployment
+    }
+
+    /// Returns the number of leading [DiffEngine::digests] that builds of the contract produced,
+    /// the rest being those of the reference backends.
+    pub fn contract_slots(&self) -> usize {
+        self.evm_slots
+    }
+
+    /// Returns `true` if the last input expanded coverage. Always `false` unless the engine was
+    /// created with coverage feedback enabled.
+    pub fn novel_coverage(&self) -> bool {
+        self.novel
+    }
+
+    /// Returns the total gas used by the contract calls of the last input. Always zero without the
+    /// `evm` feature.
+    pub fn gas_used(&self) -> u64 {
+        self.gas.total()
+    }
+
+    /// Returns the gas used by the absorb and squeeze calls of the last input. Always zero without
+    /// the `evm` feature.
+    pub fn call_gas(&self) -> CallGas {
+        self.gas
+    }
+
+    /// Returns the gas used by the variant build's calls of the last input, if the engine diffs a
+    /// variant build of the contract.
+    pub fn variant_call_gas(&self) -> Option<CallGas> {
+        self.variant_gas
+    }
+
+    /// Returns the gas used by the calls of every build of the build matrix of the last input, in
+    /// the order of the builds.
+    pub fn matrix_call_gas(&self) -> &[CallGas] {
+        &self.matrix_gas
+    }
+
+    /// Returns the digest that the second squeeze of the last input returned, if the engine squeezes
+    /// the contract twice (see [DiffEngine::with_squeeze_idempotence]).
+    pub fn resqueezed_digest(&self) -> Option<[u8; 32]> {
+        self.resqueezed
+    }
+
+    /// Returns the total gas used by the calls of every target of the last input, in the order of
+    /// the targets.
+    pub fn target_gas(&self) -> &[u64] {
+        &self.target_gas
+    }
+
+    /// Describes the given verdict on the last input for a mismatch report.
+    pub fn describe(&self, verdict: &Verdict) -> String {
+        describe(verdict, &self.names, &self.digests)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn random_split_covers_the_input() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let input = (0..=255).collect::<Vec<u8>>();
+        for len in [0, 1, 135, 136, 137, 256] {
+            for _ in 0..64 {
+                let lengths = random_split(&mut rng, len, 8);
+                assert!((1..=8).contains(&lengths.len()), "{lengths:?}");
+                assert_eq!(lengths.iter().sum::<usize>(), len, "{lengths:?}");
+                let chunks = split_at_lengths(&input[..len], &lengths);
+                assert_eq!(chunks.concat(), &input[..len], "{lengths:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn interleaved_tiny_chunks_cover_the_input() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let input = (0..=255).collect::<Vec<u8>>();
+        for len in [0, 1, 2, 136, 256] {
+            for chunk_bytes in [None, Some(1), Some(64), Some(136)] {
+                let lengths = absorb_chunk_lengths(len, chunk_bytes);
+                assert_eq!(
+                    split_at_lengths(&input[..len], &lengths),
+                    absorb_chunks(&input[..len], chunk_bytes)
+                );
+                for _ in 0..16 {
+                    let interleaved = interleave_tiny_chunks(&mut rng, &lengths);
+                    assert!(interleaved.contains(&0), "{interleaved:?}");
+                    assert_eq!(interleaved.iter().sum::<usize>(), len, "{interleaved:?}");
+                    let chunks = split_at_lengths(&input[..len], &interleaved);
+                    assert_eq!(chunks.concat(), &input[..len], "{interleaved:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,495 @@
+//! Persistence of failing inputs, so that mismatches can be replayed and debugged offline.
+
+use crate::{
+    arbitration::{arbitrate_contracts, describe, Verdict},
+    engine::{absorb_chunks, reference_message, split_at_lengths, Deployment, DiffEngine, EvmEnv},
+    gas::GasDivergence,
+    hashing::{hash_input_tiny, BackendFactory},
+    permutation::RATE_BYTES,
+};
+use alloy_primitives::hex;
+use anyhow::{Context, Result};
+use std::{
+    fmt::{self, Display, Write},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A mismatch between the implementations, with everything needed to reproduce it.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    /// Where in the campaign the mismatch was found, e.g. `at iteration 42`.
+    pub context: String,
+    /// The input that the implementations disagree on.
+    pub input: Vec<u8>,
+    /// The size of the chunks that the input was split into across absorb calls, if any.
+    pub absorb_chunk_bytes: Option<usize>,
+    /// The lengths of the chunks that the input was split into across absorb calls, if the
+    /// campaign split it at random (see [crate::DiffFuzzer::random_chunks]). Takes precedence over
+    /// `absorb_chunk_bytes`.
+    pub chunk_lengths: Option<Vec<usize>>,
+    /// What the implementations disagree on.
+    pub kind: FailureKind,
+    /// The description of the verdict, naming the dissenting implementations, or of the gas
+    /// divergence.
+    pub description: String,
+    /// The name and digest of every implementation.
+    pub digests: Vec<(&'static str, [u8; 32])>,
+    /// The number of leading `digests` that builds of the contract produced, the rest, like the
+    /// cross-checks, being those of reference backends.
+    pub contract_slots: usize,
+    /// Whether the mismatch reproduced when the input was retried through fresh implementations.
+    pub stability: Stability,
+    /// The name and digest of every additional backend that the input was cross-confirmed with,
+    /// i.e. the backends that were not registered with the campaign.
+    pub cross_checks: Vec<(&'static str, [u8; 32])>,
+    /// The master seed of the campaign that generated the input, if it was randomly generated.
+    pub seed: Option<u64>,
+    /// The coordinates of the input in the campaign, if it was randomly generated, from which
+    /// [crate::DiffFuzzer::regenerate] reproduces it.
+    pub coordinates: Option<Coordinates>,
+    /// The EVM environment that the contract calls executed in, if the campaign randomized it
+    /// (see [crate::DiffFuzzer::randomize_env]).
+    pub env: Option<EvmEnv>,
+    /// How the contract was deployed, so that the failure's calls are re-executed against the same
+    /// deployment.
+    pub deployment: Deployment,
+}
+
+/// What a [Failure] is a mismatch of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The digests of the implementations disagree.
+    Digest,
+    /// The digests agree, but the `StatefulSponge` contract used different execution gas than on
+    /// an earlier input of the same length, hinting at data-dependent branching.
+    Gas(GasDivergence),
+    /// The digests agree, but squeezing the `StatefulSponge` contract a second time returned this
+    /// digest instead of [crate::sponge::RESET_DIGEST], so the squeeze corrupted its state.
+    Squeeze([u8; 32]),
+}
+
+impl Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Digest => write!(f, "digest"),
+            Self::Gas(_) => write!(f, "gas"),
+            Self::Squeeze(_) => write!(f, "squeeze"),
+        }
+    }
+}
+
+/// The coordinates of a generated input within a campaign. Together with the campaign's seed and
+/// parameters, they deterministically regenerate the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Coordinates {
+    /// The shard that generated the input, counted from 0.
+    pub shard: u64,
+    /// The iteration of the shard that generated the input, counted from 0.
+    pub iteration: u64,
+}
+
+/// The outcome of retrying a failing input through fresh implementations, which tells harness
+/// nondeterminism apart from genuine contract bugs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stability {
+    /// The input was not retried.
+    Unchecked,
+    /// The same digests were reproduced on every one of `retries` retries.
+    Stable {
+        /// The number of retries.
+        retries: usize,
+    },
+    /// The same digests were reproduced on only `reproduced` of `retries` retries.
+    Flaky {
+        /// The number of retries that reproduced the digests.
+        reproduced: usize,
+        /// The number of retries.
+        retries: usize,
+    },
+}
+
+impl Display for Stability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unchecked => write!(f, "unchecked"),
+            Self::Stable { retries } => write!(f, "stable over {retries} retries"),
+            Self::Flaky {
+                reproduced,
+                retries,
+            } => write!(f, "flaky, reproduced on {reproduced} of {retries} retries"),
+        }
+    }
+}
+
+impl Failure {
+    /// Captures the failing `verdict` on the last input diffed by `engine`.
+    pub fn new(
+        context: String,
+        input: &[u8],
+        absorb_chunk_bytes: Option<usize>,
+        engine: &DiffEngine,
+        verdict: &Verdict,
+    ) -> Self {
+        Self {
+            context,
+            input: input.to_vec(),
+            absorb_chunk_bytes,
+            chunk_lengths: None,
+            kind: FailureKind::Digest,
+            description: engine.describe(verdict),
+            digests: engine
+                .names()
+                .iter()
+                .copied()
+                .zip(engine.digests().iter().copied())
+                .collect(),
+            contract_slots: engine.contract_slots(),
+            stability: Stability::Unchecked,
+            cross_checks: Vec::new(),
+            seed: None,
+            coordinates: None,
+            env: engine.env(),
+            deployment: engine.deployment().clone(),
+        }
+    }
+
+    /// Captures the gas `divergence` of the last input diffed by `engine`, whose digests agree.
+    pub fn gas(
+        context: String,
+        input: &[u8],
+        absorb_chunk_bytes: Option<usize>,
+        engine: &DiffEngine,
+        divergence: GasDivergence,
+    ) -> Self {
+        Self {
+            kind: FailureKind::Gas(divergence),
+            description: divergence.to_string(),
+            ..Self::new(
+                context,
+                input,
+                absorb_chunk_bytes,
+                engine,
+                &Verdict::Unanimous,
+            )
+        }
+    }
+
+    /// Captures the second squeeze of the last input diffed by `engine`, whose digests agree, that
+    /// returned `digest` instead of the digest of the reset state.
+    pub fn squeeze(
+        context: String,
+        input: &[u8],
+        absorb_chunk_bytes: Option<usize>,
+        engine: &DiffEngine,
+        digest: [u8; 32],
+    ) -> Self {
+        Self {
+            kind: FailureKind::Squeeze(digest),
+            description: format!(
+                "the second squeeze returned 0x{} instead of the digest of the reset state",
+                hex::encode(digest)
+            ),
+            ..Self::new(
+                context,
+                input,
+                absorb_chunk_bytes,
+                engine,
+                &Verdict::Unanimous,
+            )
+        }
+    }
+
+    /// Returns the chunks of the input, in the order that they were absorbed in.
+    pub fn chunks(&self) -> Vec<&[u8]> {
+        match self.chunk_lengths.as_deref() {
+            Some(lengths) => split_at_lengths(&self.input, lengths),
+            None => absorb_chunks(&self.input, self.absorb_chunk_bytes),
+        }
+    }
+
+    /// Re-hashes the input with every backend in `backends` that did not take part in the
+    /// original diff, recording their digests in [Failure::cross_checks].
+    pub fn cross_check(&mut self, backends: &[BackendFactory]) {
+        let message = reference_message(&self.chunks()).into_owned();
+        for factory in backends {
+            let mut backend = factory();
+            let name = backend.name();
+            if self.digests.iter().any(|(other, _)| *other == name) {
+                continue;
+            }
+
+            let mut digest = [0u8; 32];
+            backend.hash(&message, &mut digest);
+            self.cross_checks.push((name, digest));
+        }
+    }
+
+    /// Describes the vote over the full matrix of digests, including the cross-checks.
+    pub fn describe_matrix(&self) -> String {
+        let (names, digests): (Vec<_>, Vec<_>) = self
+            .digests
+            .iter()
+            .chain(&self.cross_checks)
+            .copied()
+            .unzip();
+        describe(
+            &arbitrate_contracts(&digests, self.contract_slots),
+            &names,
+            &digests,
+        )
+    }
+
+    /// Writes the failure into a directory under `dir` that is named after the Keccak256 digest of
+    /// the input, containing the raw input (`input.bin`), the hex-encoded input (`input.hex`) and
+    /// the run metadata (`metadata.txt`), along with a Foundry regression test that replays the
+    /// input through the `StatefulSponge` contract (`LibKeccakRegression.t.sol`). With the `evm`
+    /// feature, the ABI-encoded calldata of the contract calls is written to `calldata.txt`, ready
+    /// for `cast`, the contract bytecode under test to `stateful_sponge.hex`, and its code hash and
+    /// the gas used by every call are added to the metadata. With the `trace`
+    /// feature, an EIP-3155 trace of the
+    /// contract calls is written to `trace.jsonl` as well. Returns the path of the directory.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        let path = dir.join(hex::encode(crate::permutation::keccak256(&self.input)));
+        fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create failure directory {}", path.display()))?;
+
+        fs::write(path.join("input.bin"), &self.input)?;
+        fs::write(path.join("input.hex"), hex::encode(&self.input))?;
+        fs::write(path.join("metadata.txt"), self.metadata()?)?;
+        fs::write(
+            path.join("LibKeccakRegression.t.sol"),
+            self.regression_test()?,
+        )?;
+        #[cfg(feature = "evm")]
+        {
+            fs::write(path.join("calldata.txt"), self.calldata()?)?;
+            fs::write(
+                path.join("stateful_sponge.hex"),
+                hex::encode(crate::harness::contract_code()?),
+            )?;
+        }
+        #[cfg(feature = "trace")]
+        self.write_trace(&path.join("trace.jsonl"))?;
+        Ok(path)
+    }
+
+    /// Re-executes the failing input in a fresh EVM, with the contract deployed as it was, and
+    /// writes its EIP-3155 trace to `path`.
+    #[cfg(feature = "trace")]
+    pub fn write_trace(&self, path: &Path) -> Result<()> {
+        let file = fs::File::create(path)
+            .with_context(|| format!("Failed to create trace file {}", path.display()))?;
+        let chunks = self.chunks();
+        crate::harness::trace_chunks_evm(
+            &mut self.deployment.init_evm()?,
+            &chunks,
+            Box::new(std::io::BufWriter::new(file)),
+        )?;
+        Ok(())
+    }
+
+    /// Renders the run metadata of the failure.
+    fn metadata(&self) -> Result<String> {
+        let mut metadata = String::new();
+        writeln!(metadata, "context: {}", self.context)?;
+        writeln!(metadata, "tool version: {}", env!("CARGO_PKG_VERSION"))?;
+        match self.seed {
+            Some(seed) => writeln!(metadata, "rng seed: {seed}")?,
+            None => writeln!(metadata, "rng seed: none")?,
+        }
+        if let Some(Coordinates { shard, iteration }) = self.coordinates {
+            writeln!(
+                metadata,
+                "coordinates: shard {shard}, iteration {iteration}"
+            )?;
+        }
+        if let Some(env) = self.env {
+            writeln!(metadata, "evm env: {env}")?;
+        }
+        writeln!(metadata, "input length: {}", self.input.len())?;
+        writeln!(
+            metadata,
+            "input length mod rate: {}",
+            self.input.len() % RATE_BYTES
+        )?;
+        match self.absorb_chunk_bytes {
+            Some(size) => writeln!(metadata, "absorb chunk bytes: {size}")?,
+            None => writeln!(metadata, "absorb chunk bytes: none")?,
+        }
+        if let Some(lengths) = self.chunk_lengths.as_ref() {
+            let lengths = lengths.iter().map(ToString::to_string).collect::<Vec<_>>();
+            writeln!(metadata, "absorb chunk lengths: {}", lengths.join(", "))?;
+        }
+        writeln!(metadata, "kind: {}", self.kind)?;
+        writeln!(metadata, "verdict: {}", self.description)?;
+        writeln!(metadata, "stability: {}", self.stability)?;
+        #[cfg(feature = "evm")]
+        {
+            use crate::{
+                gas::intrinsic_gas,
+                harness::{absorb_calldata, contract_code_hash, gas_chunks_evm},
+            };
+
+            writeln!(
+                metadata,
+                "contract code hash: 0x{}",
+                hex::encode(contract_code_hash()?)
+            )?;
+            let chunks = self.chunks();
+            let gas = gas_chunks_evm(&mut self.deployment.init_evm()?, &chunks)?;
+            let (squeeze, absorbs) = gas.split_last().expect("a squeeze call");
+            for (i, (gas, chunk)) in absorbs.iter().zip(&chunks).enumerate() {
+                let intrinsic = intrinsic_gas(&absorb_calldata(chunk));
+                writeln!(
+                    metadata,
+                    "gas used by absorb call {i}: {gas} ({intrinsic} intrinsic, {} execution)",
+                    gas.saturating_sub(intrinsic)
+                )?;
+            }
+            writeln!(metadata, "gas used by squeeze call: {squeeze}")?;
+        }
+        for (name, digest) in &self.digests {
+            writeln!(metadata, "digest {name}: 0x{}", hex::encode(digest))?;
+        }
+        if !self.cross_checks.is_empty() {
+            writeln!(
+                metadata,
+                "cross-checked verdict: {}",
+                self.describe_matrix()
+            )?;
+            for (name, digest) in &self.cross_checks {
+                writeln!(
+                    metadata,
+                    "cross-check digest {name}: 0x{}",
+                    hex::encode(digest)
+                )?;
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// Renders the calldata of the `absorb(bytes)` calls, in order, followed by the `squeeze()`
+    /// call, for replaying the failure against a deployed contract with `cast`.
+    #[cfg(feature = "evm")]
+    fn calldata(&self) -> Result<String> {
+        use crate::harness::{absorb_calldata, squeeze_calldata};
+
+        let mut calldata = String::new();
+        writeln!(
+            calldata,
+            "# Replay against a deployed `StatefulSponge` at $SPONGE with `cast send $SPONGE <data>`"
+        )?;
+        writeln!(
+            calldata,
+            "# for every absorb call in order, followed by `cast call $SPONGE <data>` to squeeze."
+        )?;
+        for chunk in self.chunks() {
+            writeln!(
+                calldata,
+                "absorb: 0x{}",
+                hex::encode(absorb_calldata(chunk))
+            )?;
+        }
+        writeln!(calldata, "squeeze: 0x{}", hex::encode(squeeze_calldata()))?;
+        Ok(calldata)
+    }
+
+    /// Renders a ready-to-run Foundry test that absorbs the failing input into the
+    /// `StatefulSponge` contract, split across the same calls as in the campaign, and asserts that
+    /// the squeezed digest matches the Keccak256 digest of the `tiny-keccak` reference.
+    fn regression_test(&self) -> Result<String> {
+        let chunks = self.chunks();
+        let mut expected = [0u8; 32];
+        hash_input_tiny(&reference_message(&chunks), &mut expected);
+        let name = hex::encode(&crate::permutation::keccak256(&self.input)[..4]);
+
+        let mut test = String::new();
+        writeln!(test, "// SPDX-License-Identifier: MIT")?;
+        writeln!(test, "pragma solidity 0.8.15;")?;
+        writeln!(test)?;
+        writeln!(test, "import {{ Test }} from \"forge-std/Test.sol\";")?;
+        writeln!(test)?;
+        writeln!(
+            test,
+            "import {{ StatefulSponge }} from \"contracts/StatefulSponge.sol\";"
+        )?;
+        writeln!(test)?;
+        writeln!(
+            test,
+            "/// @notice Regression test for the mismatch found {} by `evm-keccak-fuzz` {}.",
+            self.context,
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(test, "contract LibKeccakRegression_Test is Test {{")?;
+        writeln!(test, "    StatefulSponge internal sponge;")?;
+        writeln!(test)?;
+        writeln!(test, "    function setUp() public {{")?;
+        writeln!(test, "        sponge = new StatefulSponge();")?;
+        writeln!(test, "    }}")?;
+        writeln!(test)?;
+        writeln!(
+            test,
+            "    /// @notice Hashes the {} byte input ({} mod the rate) in {} absorb call(s).",
+            self.input.len(),
+            self.input.len() % RATE_BYTES,
+            chunks.len()
+        )?;
+        writeln!(
+            test,
+            "    function test_regression{name}_success() public {{"
+        )?;
+        for chunk in &chunks {
+            writeln!(
+                test,
+                "        sponge.absorb(hex\"{}\");",
+                hex::encode(chunk)
+            )?;
+        }
+        writeln!(
+            test,
+            "        assertEq(sponge.squeeze(), bytes32(0x{}));",
+            hex::encode(expected)
+        )?;
+        writeln!(test, "    }}")?;
+        writeln!(test, "}}")?;
+        Ok(test)
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            FailureKind::Digest => write!(f, "Hash mismatch {}", self.context)?,
+            FailureKind::Gas(_) => write!(f, "Gas divergence {}", self.context)?,
+            FailureKind::Squeeze(_) => write!(f, "Squeeze corruption {}", self.context)?,
+        }
+        if self.stability != Stability::Unchecked {
+            write!(f, ", {}", self.stability)?;
+        }
+        write!(f, " ({})", self.description)?;
+        if !self.cross_checks.is_empty() {
+            write!(f, " - cross-checked: ({})", self.describe_matrix())?;
+        }
+        write!(f, " - input: {}", hex::encode(&self.input))
+    }
+}
+
+/// Reads an input given either as the path of a file, or as a hex string. Files with a `.hex`
+/// extension, like the `input.hex` written by [Failure::write], are hex-decoded; any other file is
+/// read as raw bytes.
+pub fn read_input(arg: &str) -> Result<Vec<u8>> {
+    let path = Path::new(arg);
+    if !path.is_file() {
+        return hex::decode(arg.trim())
+            .with_context(|| format!("`{arg}` is neither a file nor a hex string"));
+    }
+
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if path.extension().is_some_and(|ext| ext == "hex") {
+        let text = String::from_utf8(bytes)?;
+        Ok(hex::decode(text.trim())?)
+    } else {
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,1488 @@
+use crate::{
+    arbitration::Verdict,
+    checkpoint::{Checkpoint, CheckpointWriter, ShardCheckpoint},
+    corpus::{length_bucket, load_corpus, write_corpus_entry},
+    engine::{
+        absorb_chunk_lengths, interleave_tiny_chunks, random_split, split_at_lengths, Deployment,
+        DiffEngine, EvmEnv, MatrixBuild, Target,
+    },
+    failure::{Coordinates, Failure, FailureKind, Stability},
+    gas::{execution_gas, GasAnomalyDetector, GasCsv, GasInvariance},
+    hashing::{available_backends, BackendFactory},
+    lengths::{LengthDistribution, Ramp},
+    metrics::{bind_metrics, metrics_task},
+    mutation::{mutate, Strategy},
+    patterns::fill_pattern,
+    payloads::{generate_payload, Payload},
+    report::{GasStats, Report},
+    reporter::Reporter,
+    selftest::{self_test, FILL_PATTERNS},
+    soak::{soak_task, Soak},
+    spec::EvmSpec,
+    sponge::RESET_DIGEST,
+    stats::{sample_timeline, LiveStats},
+};
+use anyhow::{anyhow, bail, Result};
+use indicatif::{MultiProgress, ProgressBar, ProgressState, ProgressStyle};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::{
+    collections::HashSet,
+    fmt::Write,
+    fs,
+    future::Future,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{sync::Notify, task::JoinSet};
+
+/// The log2 of the number of 32-bit words of the ChaCha20 stream reserved for each iteration, i.e.
+/// 16 GiB of randomness per input.
+const ITERATION_WORDS_LOG2: u32 = 32;
+
+/// The bit that sets the RNG streams of the EVM environments apart from those of the shards.
+const ENV_STREAM: u64 = 1 << 63;
+
+/// The bit that sets the RNG streams of the random absorb splits apart from those of the shards.
+const CHUNK_STREAM: u64 = 1 << 62;
+
+/// The interval between the status lines printed by the tasks of unbounded campaigns.
+const STATUS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The interval between the checkpoints written by every task.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The interval between the throughput samples of profiled campaigns.
+const TIMELINE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Seed inputs, labelled for mismatch reports.
+type LabelledInputs = Vec<(String, Vec<u8>)>;
+
+/// The differential fuzzer. Spawns `thread_count` tasks that each hash random inputs with the
+/// `StatefulSponge` contract and every registered [HashBackend], failing on the first mismatch.
+/// Without the `evm` feature, only the registered backends are diffed against each other.
+///
+/// By default, every backend compiled into the build is registered, so a mismatch can be attributed
+/// to either the contract or one of the native implementations.
+#[derive(Debug, Clone)]
+pub struct DiffFuzzer {
+    /// The number of concurrent fuzzing tasks to spawn.
+    pub thread_count: u64,
+    /// The number of shards that the campaign's iterations are divided into. Every shard draws from
+    /// its own ChaCha20 stream and mutates its own corpus, and the shards are spread across the
+    /// tasks, so the generated inputs depend on the shard count but not on `thread_count`.
+    pub shards: u64,
+    /// The total number of differential hashes to perform, split evenly across the shards. If zero
+    /// (and no `duration` is set), the campaign runs until interrupted, such as on soak boxes.
+    pub diff_count: u64,
+    /// If set, every task fuzzes for this long instead of for its share of `diff_count`, since
+    /// soak tests are usually budgeted in wall time rather than hash counts.
+    pub duration: Option<Duration>,
+    /// The exclusive upper bound on the length of generated inputs.
+    pub max_input_bytes: usize,
+    /// The inclusive lower bound on the length of randomly generated inputs.
+    pub min_input_bytes: usize,
+    /// An explicit set of lengths that randomly generated inputs are sampled from. If non-empty,
+    /// this overrides `min_input_bytes`, `max_input_bytes` and `length_distribution` for random
+    /// inputs.
+    pub input_sizes: Vec<usize>,
+    /// The reference backends that the contract is differentially tested against.
+    pub backends: Vec<BackendFactory>,
+    /// How new inputs are generated. Mutations draw from an in-memory corpus made up of the seed
+    /// inputs and every novel input (see `corpus_out`).
+    pub strategy: Strategy,
+    /// The kinds of payload generated by the [Strategy::Payloads] strategy. Every kind if empty.
+    pub payloads: Vec<Payload>,
+    /// How the lengths of randomly generated inputs are sampled.
+    pub length_distribution: LengthDistribution,
+    /// If set, the bound on input lengths ramps up to `max_input_bytes` over the campaign, rather
+    /// than being fixed from the first iteration. The ramp follows the iterations of every shard,
+    /// so it doesn't depend on the thread count.
+    pub ramp: Option<Ramp>,
+    /// The fraction of randomly generated inputs, between 0 and 1, that are filled with a
+    /// deterministic [crate::patterns::Pattern] rather than uniformly random bytes.
+    pub pattern_ratio: f64,
+    /// Whether to use program-counter coverage feedback from the EVM to decide which inputs are
+    /// novel, in addition to their length bucket. Requires the `evm` feature.
+    pub coverage_guided: bool,
+    /// The extra EVM specs to diff the contract under, besides revm's latest spec. Every spec's
+    /// digest must match the others (see [crate::spec]). Requires the `evm` feature.
+    pub specs: Vec<EvmSpec>,
+    /// The runtime bytecode of a variant build of the contract, e.g. one that copies memory with
+    /// `MCOPY`, to diff against the contract under the Cancun spec, recording its gas in
+    /// [Report::variant_gas]. Requires the `evm` feature.
+    pub variant_code: Option<Vec<u8>>,
+    /// Other builds of the contract, e.g. those of other solc versions, to diff against the
+    /// contract under the latest spec on every input, recording their gas in
+    /// [Report::matrix_gas]. Requires the `evm` feature.
+    pub build_matrix: Vec<MatrixBuild>,
+    /// Other contracts of the `LibKeccak` family, e.g. a one-shot `hash(bytes)` wrapper or a Huff
+    /// build, that every input is also run through, each of which must squeeze the same digest.
+    /// Requires the `evm` feature.
+    pub targets: Vec<Target>,
+    /// The initcode to deploy the contract with through a creation transaction, executing its
+    /// constructor, instead of inserting its runtime bytecode. Requires the `evm` feature.
+    pub initcode: Option<Vec<u8>>,
+    /// The addresses to deploy the contract at instead of
+    /// [crate::constants::STATEFUL_SPONGE_ADDR]. With several addresses, every input is hashed in
+    /// interleaved sessions of the contracts (see [DiffEngine::with_addresses]). Requires the `evm`
+    /// feature.
+    pub contract_addresses: Vec<[u8; 20]>,
+    /// The Keccak256 hash that the contract's runtime bytecode must have, if pinned, so that the
+    /// campaign can't silently run against a stale or wrong build. Requires the `evm` feature.
+    pub expected_code_hash: Option<[u8; 32]>,
+    /// Whether to execute the contract calls of every random iteration in a random block and
+    /// transaction environment (see [EvmEnv]), which the digest must not depend on. Requires the
+    /// `evm` feature.
+    pub randomize_env: bool,
+    /// If set, inputs longer than this many bytes are split into chunks that are each absorbed in
+    /// their own transaction, so inputs far larger than is comfortable for a single call's calldata
+    /// (e.g. 1–16 MiB) can be tested over long absorb sequences.
+    pub absorb_chunk_bytes: Option<usize>,
+    /// If set, every generated input is split at random into 1 to this many chunks, each absorbed
+    /// in its own transaction, to exercise the sponge across arbitrary call boundaries. The split
+    /// is drawn from an RNG stream of its own, so the inputs stay the same as without it, and the
+    /// lengths of the chunks are recorded in [Failure::chunk_lengths]. Seed inputs are absorbed as
+    /// with `absorb_chunk_bytes`, which can't be set alongside.
+    pub random_chunks: Option<usize>,
+    /// Whether to interleave empty and 1 byte absorb calls among the chunks of every generated input
+    /// (see [crate::engine::interleave_tiny_chunks]), whose padding the digest must account for.
+    /// Like `random_chunks`, the interleaving is drawn from an RNG stream of its own and recorded
+    /// in [Failure::chunk_lengths]. Requires inputs split with `random_chunks` or
+    /// `absorb_chunk_bytes`.
+    pub tiny_chunks: bool,
+    /// Whether to also absorb the message of every chunked input in a single call, whose digest
+    /// must match that of the chunked calls (see [DiffEngine::with_chunking_invariance]). Requires
+    /// inputs split with `random_chunks` or `absorb_chunk_bytes`, and the `evm` feature.
+    pub chunking_invariance: bool,
+    /// Whether to squeeze the contract a second time after every input, which must return the
+    /// digest of the reset state (see [DiffEngine::with_squeeze_idempotence]). Requires the `evm`
+    /// feature.
+    pub squeeze_idempotence: bool,
+    /// A directory of seed inputs that are replayed before any random inputs are generated. The
+    /// seeds are split across the shards, so that each one is replayed exactly once.
+    pub corpus_dir: Option<PathBuf>,
+    /// Additional in-memory seed inputs, labelled for mismatch reports, that are replayed alongside
+    /// the entries of `corpus_dir`.
+    pub seeds: Vec<(String, Vec<u8>)>,
+    /// A directory of previously-failing inputs, which every campaign replays before anything
+    /// else, so that known bugs fail fast against new bytecode. Every new failing input is added
+    /// to it. Missing directories are treated as empty.
+    pub regressions_dir: Option<PathBuf>,
+    /// A directory that novel inputs are written to, i.e. inputs that land in a new length bucket
+    /// or expand coverage, so that long campaigns build up a reusable corpus.
+    pub corpus_out: Option<PathBuf>,
+    /// A directory that failing inputs and their run metadata are written to (see
+    /// [Failure::write]), so that mismatches can be replayed later.
+    pub output_dir: Option<PathBuf>,
+    /// Whether to record every mismatch in the campaign's [Report] and keep going, rather than
+    /// failing on the first one, so that a single campaign characterizes the full extent of a bug.
+    pub keep_going: bool,
+    /// The number of times a failing input is retried through a fresh [DiffEngine] before it is
+    /// reported, to tell whether the mismatch is stable or flaky.
+    pub retries: usize,
+    /// The master seed of the campaign. Every shard draws from its own ChaCha20 stream of the seed,
+    /// so a campaign with the same seed and parameters generates exactly the same inputs. If unset,
+    /// a random seed is drawn when the campaign starts.
+    pub seed: Option<u64>,
+    /// Once set, e.g. from a signal handler, every task stops after its current iteration and the
+    /// campaign returns its [Report] so far.
+    pub stop: Arc<AtomicBool>,
+    /// Counters that every task updates as it goes, e.g. for watching the campaign while it runs.
+    pub stats: Arc<LiveStats>,
+    /// Whether to profile the campaign in its [Report]: the number of diffs and the gas of every
+    /// distinct input length, and the throughput over time, e.g. for HTML reports.
+    pub profile: bool,
+    /// If set, the gas of every diffed input is streamed to this CSV file.
+    pub gas_csv: Option<Arc<GasCsv>>,
+    /// Whether to check that every input of a length uses the same execution gas (see
+    /// [crate::gas::execution_gas]) as the first input of that length in its task, reporting any
+    /// divergence like a mismatch. Requires the `evm` feature.
+    pub gas_invariance: bool,
+    /// If set, every input whose execution gas is more than this many standard deviations from the
+    /// mean of its length bucket in its task is recorded in [Report::gas_anomalies] (see
+    /// [GasAnomalyDetector]). Requires the `evm` feature.
+    pub gas_anomaly_threshold: Option<f64>,
+    /// If set, the campaign's statistics are periodically dumped for soak machines (see [Soak]).
+    pub soak: Option<Soak>,
+    /// If set, the campaign's statistics are served as Prometheus metrics on this address while it
+    /// runs.
+    pub metrics_addr: Option<SocketAddr>,
+    /// The sinks that the events of the campaign are reported to, in order (see [Reporter]).
+    pub reporters: Vec<Arc<dyn Reporter>>,
+    /// A file that the progress of the campaign is periodically written to (see [Checkpoint]).
+    pub checkpoint: Option<PathBuf>,
+    /// A checkpoint to resume the campaign from, instead of starting from the first iteration.
+    /// The seed inputs are not replayed again, since they are part of the checkpointed corpora.
+    /// Refused for coverage-guided campaigns, whose coverage map isn't checkpointed.
+    pub resume: Option<Checkpoint>,
+}
+
+impl Default for DiffFuzzer {
+    fn default() -> Self {
+        Self {
+            thread_count: 4,
+            shards: 64,
+            diff_count: 100_000,
+            duration: None,
+            max_input_bytes: 100,
+            min_input_bytes: 0,
+            input_sizes: Vec::new(),
+            backends: available_backends(),
+            strategy: Strategy::default(),
+            payloads: Vec::new(),
+            length_distribution: LengthDistribution::default(),
+            ramp: None,
+            pattern_ratio: 0.0,
+            coverage_guided: false,
+            specs: Vec::new(),
+            variant_code: None,
+            build_matrix: Vec::new(),
+            targets: Vec::new(),
+            initcode: None,
+            contract_addresses: Vec::new(),
+            expected_code_hash: None,
+            randomize_env: false,
+            absorb_chunk_bytes: None,
+            random_chunks: None,
+            tiny_chunks: false,
+            chunking_invariance: false,
+            squeeze_idempotence: false,
+            corpus_dir: None,
+            seeds: Vec::new(),
+            regressions_dir: None,
+            corpus_out: None,
+            output_dir: None,
+            keep_going: false,
+            retries: 3,
+            seed: None,
+            stop: Arc::default(),
+            stats: Arc::default(),
+            profile: false,
+            gas_csv: None,
+            gas_invariance: false,
+            gas_anomaly_threshold: None,
+            soak: None,
+            metrics_addr: None,
+            reporters: Vec::new(),
+            checkpoint: None,
+            resume: None,
+        }
+    }
+}
+
+impl DiffFuzzer {
+    /// Creates a new [DiffFuzzer] with the given campaign parameters.
+    pub fn new(thread_count: u64, diff_count: u64, max_input_bytes: usize) -> Self {
+        Self {
+            thread_count,
+            diff_count,
+            max_input_bytes,
+            ..Default::default()
+        }
+    }
+
+    /// Registers an additional reference backend with the fuzzer.
+    pub fn register_backend(&mut self, backend: BackendFactory) -> &mut Self {
+        self.backends.push(backend);
+        self
+    }
+
+    /// Runs the fuzzing campaign to completion, adding a progress bar per task to
+    /// `progress_group`. Returns an error if any task fails to execute, or observes a mismatch
+    /// outside of keep-going mode.
+    pub async fn run(&self, progress_group: &MultiProgress) -> Result<Report> {
+        self.reported(self.run_campaign(progress_group)).await
+    }
+
+    /// Notifies the fuzzer's reporters of the start of `campaign`, runs it and notifies them of
+    /// its outcome. The campaign's error takes precedence over those of the reporters.
+    async fn reported(&self, campaign: impl Future<Output = Result<Report>>) -> Result<Report> {
+        for reporter in &self.reporters {
+            reporter.on_start(self)?;
+        }
+        let outcome = campaign.await;
+        let mut finished = Ok(());
+        for reporter in &self.reporters {
+            let result = reporter.on_finish(self, &outcome);
+            finished = finished.and(result);
+        }
+        let report = outcome?;
+        finished?;
+        Ok(report)
+    }
+
+    /// Runs the fuzzing campaign, without notifying the reporters (see [DiffFuzzer::run]).
+    async fn run_campaign(&self, progress_group: &MultiProgress) -> Result<Report> {
+        let progress_style = match self.duration {
+            Some(_) => timed_progress_style()?,
+            None if self.is_unbounded() => unbounded_progress_style()?,
+            None => progress_style()?,
+        };
+
+        if self.input_sizes.is_empty() && self.min_input_bytes >= self.max_input_bytes {
+            bail!(
+                "The minimum input length ({}) must be below the maximum input length ({})",
+                self.min_input_bytes,
+                self.max_input_bytes
+            );
+        }
+
+        if !(0.0..=1.0).contains(&self.pattern_ratio) {
+            bail!(
+                "The pattern ratio ({}) must be between 0 and 1",
+                self.pattern_ratio
+            );
+        }
+
+        if self.absorb_chunk_bytes == Some(0) {
+            bail!("The absorb chunk size must be non-zero");
+        }
+        if self.tiny_chunks && self.random_chunks.is_none() && self.absorb_chunk_bytes.is_none() {
+            bail!(
+                "Interleaving tiny chunks requires inputs split across absorb calls, at random or \
+                 into chunks of a fixed size"
+            );
+        }
+        if self.tiny_chunks && (self.gas_invariance || self.gas_anomaly_threshold.is_some()) {
+            bail!(
+                "The gas of inputs with tiny chunks depends on the interleaving, so it can't be \
+                 checked for invariance or anomalies"
+            );
+        }
+        if self.chunking_invariance
+            && self.random_chunks.is_none()
+            && self.absorb_chunk_bytes.is_none()
+        {
+            bail!(
+                "Checking chunking invariance requires inputs split across absorb calls, at \
+                 random or into chunks of a fixed size"
+            );
+        }
+        if let Some(max_chunks) = self.random_chunks {
+            if max_chunks == 0 {
+                bail!("The maximum number of random chunks must be non-zero");
+            }
+            if self.absorb_chunk_bytes.is_some() {
+                bail!("Inputs can't be split both at random and into chunks of a fixed size");
+            }
+            if self.gas_invariance || self.gas_anomaly_threshold.is_some() {
+                bail!(
+                    "The gas of inputs split at random depends on the split, so it can't be \
+                     checked for invariance or anomalies"
+                );
+            }
+        }
+
+        self.check_evm_options()?;
+
+        if self.duration == Some(Duration::ZERO) {
+            bail!("The campaign duration must be non-zero");
+        }
+        if let Some(duration) = self.duration {
+            if Instant::now().checked_add(duration).is_none() {
+                bail!(
+                    "The campaign duration of {}s is too long",
+                    duration.as_secs()
+                );
+            }
+        }
+
+        if let Some(ramp) = self.ramp {
+            if ramp.diffs == Some(0) {
+                bail!("The ramp must span a non-zero number of diffs");
+            }
+            if ramp.diffs.is_none() && (self.duration.is_some() || self.is_unbounded()) {
+                bail!("A ramp over a time-limited or unbounded campaign requires a diff count");
+            }
+        }
+
+        if self
+            .soak
+            .as_ref()
+            .is_some_and(|soak| soak.interval.is_zero())
+        {
+            bail!("The soak statistics interval must be non-zero");
+        }
+
+        if self.thread_count == 0 || self.thread_count > self.shards {
+            bail!(
+                "The thread count ({}) must be between 1 and the shard count ({})",
+                self.thread_count,
+                self.shards
+            );
+        }
+
+        // Make sure the reference backends agree with each other before trusting them.
+        self_test(&self.backends)?;
+
+        if let Some(dir) = self.corpus_out.as_ref() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let clock = Instant::now();
+        let mut config = self.clone();
+        let (seeds, resumed) = match self.resume.as_ref() {
+            Some(checkpoint) => {
+                if self.coverage_guided {
+                    bail!(
+                        "A coverage-guided campaign can't be resumed, since checkpoints don't record \
+                         the coverage map that decides which inputs it keeps"
+                    );
+                }
+                if checkpoint.shards.len() as u64 != self.shards {
+                    bail!(
+                        "The checkpoint has {} shards, but the shard count is {}",
+                        checkpoint.shards.len(),
+                        self.shards
+                    );
+                }
+                if self.seed.is_some_and(|seed| seed != checkpoint.seed) {
+                    bail!("The seed does not match the seed of the checkpoint");
+                }
+                config.seed = Some(checkpoint.seed);
+                let seeds = vec![Vec::new(); self.shards as usize];
+                (seeds, checkpoint.shards.clone())
+            }
+            None => {
+                config.seed = Some(self.seed.unwrap_or_else(rand::random));
+                let resumed = vec![ShardCheckpoint::default(); self.shards as usize];
+                (self.deal_seeds()?, resumed)
+            }
+        };
+        let checkpoints = self.checkpoint.clone().map(|path| {
+            let checkpoint = Checkpoint {
+                seed: config.seed.unwrap_or_default(),
+                shards: resumed.clone(),
+            };
+            Arc::new(CheckpointWriter::new(path, checkpoint))
+        });
+
+        let soak_done = Arc::new(Notify::new());
+        let soak = self
+            .soak
+            .clone()
+            .map(|soak| tokio::spawn(soak_task(soak, self.stats.clone(), soak_done.clone())));
+        let metrics_done = Arc::new(Notify::new());
+        let metrics = match self.metrics_addr {
+            Some(addr) => Some(tokio::spawn(metrics_task(
+                bind_metrics(addr).await?,
+                self.stats.clone(),
+                metrics_done.clone(),
+            ))),
+            None => None,
+        };
+        let timeline_done = Arc::new(Notify::new());
+        let timeline = self.profile.then(|| {
+            tokio::spawn(sample_timeline(
+                self.stats.clone(),
+                TIMELINE_INTERVAL,
+                timeline_done.clone(),
+            ))
+        });
+
+        let mut report = config.replay_regressions()?;
+
+        // Spread the shards across the tasks round-robin. Since every shard is self-contained, this
+        // only affects the scheduling, and not which inputs are generated.
+        let mut assignments = vec![Vec::new(); self.thread_count as usize];
+        for ((shard, seeds), resumed) in (0..self.shards).zip(seeds).zip(resumed) {
+            assignments[(shard % self.thread_count) as usize].push((shard, seeds, resumed));
+        }
+
+        // Time-limited campaigns share a single deadline, however the tasks are scheduled.
+        let started = self.duration.map(|_| Instant::now());
+        let mut join_set = JoinSet::new();
+        for (i, shards) in (0..self.thread_count).zip(assignments) {
+            let pb = match self.duration {
+                Some(duration) => ProgressBar::new(duration.as_secs().max(1)),
+                None if self.is_unbounded() => ProgressBar::new_spinner(),
+                None => ProgressBar::new(
+                    shards
+                        .iter()
+                        .map(|(shard, seeds, resumed)| {
+                            self.shard_quota(*shard).saturating_sub(resumed.iteration)
+                                + seeds.len() as u64
+                        })
+                        .sum(),
+                ),
+            };
+            let pb = progress_group.add(pb);
+            pb.set_style(progress_style.clone());
+            pb.set_message(format!("Thread {}", i + 1));
+
+            join_set.spawn(fuzz_task(
+                config.clone(),
+                pb,
+                i + 1,
+                started,
+                shards,
+                checkpoints.clone(),
+            ));
+        }
+
+        while let Some(res) = join_set.join_next().await {
+            report.merge(res??);
+        }
+        report.threads.sort_by_key(|stats| stats.thread);
+        report.elapsed = clock.elapsed();
+        if let Some(csv) = self.gas_csv.as_ref() {
+            csv.flush()?;
+        }
+        report.interrupted = self.stop.load(Ordering::Relaxed);
+
+        if let Some(soak) = soak {
+            soak_done.notify_one();
+            soak.await??;
+        }
+        if let Some(metrics) = metrics {
+            metrics_done.notify_one();
+            metrics.await?;
+        }
+        if let Some(timeline) = timeline {
+            timeline_done.notify_one();
+            report.timeline = timeline.await?;
+        }
+
+        Ok(report)
+    }
+
+    /// Replays every input in `regressions_dir`, if set and present, returning an error on the
+    /// first mismatch outside of keep-going mode. The replays are counted in [Report::regressions]
+    /// rather than as diffs, so that they don't inflate the campaign's diff count.
+    fn replay_regressions(&self) -> Result<Report> {
+        let mut report = Report::default();
+        let Some(dir) = self.regressions_dir.as_ref().filter(|dir| dir.is_dir()) else {
+            return Ok(report);
+        };
+
+        // The inputs are already in the directory, so don't add them again.
+        let config = Self {
+            regressions_dir: None,
+            ..self.clone()
+        };
+        let mut engine = self.engine(false)?;
+        for (path, input) in load_corpus(dir)? {
+            let verdict = diff_input(&mut engine, &input, self.absorb_chunk_bytes)?;
+            report.regressions += 1;
+            if !verdict.is_unanimous() {
+                let context = format!("on regression input {}", path.display());
+                let failure =
+                    Failure::new(context, &input, self.absorb_chunk_bytes, &engine, &verdict);
+                record_failure(&config, failure, &mut report)?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Returns how the fuzzer's engines deploy the contract diffed under the latest spec, for the
+    /// tools that re-execute its calls outside of an engine.
+    pub fn deployment(&self) -> Deployment {
+        Deployment {
+            initcode: self.initcode.clone(),
+            addresses: self.contract_addresses.clone(),
+        }
+    }
+
+    /// Creates a [DiffEngine] with the fuzzer's backends, addresses, deployment, EVM specs and
+    /// variant build, with coverage feedback if `coverage_guided` is set.
+    pub fn engine(&self, coverage_guided: bool) -> Result<DiffEngine> {
+        let mut engine = DiffEngine::new(&self.backends, coverage_guided)?
+            .with_addresses(&self.contract_addresses)?;
+        if let Some(initcode) = self.initcode.as_deref() {
+            if !self.contract_addresses.is_empty() {
+                bail!("A contract deployed with a creation transaction can't be given addresses");
+            }
+            engine = engine.with_initcode(initcode)?;
+        }
+        if self.chunking_invariance {
+            engine = engine.with_chunking_invariance()?;
+        }
+        if self.squeeze_idempotence {
+            engine = engine.with_squeeze_idempotence()?;
+        }
+        let engine = engine.with_specs(&self.specs)?;
+        let engine = match self.variant_code.as_deref() {
+            Some(code) => engine.with_variant(code)?,
+            None => engine,
+        };
+        engine
+            .with_build_matrix(&self.build_matrix)?
+            .with_targets(&self.targets)
+    }
+
+    /// Fails if the campaign checks gas invariance, detects gas anomalies, randomizes the EVM
+    /// environment or pins the contract's code hash without the `evm` feature, detects gas
+    /// anomalies with a non-positive threshold, or if the contract's code hash isn't the pinned one.
+    fn check_evm_options(&self) -> Result<()> {
+        if let Some(expected) = self.expected_code_hash {
+            #[cfg(not(feature = "evm"))]
+            {
+                let _ = expected;
+                bail!("Pinning the contract's code hash requires the `evm` feature");
+            }
+            #[cfg(feature = "evm")]
+            {
+                let actual = crate::harness::contract_code_hash()?;
+                if actual != expected {
+                    bail!(
+                        "The contract's code hash is 0x{}, expected 0x{}: its bytecode is not the \
+                         pinned build",
+                        alloy_primitives::hex::encode(actual),
+                        alloy_primitives::hex::encode(expected)
+                    );
+                }
+            }
+        }
+        if self.randomize_env && !cfg!(feature = "evm") {
+            bail!("Randomizing the EVM environment requires the `evm` feature");
+        }
+        if self.gas_invariance && !cfg!(feature = "evm") {
+            bail!("The gas invariance check requires the `evm` feature");
+        }
+        if let Some(threshold) = self.gas_anomaly_threshold {
+            if !cfg!(feature = "evm") {
+                bail!("Gas anomaly detection requires the `evm` feature");
+            }
+            if threshold <= 0.0 {
+                bail!("The gas anomaly threshold ({threshold}) must be positive");
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the campaign runs until interrupted, rather than for a number of diffs or
+    /// a duration.
+    pub fn is_unbounded(&self) -> bool {
+        self.diff_count == 0 && self.duration.is_none()
+    }
+
+    /// Returns the lengths of the absorb chunks of the `len` byte input of iteration `iteration` of
+    /// shard `shard`, if the fuzzer splits inputs at random (see [DiffFuzzer::random_chunks]) or
+    /// interleaves tiny chunks (see [DiffFuzzer::tiny_chunks]).
+    fn chunk_lengths(
+        &self,
+        seed: u64,
+        shard: u64,
+        iteration: u64,
+        len: usize,
+    ) -> Option<Vec<usize>> {
+        let mut rng = chunk_rng(seed, shard, iteration);
+        let lengths = match self.random_chunks {
+            Some(max_chunks) => random_split(&mut rng, len, max_chunks),
+            None if self.tiny_chunks => absorb_chunk_lengths(len, self.absorb_chunk_bytes),
+            None => return None,
+        };
+        if self.tiny_chunks {
+            Some(interleave_tiny_chunks(&mut rng, &lengths))
+        } else {
+            Some(lengths)
+        }
+    }
+
+    /// Returns the number of iterations that shard `shard` runs: its even share of `diff_count`,
+    /// with the remainder spread over the first shards.
+    fn shard_quota(&self, shard: u64) -> u64 {
+        self.diff_count / self.shards + u64::from(shard < self.diff_count % self.shards)
+    }
+
+    /// Returns the exclusive upper bound on the length of the inputs generated at iteration
+    /// `iteration` of every shard, following the fuzzer's [Ramp], if set.
+    fn max_input_bytes_at(&self, iteration: u64) -> usize {
+        let Some(ramp) = self.ramp else {
+            return self.max_input_bytes;
+        };
+
+        let span = (ramp.diffs.unwrap_or(self.diff_count) / self.shards).max(1);
+        ramp.max_input_bytes(iteration as f64 / span as f64, self.max_input_bytes)
+            .max(self.min_input_bytes + 1)
+    }
+
+    /// Generates the input of iteration `iteration` of shard `shard` (counted from 0) with the
+    /// fuzzer's seed and parameters as if the shard's corpus were empty, so that it is a function of
+    /// its coordinates alone under every strategy, e.g. for feeding an identical input stream to
+    /// several builds of the contract (see [crate::compare]).
+    pub fn generate_fresh(&self, shard: u64, iteration: u64) -> Result<Vec<u8>> {
+        let Some(seed) = self.seed else {
+            bail!("Generating an input requires the seed of the campaign");
+        };
+        let mut input = Vec::new();
+        generate_input(
+            self,
+            iteration,
+            &mut iteration_rng(seed, shard, iteration),
+            &[],
+            &mut input,
+        );
+        Ok(input)
+    }
+
+    /// Regenerates the input of iteration `iteration` of shard `shard` (counted from 0) of the
+    /// campaign with the fuzzer's seed and parameters, e.g. from the coordinates of a
+    /// [Failure]. Under the `random` and `payloads` strategies, inputs are a function of their
+    /// coordinates alone; otherwise, the shard's corpus is rebuilt by replaying every prior
+    /// iteration (and diffing it, if coverage-guided). The thread count doesn't matter.
+    pub fn regenerate(&self, shard: u64, iteration: u64) -> Result<Vec<u8>> {
+        let Some(seed) = self.seed else {
+            bail!("Regenerating an input requires the seed of the campaign");
+        };
+        if shard >= self.shards {
+            bail!(
+                "The shard ({shard}) must be below the shard count ({})",
+                self.shards
+            );
+        }
+
+        let mut engine = self
+            .coverage_guided
+            .then(|| self.engine(true))
+            .transpose()?;
+        let mut corpus = Vec::new();
+        let mut input = Vec::new();
+        if !self.strategy.mutates() {
+            generate_input(
+                self,
+                iteration,
+                &mut iteration_rng(seed, shard, iteration),
+                &corpus,
+                &mut input,
+            );
+            return Ok(input);
+        }
+
+        for (_, seed) in self.deal_seeds()?.swap_remove(shard as usize) {
+            if let Some(engine) = engine.as_mut() {
+                diff_input(engine, &seed, self.absorb_chunk_bytes)?;
+            }
+            corpus.push(seed);
+        }
+
+        let mut length_buckets = corpus
+            .iter()
+            .map(|entry| length_bucket(entry.len()))
+            .collect::<HashSet<_>>();
+        for i in 0..=iteration {
+            generate_input(
+                self,
+                i,
+                &mut iteration_rng(seed, shard, i),
+                &corpus,
+                &mut input,
+            );
+            if i == iteration {
+                break;
+            }
+
+            let mut novel = length_buckets.insert(length_bucket(input.len()));
+            if let Some(engine) = engine.as_mut() {
+                let lengths = self.chunk_lengths(seed, shard, i, input.len());
+                diff_split(engine, &input, self.absorb_chunk_bytes, lengths.as_deref())?;
+                novel |= engine.novel_coverage();
+            }
+            if novel {
+                corpus.push(input.clone());
+            }
+        }
+        Ok(input)
+    }
+
+    /// Deals the entries of `corpus_dir` and the in-memory `seeds` out to the shards round-robin.
+    fn deal_seeds(&self) -> Result<Vec<LabelledInputs>> {
+        let mut seeds = vec![Vec::new(); self.shards as usize];
+        let corpus = match self.corpus_dir.as_ref() {
+            Some(dir) => load_corpus(dir)?,
+            None => Vec::new(),
+        };
+        let corpus = corpus
+            .into_iter()
+            .map(|(path, seed)| (path.display().to_string(), seed));
+        for (i, seed) in corpus.chain(self.seeds.iter().cloned()).enumerate() {
+            seeds[i % self.shards as usize].push(seed);
+        }
+        Ok(seeds)
+    }
+
+    /// Exhaustively diffs every input length from 0 to `max_len` (inclusive), once per fill
+    /// pattern, guaranteeing that every padding path is covered. The lengths are interleaved across
+    /// `thread_count` tasks. Returns an error on the first mismatch outside of keep-going mode.
+    pub async fn sweep(&self, max_len: usize, progress_group: &MultiProgress) -> Result<Report> {
+        self.reported(self.run_sweep(max_len, progress_group)).await
+    }
+
+    /// Runs the exhaustive length sweep, without notifying the reporters (see [DiffFuzzer::sweep]).
+    async fn run_sweep(&self, max_len: usize, progress_group: &MultiProgress) -> Result<Report> {
+        let progress_style = progress_style()?;
+
+        self.check_evm_options()?;
+        self_test(&self.backends)?;
+
+        let clock = Instant::now();
+        let mut report = self.replay_regressions()?;
+        let mut join_set = JoinSet::new();
+        for i in 0..self.thread_count {
+            let lengths = (i as usize..=max_len)
+                .step_by(self.thread_count as usize)
+                .collect::<Vec<_>>();
+            let pb = progress_group.add(ProgressBar::new(
+                (lengths.len() * FILL_PATTERNS.len()) as u64,
+            ));
+            pb.set_style(progress_style.clone());
+            pb.set_message(format!("Thread {}", i + 1));
+
+            join_set.spawn(sweep_task(self.clone(), pb, i + 1, lengths));
+        }
+
+        while let Some(res) = join_set.join_next().await {
+            report.merge(res??);
+        }
+        report.threads.sort_by_key(|stats| stats.thread);
+        report.elapsed = clock.elapsed();
+        if let Some(csv) = self.gas_csv.as_ref() {
+            csv.flush()?;
+        }
+        report.interrupted = self.stop.load(Ordering::Relaxed);
+
+        Ok(report)
+    }
+}
+
+/// Returns the style of the per-task progress bars.
+fn progress_style() -> Result<ProgressStyle> {
+    Ok(ProgressStyle::with_template(
+        "{spinner:.green} [{elapsed_precise}] [{bar:60.cyan/blue}] ({msg} | eta: {eta})",
+    )?
+    .with_key("eta", |state: &ProgressState, w: &mut dyn Write| {
+        write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap()
+    })
+    .progress_chars("#>-"))
+}
+
+/// Returns the style of the per-task progress bars of time-limited campaigns, whose length and
+/// position are the campaign duration and the elapsed time in seconds.
+fn timed_progress_style() -> Result<ProgressStyle> {
+    Ok(ProgressStyle::with_template(
+        "{spinner:.green} [{elapsed_precise}] [{bar:60.cyan/blue}] ({msg} | remaining: {remaining})",
+    )?
+    .with_key("remaining", |state: &ProgressState, w: &mut dyn Write| {
+        let remaining = state.len().unwrap_or_default().saturating_sub(state.pos());
+        write!(w, "{}s", remaining).unwrap()
+    })
+    .progress_chars("#>-"))
+}
+
+/// Returns the style of the per-task progress bars of unbounded campaigns, which count the diffs
+/// performed so far.
+fn unbounded_progress_style() -> Result<ProgressStyle> {
+    Ok(ProgressStyle::with_template(
+        "{spinner:.green} [{elapsed_precise}] ({msg} | {human_pos} diffs, {per_sec})",
+    )?)
+}
+
+/// Advances the progress bar of a fuzzing task past one input, or to the elapsed time since
+/// `started` in time-limited campaigns.
+fn advance(pb: &ProgressBar, started: Option<Instant>) {
+    match started {
+        Some(started) => pb.set_position(started.elapsed().as_secs()),
+        None => pb.inc(1),
+    }
+}
+
+/// Spawns a task that diffs every length in `lengths` once per fill pattern.
+async fn sweep_task(
+    config: DiffFuzzer,
+    pb: ProgressBar,
+    thread: u64,
+    lengths: Vec<usize>,
+) -> Result<Report> {
+    let mut engine = config.engine(config.coverage_guided)?;
+    let mut input = Vec::new();
+    let mut report = Report::default();
+    let mut gas_invariance = GasInvariance::default();
+    let mut gas_anomalies = config.gas_anomaly_threshold.map(GasAnomalyDetector::new);
+    let live_diffs = config.stats.register_thread(thread);
+
+    'sweep: for len in lengths {
+        for (pattern, fill) in FILL_PATTERNS {
+            if config.stop.load(Ordering::Relaxed) {
+                break 'sweep;
+            }
+
+            input.clear();
+            input.extend((0..len).map(fill));
+
+            let verdict = engine.diff(&input)?;
+            tally(&config, &engine, &input, &mut report)?;
+            live_diffs.fetch_add(1, Ordering::Relaxed);
+            let context = || format!("on the {len} byte {pattern} input");
+            let failure = if verdict.is_unanimous() {
+                detect_gas_anomaly(
+                    gas_anomalies.as_mut(),
+                    &engine,
+                    &input,
+                    context,
+                    &mut report,
+                );
+                check_gas(&config, &mut gas_invariance, &engine, &input, None, context)
+                    .or_else(|| check_squeeze(&engine, &input, None, context))
+            } else {
+                Some(Failure::new(context(), &input, None, &engine, &verdict))
+            };
+            if let Some(failure) = failure {
+                record_failure(&config, failure, &mut report)?;
+                pb.set_message(format!("mismatches: {}", report.failures.len()));
+            }
+            pb.inc(1);
+            tokio::task::yield_now().await;
+        }
+    }
+
+    let stats = report.thread_stats(thread, pb.elapsed());
+    report.threads.push(stats);
+    pb.finish_with_message("DONE");
+    Ok(report)
+}
+
+/// The state of a shard while its task fuzzes it.
+struct Shard {
+    /// The index of the shard, counted from 0.
+    index: u64,
+    /// The number of iterations that the shard runs, unless time-limited or unbounded.
+    quota: u64,
+    /// The shard's own engine, so that its coverage feedback doesn't depend on the other shards.
+    engine: DiffEngine,
+    /// The seed inputs plus every novel input of the shard, which new inputs are mutated from.
+    corpus: Vec<Vec<u8>>,
+    /// The length buckets covered by the shard's corpus.
+    length_buckets: HashSet<(usize, u8)>,
+    /// The next iteration that the shard runs.
+    iteration: u64,
+}
+
+impl Shard {
+    /// Returns the progress of the shard, for the campaign's [Checkpoint].
+    fn checkpoint(&self) -> (u64, ShardCheckpoint) {
+        let checkpoint = ShardCheckpoint {
+            iteration: self.iteration,
+            corpus: self.corpus.clone(),
+        };
+        (self.index, checkpoint)
+    }
+}
+
+/// Spawns a task that fuzzes the `shards` assigned to it, each given its index, its seed inputs and
+/// the progress to resume it from. The task first replays the seeds of every shard, and then steps
+/// the shards in turn through their share of the campaign's iterations (or, in time-limited
+/// campaigns, until the duration has elapsed since `started`) of differential testing the fuzzer's
+/// reference backends vs. the `StatefulSponge` contract. The task periodically records the progress
+/// of its shards with `checkpoints`, if set.
+async fn fuzz_task(
+    config: DiffFuzzer,
+    pb: ProgressBar,
+    thread: u64,
+    started: Option<Instant>,
+    shards: Vec<(u64, LabelledInputs, ShardCheckpoint)>,
+    checkpoints: Option<Arc<CheckpointWriter>>,
+) -> Result<Report> {
+    let max_input_bytes = config.max_input_bytes;
+    let rng_seed = config.seed.unwrap_or_default();
+    let deadline = started
+        .zip(config.duration)
+        .and_then(|(started, duration)| started.checked_add(duration));
+    let unbounded = config.is_unbounded();
+    let mut last_status = Instant::now();
+    let mut report = Report::default();
+    let mut gas_invariance = GasInvariance::default();
+    let mut gas_anomalies = config.gas_anomaly_threshold.map(GasAnomalyDetector::new);
+    let live_diffs = config.stats.register_thread(thread);
+
+    // Replay the seed inputs of every shard before generating any of our own.
+    let mut states = Vec::with_capacity(shards.len());
+    for (index, seeds, resumed) in shards {
+        let mut engine = config.engine(config.coverage_guided)?;
+        let mut corpus = resumed.corpus;
+        for (label, seed) in seeds {
+            if config.stop.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let verdict = diff_input(&mut engine, &seed, config.absorb_chunk_bytes)?;
+            tally(&config, &engine, &seed, &mut report)?;
+            live_diffs.fetch_add(1, Ordering::Relaxed);
+            let chunk_bytes = config.absorb_chunk_bytes;
+            let context = || format!("on seed input {label}");
+            let failure = if verdict.is_unanimous() {
+                detect_gas_anomaly(gas_anomalies.as_mut(), &engine, &seed, context, &mut report);
+                check_gas(
+                    &config,
+                    &mut gas_invariance,
+                    &engine,
+                    &seed,
+                    chunk_bytes,
+                    context,
+                )
+                .or_else(|| check_squeeze(&engine, &seed, chunk_bytes, context))
+            } else {
+                Some(Failure::new(
+                    context(),
+                    &seed,
+                    chunk_bytes,
+                    &engine,
+                    &verdict,
+                ))
+            };
+            if let Some(failure) = failure {
+                record_failure(&config, failure, &mut report)?;
+                pb.set_message(task_message(thread, corpus.len(), &report));
+            }
+
+            corpus.push(seed);
+            advance(&pb, started);
+        }
+
+        states.push(Shard {
+            index,
+            quota: config.shard_quota(index),
+            engine,
+            length_buckets: corpus
+                .iter()
+                .map(|entry| length_bucket(entry.len()))
+                .collect(),
+            corpus,
+            iteration: resumed.iteration,
+        });
+    }
+    let corpus_len = |states: &[Shard]| states.iter().map(|shard| shard.corpus.len()).sum();
+
+    // Re-use the same memory for the input.
+    let mut input = Vec::with_capacity(max_input_bytes);
+    let mut last_checkpoint = Instant::now();
+
+    'campaign: loop {
+        let mut stepped = false;
+        for shard_index in 0..states.len() {
+            let expired = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if expired || config.stop.load(Ordering::Relaxed) {
+                break 'campaign;
+            }
+            // Hidden progress bars, e.g. under the dashboard, don't print status lines either.
+            if unbounded && !pb.is_hidden() && last_status.elapsed() >= STATUS_INTERVAL {
+                last_status = Instant::now();
+                let status = format!(
+                    "[{}s] {} | diffs: {}",
+                    pb.elapsed().as_secs(),
+                    task_message(thread, corpus_len(&states), &report),
+                    report.diffs
+                );
+                pb.suspend(|| tracing::info!("{status}"));
+            }
+
+            let shard = &mut states[shard_index];
+            let i = shard.iteration;
+            if deadline.is_none() && !unbounded && i >= shard.quota {
+                continue;
+            }
+            stepped = true;
+
+            generate_input(
+                &config,
+                i,
+                &mut iteration_rng(rng_seed, shard.index, i),
+                &shard.corpus,
+                &mut input,
+            );
+            if config.randomize_env {
+                shard
+                    .engine
+                    .set_env(EvmEnv::random(&mut env_rng(rng_seed, shard.index, i)));
+            }
+
+            let lengths = config.chunk_lengths(rng_seed, shard.index, i, input.len());
+            let verdict = diff_split(
+                &mut shard.engine,
+                &input,
+                config.absorb_chunk_bytes,
+                lengths.as_deref(),
+            )?;
+            tally(&config, &shard.engine, &input, &mut report)?;
+            live_diffs.fetch_add(1, Ordering::Relaxed);
+            let chunk_bytes = config.absorb_chunk_bytes;
+            let context = || format!("at iteration {i} of shard {}", shard.index);
+            let failure = if verdict.is_unanimous() {
+                detect_gas_anomaly(
+                    gas_anomalies.as_mut(),
+                    &shard.engine,
+                    &input,
+                    context,
+                    &mut report,
+                );
+                check_gas(
+                    &config,
+                    &mut gas_invariance,
+                    &shard.engine,
+                    &input,
+                    chunk_bytes,
+                    context,
+                )
+                .or_else(|| check_squeeze(&shard.engine, &input, chunk_bytes, context))
+            } else {
+                Some(Failure::new(
+                    context(),
+                    &input,
+                    chunk_bytes,
+                    &shard.engine,
+                    &verdict,
+                ))
+            };
+            let mut changed = failure.is_some();
+            if let Some(mut failure) = failure {
+                failure.coordinates = Some(Coordinates {
+                    shard: shard.index,
+                    iteration: i,
+                });
+                failure.chunk_lengths = lengths;
+                record_failure(&config, failure, &mut report)?;
+            }
+
+            let new_bucket = shard.length_buckets.insert(length_bucket(input.len()));
+            if new_bucket || shard.engine.novel_coverage() {
+                if let Some(dir) = config.corpus_out.as_ref() {
+                    write_corpus_entry(dir, &input)?;
+                }
+                shard.corpus.push(input.clone());
+                changed = true;
+            }
+            shard.iteration += 1;
+
+            if changed {
+                pb.set_message(task_message(thread, corpus_len(&states), &report));
+            }
+            advance(&pb, started);
+
+            if let Some(checkpoints) = checkpoints.as_ref() {
+                if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                    last_checkpoint = Instant::now();
+                    checkpoints.update(states.iter().map(Shard::checkpoint))?;
+                }
+            }
+
+            // Let the other tasks make progress when there are fewer workers than tasks, which
+            // would otherwise starve in time-limited and unbounded campaigns.
+            tokio::task::yield_now().await;
+        }
+
+        if !stepped {
+            break;
+        }
+    }
+
+    if let Some(checkpoints) = checkpoints.as_ref() {
+        checkpoints.update(states.iter().map(Shard::checkpoint))?;
+    }
+
+    let stats = report.thread_stats(thread, pb.elapsed());
+    report.threads.push(stats);
+    pb.finish_with_message("DONE");
+    Ok(report)
+}
+
+/// Returns the RNG that generates the input of iteration `iteration` of shard `shard`: the shard's
+/// own ChaCha20 stream of the campaign seed, positioned at the iteration's window of the stream.
+/// Every input is thus a function of `(seed, shard, iteration)` and the shard's corpus alone.
+fn iteration_rng(seed: u64, shard: u64, iteration: u64) -> ChaCha20Rng {
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    rng.set_stream(shard);
+    rng.set_word_pos((iteration as u128) << ITERATION_WORDS_LOG2);
+    rng
+}
+
+/// Returns the RNG that the EVM environment of an iteration is drawn from. It runs on a stream of
+/// its own, so that randomizing the environment leaves the generated inputs unchanged.
+fn env_rng(seed: u64, shard: u64, iteration: u64) -> ChaCha20Rng {
+    iteration_rng(seed, shard | ENV_STREAM, iteration)
+}
+
+/// Returns the RNG that the absorb split of an iteration is drawn from (see
+/// [DiffFuzzer::random_chunks]), on a stream of its own like [env_rng].
+fn chunk_rng(seed: u64, shard: u64, iteration: u64) -> ChaCha20Rng {
+    iteration_rng(seed, shard | CHUNK_STREAM, iteration)
+}
+
+/// Generates the input of iteration `iteration` into `input`, mutating it from an entry of `corpus`
+/// or generating it from scratch, depending on the fuzzer's strategy.
+fn generate_input<R: Rng>(
+    config: &DiffFuzzer,
+    iteration: u64,
+    rng: &mut R,
+    corpus: &[Vec<u8>],
+    input: &mut Vec<u8>,
+) {
+    let max_input_bytes = config.max_input_bytes_at(iteration);
+    input.clear();
+
+    // Only draw a parent when the strategy mutates, so that inputs of the other strategies don't
+    // depend on the corpus.
+    let parent = if config.strategy.mutates() {
+        corpus.choose(rng)
+    } else {
+        None
+    };
+    match parent {
+        Some(parent) if config.strategy.should_mutate(rng) => {
+            input.extend_from_slice(parent);
+            mutate(rng, input, corpus, max_input_bytes);
+        }
+        _ if config.strategy == Strategy::Payloads => {
+            generate_payload(rng, &config.payloads, input, max_input_bytes);
+        }
+        _ => {
+            let len = match config.input_sizes.choose(rng) {
+                Some(len) => *len,
+                None => config
+                    .length_distribution
+                    .sample(rng, config.min_input_bytes..max_input_bytes),
+            };
+            input.resize(len, 0);
+            if rng.gen_bool(config.pattern_ratio) {
+                fill_pattern(rng, input);
+            } else {
+                rng.fill(input.as_mut_slice());
+            }
+        }
+    }
+}
+
+/// Returns the progress bar message of a fuzzing task.
+fn task_message(thread: u64, corpus_len: usize, report: &Report) -> String {
+    match report.failures.len() {
+        0 => format!("Thread {thread} | corpus: {corpus_len}"),
+        n => format!("Thread {thread} | corpus: {corpus_len} | mismatches: {n}"),
+    }
+}
+
+/// Diffs `input` with `engine`, splitting it across absorb calls of at most `absorb_chunk_bytes`
+/// bytes each if set.
+pub(crate) fn diff_input(
+    engine: &mut DiffEngine,
+    input: &[u8],
+    absorb_chunk_bytes: Option<usize>,
+) -> Result<Verdict> {
+    match absorb_chunk_bytes {
+        Some(size) => engine.diff_in_chunks(input, size),
+        None => engine.diff(input),
+    }
+}
+
+/// Diffs `input` with `engine`, split across absorb calls of the given chunk `lengths` if set, or
+/// as [diff_input] does otherwise.
+fn diff_split(
+    engine: &mut DiffEngine,
+    input: &[u8],
+    absorb_chunk_bytes: Option<usize>,
+    lengths: Option<&[usize]>,
+) -> Result<Verdict> {
+    match lengths {
+        Some(lengths) => engine.diff_chunked(&split_at_lengths(input, lengths)),
+        None => diff_input(engine, input, absorb_chunk_bytes),
+    }
+}
+
+/// Counts `input`, the last input diffed by `engine`, in `report` (and its length profile, if
+/// profiling) and the fuzzer's live statistics, and streams its gas to the fuzzer's gas CSV, if set.
+fn tally(
+    config: &DiffFuzzer,
+    engine: &DiffEngine,
+    input: &[u8],
+    report: &mut Report,
+) -> Result<()> {
+    report.diffs += 1;
+    if cfg!(feature = "evm") {
+        let gas = engine.call_gas();
+        report.gas.record(gas.total());
+        report.absorb_gas.record(gas.absorb);
+        report.squeeze_gas.record(gas.squeeze);
+    }
+    if let Some(gas) = engine.variant_call_gas() {
+        report.variant_gas.record(gas.total());
+    }
+    let matrix_gas = engine.matrix_call_gas();
+    report
+        .matrix_gas
+        .resize(matrix_gas.len(), GasStats::default());
+    for (stats, gas) in report.matrix_gas.iter_mut().zip(matrix_gas) {
+        stats.record(gas.total());
+    }
+    let target_gas = engine.target_gas();
+    report
+        .target_gas
+        .resize(target_gas.len(), GasStats::default());
+    for (stats, &gas) in report.target_gas.iter_mut().zip(target_gas) {
+        stats.record(gas);
+    }
+    if config.profile {
+        let profile = report.lengths.entry(input.len()).or_default();
+        profile.diffs += 1;
+        if cfg!(feature = "evm") {
+            profile.gas.record(engine.gas_used());
+        }
+    }
+    config.stats.record_diff(engine.gas_used());
+    if let Some(csv) = config.gas_csv.as_ref() {
+        csv.record(input.len(), engine.call_gas(), engine.target_gas())?;
+    }
+    for reporter in &config.reporters {
+        reporter.on_iteration(input, engine);
+    }
+    Ok(())
+}
+
+/// Checks that `input`, the last input diffed by `engine`, used the same execution gas as the
+/// earlier inputs of its length in `invariance`, if the fuzzer checks gas invariance. Returns the
+/// failure found `context()` otherwise.
+fn check_gas(
+    config: &DiffFuzzer,
+    invariance: &mut GasInvariance,
+    engine: &DiffEngine,
+    input: &[u8],
+    absorb_chunk_bytes: Option<usize>,
+    context: impl FnOnce() -> String,
+) -> Option<Failure> {
+    if !config.gas_invariance {
+        return None;
+    }
+    let divergence = invariance.check(input, engine.call_gas())?;
+    Some(Failure::gas(
+        context(),
+        input,
+        absorb_chunk_bytes,
+        engine,
+        divergence,
+    ))
+}
+
+/// Checks that the second squeeze of `input`, the last input diffed by `engine`, returned the
+/// digest of the reset state, if the engine squeezes twice. Returns the failure found `context()`
+/// otherwise.
+fn check_squeeze(
+    engine: &DiffEngine,
+    input: &[u8],
+    absorb_chunk_bytes: Option<usize>,
+    context: impl FnOnce() -> String,
+) -> Option<Failure> {
+    let digest = engine
+        .resqueezed_digest()
+        .filter(|digest| *digest != RESET_DIGEST)?;
+    Some(Failure::squeeze(
+        context(),
+        input,
+        absorb_chunk_bytes,
+        engine,
+        digest,
+    ))
+}
+
+/// Records the gas of `input`, the last input diffed by `engine`, in `detector`, if the fuzzer
+/// detects gas anomalies, adding the anomaly found `context()` to `report` if its gas is an
+/// outlier.
+fn detect_gas_anomaly(
+    detector: Option<&mut GasAnomalyDetector>,
+    engine: &DiffEngine,
+    input: &[u8],
+    context: impl FnOnce() -> String,
+    report: &mut Report,
+) {
+    let Some(anomaly) =
+        detector.and_then(|detector| detector.check(input, engine.call_gas(), context))
+    else {
+        return;
+    };
+    tracing::warn!("Gas anomaly: {anomaly}");
+    report.gas_anomalies.push(anomaly);
+}
+
+/// Writes `failure` into the fuzzer's output directory, if set, adds its input to the regression
+/// directory, if set, and records it in `report` in keep-going mode. Otherwise, returns the error
+/// that the task fails with.
+fn record_failure(config: &DiffFuzzer, mut failure: Failure, report: &mut Report) -> Result<()> {
+    failure.stability = retry_failure(config, &failure)?;
+    failure.cross_check(&available_backends());
+    failure.seed = config.seed;
+    config.stats.record_mismatch(&failure);
+    tracing::debug!(
+        context = %failure.context,
+        stability = %failure.stability,
+        "Found mismatch: {}",
+        failure.description
+    );
+    for reporter in &config.reporters {
+        reporter.on_mismatch(&failure)?;
+    }
+    if let Some(dir) = config.regressions_dir.as_ref() {
+        fs::create_dir_all(dir)?;
+        write_corpus_entry(dir, &failure.input)?;
+    }
+    if !config.keep_going {
+        return Err(persist_failure(config, failure));
+    }
+
+    if let Some(dir) = config.output_dir.as_ref() {
+        failure.write(dir)?;
+    }
+    report.failures.push(failure);
+    Ok(())
+}
+
+/// Re-runs the failing input `retries` times, each through a fresh [DiffEngine], counting the
+/// retries that reproduce the original digests (or, for gas divergences, execution gas).
+fn retry_failure(config: &DiffFuzzer, failure: &Failure) -> Result<Stability> {
+    if config.retries == 0 {
+        return Ok(Stability::Unchecked);
+    }
+
+    let mut reproduced = 0;
+    for _ in 0..config.retries {
+        let mut engine = config.engine(false)?;
+        if let Some(env) = failure.env {
+            engine.set_env(env);
+        }
+        engine.diff_chunked(&failure.chunks())?;
+        let reproduces = match failure.kind {
+            FailureKind::Digest => engine
+                .digests()
+                .iter()
+                .eq(failure.digests.iter().map(|(_, digest)| digest)),
+            FailureKind::Gas(divergence) => {
+                execution_gas(&failure.input, engine.call_gas()) == divergence.gas
+            }
+            FailureKind::Squeeze(digest) => engine.resqueezed_digest() == Some(digest),
+        };
+        if reproduces {
+            reproduced += 1;
+        }
+    }
+
+    if reproduced == config.retries {
+        Ok(Stability::Stable {
+            retries: config.retries,
+        })
+    } else {
+        Ok(Stability::Flaky {
+            reproduced,
+            retries: config.retries,
+        })
+    }
+}
+
+/// Writes `failure` into the fuzzer's output directory, if set, and converts it into the error that
+/// the task fails with.
+fn persist_failure(config: &DiffFuzzer, failure: Failure) -> anyhow::Error {
+    match config.output_dir.as_ref().map(|dir| failure.write(dir)) {
+        Some(Ok(path)) => anyhow!("{failure} - written to {}", path.display()),
+        Some(Err(e)) => e.context(failure.to_string()),
+        None => anyhow!("{failure}"),
+    }
+}
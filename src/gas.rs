@@ -0,0 +1,500 @@
+//! Gas measurements of the `StatefulSponge` contract calls.
+
+use crate::{engine::Deployment, html::Plot, permutation::RATE_BYTES};
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Write as _},
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The gas used by the contract calls that hashed an input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallGas {
+    /// The total gas used by the `absorb` calls.
+    pub absorb: u64,
+    /// The gas used by the `squeeze` call.
+    pub squeeze: u64,
+}
+
+impl CallGas {
+    /// Returns the total gas used by the calls.
+    pub fn total(&self) -> u64 {
+        self.absorb + self.squeeze
+    }
+}
+
+/// The calldata gas of a zero byte, per [EIP-2028](https://eips.ethereum.org/EIPS/eip-2028).
+const ZERO_BYTE_GAS: u64 = 4;
+
+/// The calldata gas of a non-zero byte, per [EIP-2028](https://eips.ethereum.org/EIPS/eip-2028).
+const NON_ZERO_BYTE_GAS: u64 = 16;
+
+/// The intrinsic gas of every transaction, before its calldata.
+const TX_BASE_GAS: u64 = 21_000;
+
+/// Returns the intrinsic gas of a transaction with `calldata`: the base cost of the transaction
+/// plus the gas of its calldata, which is charged before any instruction executes.
+pub fn intrinsic_gas(calldata: &[u8]) -> u64 {
+    TX_BASE_GAS + calldata_gas(calldata)
+}
+
+/// Returns the intrinsic calldata gas of `data`.
+pub fn calldata_gas(data: &[u8]) -> u64 {
+    data.iter()
+        .map(|&byte| match byte {
+            0 => ZERO_BYTE_GAS,
+            _ => NON_ZERO_BYTE_GAS,
+        })
+        .sum()
+}
+
+/// Returns the gas that the calls which hashed `input` used, less the calldata gas of the input's
+/// bytes. Unlike the gas used, it doesn't depend on the input's zero bytes, so it is the same for
+/// every input of a length unless the contract branches on the data.
+pub fn execution_gas(input: &[u8], gas: CallGas) -> u64 {
+    gas.total().saturating_sub(calldata_gas(input))
+}
+
+/// The execution gas (see [execution_gas]) of the first input of every length that a task hashed,
+/// to check that inputs of equal length use equal gas.
+#[derive(Debug, Clone, Default)]
+pub struct GasInvariance {
+    /// The execution gas of the first input, by length.
+    lengths: HashMap<usize, u64>,
+}
+
+/// An input whose execution gas differs from that of the first input of its length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasDivergence {
+    /// The length of the input, in bytes.
+    pub len: usize,
+    /// The execution gas of the input.
+    pub gas: u64,
+    /// The execution gas of the first input of the same length.
+    pub expected: u64,
+}
+
+impl GasInvariance {
+    /// Records the execution gas of `input`, whose calls used `gas`, returning the divergence if
+    /// an earlier input of the same length used different execution gas.
+    pub fn check(&mut self, input: &[u8], gas: CallGas) -> Option<GasDivergence> {
+        let gas = execution_gas(input, gas);
+        let expected = *self.lengths.entry(input.len()).or_insert(gas);
+        (gas != expected).then_some(GasDivergence {
+            len: input.len(),
+            gas,
+            expected,
+        })
+    }
+}
+
+impl Display for GasDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the {} byte input used {} execution gas, but an earlier input of the same length used {} \
+             ({:+})",
+            self.len,
+            self.gas,
+            self.expected,
+            self.gas as i64 - self.expected as i64
+        )
+    }
+}
+
+/// The number of inputs of a length bucket whose gas is recorded before [GasAnomalyDetector] flags
+/// any of them, so that the distribution has settled.
+const MIN_ANOMALY_SAMPLES: u64 = 32;
+
+/// The running distribution of the execution gas (see [execution_gas]) of every length bucket of
+/// the inputs that a task hashed, i.e. of every number of rate-sized blocks, to flag the inputs whose
+/// gas is a statistical outlier of their bucket, even when their digests agree.
+#[derive(Debug, Clone, Default)]
+pub struct GasAnomalyDetector {
+    /// The number of standard deviations from the mean of its bucket beyond which the gas of an
+    /// input is anomalous.
+    pub threshold: f64,
+    /// The running distribution of every length bucket, by bucket.
+    buckets: HashMap<usize, RunningGas>,
+}
+
+/// The running mean and variance of a distribution of gas, by Welford's algorithm.
+#[derive(Debug, Clone, Copy, Default)]
+struct RunningGas {
+    /// The number of samples.
+    count: u64,
+    /// The mean of the samples.
+    mean: f64,
+    /// The sum of the squared differences of the samples from the mean.
+    m2: f64,
+}
+
+impl RunningGas {
+    /// Adds a sample to the distribution.
+    fn record(&mut self, gas: f64) {
+        self.count += 1;
+        let delta = gas - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (gas - self.mean);
+    }
+
+    /// Returns the standard deviation of the samples.
+    fn std_dev(&self) -> f64 {
+        (self.m2 / self.count.max(1) as f64).sqrt()
+    }
+}
+
+/// An input whose execution gas is a statistical outlier of its length bucket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GasAnomaly {
+    /// Where in the campaign the input was diffed, e.g. `at iteration 42 of shard 3`.
+    pub context: String,
+    /// The length of the input, in bytes.
+    pub len: usize,
+    /// The execution gas of the input.
+    pub gas: u64,
+    /// The mean execution gas of the bucket before the input.
+    pub mean: f64,
+    /// The standard deviation of the execution gas of the bucket before the input.
+    pub std_dev: f64,
+}
+
+impl GasAnomaly {
+    /// Returns the number of standard deviations that the gas of the input is from the mean.
+    pub fn z_score(&self) -> f64 {
+        (self.gas as f64 - self.mean) / self.std_dev
+    }
+}
+
+impl Display for GasAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the {} byte input {} used {} execution gas, {:+.1} standard deviations from the mean \
+             of {:.0} of its bucket",
+            self.len,
+            self.context,
+            self.gas,
+            self.z_score(),
+            self.mean
+        )
+    }
+}
+
+impl GasAnomalyDetector {
+    /// Creates a detector that flags gas beyond `threshold` standard deviations from the mean.
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Records the execution gas of `input`, whose calls used `gas`, in the distribution of its
+    /// bucket, returning the anomaly found `context()` if the gas is an outlier of the bucket.
+    pub fn check(
+        &mut self,
+        input: &[u8],
+        gas: CallGas,
+        context: impl FnOnce() -> String,
+    ) -> Option<GasAnomaly> {
+        let gas = execution_gas(input, gas);
+        let bucket = self.buckets.entry(input.len() / RATE_BYTES).or_default();
+        let (mean, std_dev) = (bucket.mean, bucket.std_dev());
+        let settled = bucket.count >= MIN_ANOMALY_SAMPLES;
+        bucket.record(gas as f64);
+
+        // A bucket of constant gas has no spread, so any deviation from it is anomalous.
+        let deviation = (gas as f64 - mean).abs();
+        let anomalous = if std_dev == 0.0 {
+            deviation > 0.0
+        } else {
+            deviation > self.threshold * std_dev
+        };
+        (settled && anomalous).then(|| GasAnomaly {
+            context: context(),
+            len: input.len(),
+            gas,
+            mean,
+            std_dev,
+        })
+    }
+}
+
+/// A CSV file that every task of a campaign streams the gas of its inputs to, one row per hash,
+/// with a column for the gas of every target (see [crate::DiffFuzzer::targets]), if any:
+///
+/// ```text
+/// length,absorb_gas,squeeze_gas,total_gas,target1_gas
+/// 135,75321,24991,100312,21990
+/// ```
+#[derive(Debug)]
+pub struct GasCsv {
+    /// The path of the file.
+    path: PathBuf,
+    /// The buffered file.
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl GasCsv {
+    /// Creates the CSV file at `path` and writes its header, with a column for each of `targets`
+    /// targets. Requires the `evm` feature.
+    pub fn create(path: &Path, targets: usize) -> Result<Self> {
+        if !cfg!(feature = "evm") {
+            bail!("Gas measurements require the `evm` feature");
+        }
+
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create gas CSV {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        write!(writer, "length,absorb_gas,squeeze_gas,total_gas")?;
+        for target in 1..=targets {
+            write!(writer, ",target{target}_gas")?;
+        }
+        writeln!(writer)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Mutex::new(writer),
+        })
+    }
+
+    /// Appends the row of a `len` byte input, whose calls used `gas`, with the gas of the targets'
+    /// calls `target_gas`.
+    pub fn record(&self, len: usize, gas: CallGas, target_gas: &[u64]) -> Result<()> {
+        let mut writer = self.writer.lock().expect("gas CSV lock poisoned");
+        let mut row = format!("{len},{},{},{}", gas.absorb, gas.squeeze, gas.total());
+        for gas in target_gas {
+            row.push_str(&format!(",{gas}"));
+        }
+        writeln!(writer, "{row}")
+            .with_context(|| format!("Failed to write gas CSV {}", self.path.display()))
+    }
+
+    /// Flushes the buffered rows to the file.
+    pub fn flush(&self) -> Result<()> {
+        let mut writer = self.writer.lock().expect("gas CSV lock poisoned");
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write gas CSV {}", self.path.display()))
+    }
+}
+
+/// The gas used to hash an input of a given length, as a point of the contract's cost curve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasPoint {
+    /// The length of the input, in bytes.
+    pub len: usize,
+    /// The gas used by the contract calls that hashed the input.
+    pub gas: CallGas,
+    /// The intrinsic gas of the `absorb` call (see [intrinsic_gas]), i.e. the part of its gas that
+    /// pays for the transaction and its calldata rather than for the permutation work.
+    pub absorb_intrinsic: u64,
+    /// The intrinsic gas of the `squeeze` call.
+    pub squeeze_intrinsic: u64,
+}
+
+impl GasPoint {
+    /// Returns the gas spent executing the `absorb` call, i.e. its gas less the intrinsic gas.
+    pub fn absorb_execution(&self) -> u64 {
+        self.gas.absorb.saturating_sub(self.absorb_intrinsic)
+    }
+
+    /// Returns the gas spent executing both calls, as when the contract is called from another
+    /// contract, e.g. a Foundry test, rather than by transactions.
+    pub fn execution(&self) -> u64 {
+        self.gas
+            .total()
+            .saturating_sub(self.absorb_intrinsic + self.squeeze_intrinsic)
+    }
+
+    /// Returns the number of Keccak-f permutations that absorbing the padded input takes.
+    pub fn blocks(&self) -> usize {
+        self.len / RATE_BYTES + 1
+    }
+}
+
+/// The gas used to hash the shortest input of every number of blocks, and its increase over the
+/// previous number of blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockCost {
+    /// The number of blocks of the padded input.
+    pub blocks: usize,
+    /// The total gas used to hash the shortest input with that many blocks.
+    pub gas: u64,
+    /// The intrinsic gas of the `absorb` call of the shortest input with that many blocks.
+    pub absorb_intrinsic: u64,
+    /// The increase in gas over the shortest input with one block fewer, if measured.
+    pub incremental: Option<i64>,
+}
+
+/// Measures the gas used to hash an input of every length in `lengths`, each in a fresh EVM with
+/// the contract deployed as in `deployment` and a single `absorb` call. The inputs are made of
+/// non-zero bytes, so that their calldata costs are the worst case. Requires the `evm` feature.
+pub fn measure_gas_curve(
+    deployment: &Deployment,
+    lengths: impl IntoIterator<Item = usize>,
+) -> Result<Vec<GasPoint>> {
+    #[cfg(feature = "evm")]
+    {
+        lengths
+            .into_iter()
+            .map(|len| {
+                let input = (0..len).map(|i| (i % 255) as u8 + 1).collect::<Vec<_>>();
+                let gas = crate::harness::gas_chunks_evm(&mut deployment.init_evm()?, &[&input])?;
+                Ok(GasPoint {
+                    len,
+                    gas: CallGas {
+                        absorb: gas[0],
+                        squeeze: gas[1],
+                    },
+                    absorb_intrinsic: intrinsic_gas(&crate::harness::absorb_calldata(&input)),
+                    squeeze_intrinsic: intrinsic_gas(&crate::harness::squeeze_calldata()),
+                })
+            })
+            .collect()
+    }
+    #[cfg(not(feature = "evm"))]
+    {
+        let _ = (deployment, lengths);
+        bail!("Gas measurements require the `evm` feature")
+    }
+}
+
+/// Returns the cost of every number of blocks covered by `points`, in order.
+pub fn block_costs(points: &[GasPoint]) -> Vec<BlockCost> {
+    let mut costs: Vec<BlockCost> = Vec::new();
+    let mut points = points.to_vec();
+    points.sort_by_key(|point| point.len);
+    for point in points {
+        if costs
+            .last()
+            .is_some_and(|cost| cost.blocks == point.blocks())
+        {
+            continue;
+        }
+        let gas = point.gas.total();
+        let incremental = costs
+            .last()
+            .filter(|cost| cost.blocks + 1 == point.blocks())
+            .map(|cost| gas as i64 - cost.gas as i64);
+        costs.push(BlockCost {
+            blocks: point.blocks(),
+            gas,
+            absorb_intrinsic: point.absorb_intrinsic,
+            incremental,
+        });
+    }
+    costs
+}
+
+/// Writes the cost curve as CSV to `writer`, one row per length:
+///
+/// ```text
+/// length,blocks,absorb_gas,absorb_intrinsic_gas,absorb_execution_gas,squeeze_gas,total_gas
+/// ```
+pub fn write_gas_curve_csv(mut writer: impl Write, points: &[GasPoint]) -> Result<()> {
+    writeln!(
+        writer,
+        "length,blocks,absorb_gas,absorb_intrinsic_gas,absorb_execution_gas,squeeze_gas,total_gas"
+    )?;
+    for point in points {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{}",
+            point.len,
+            point.blocks(),
+            point.gas.absorb,
+            point.absorb_intrinsic,
+            point.absorb_execution(),
+            point.gas.squeeze,
+            point.gas.total()
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The made-up test contract that the entries of a gas snapshot are named after.
+const SNAPSHOT_CONTRACT: &str = "StatefulSponge_Gas";
+
+/// Writes the cost curve to `writer` in the line format of a `forge snapshot` `.gas-snapshot`, one
+/// entry per length with the execution gas of both calls (see [GasPoint::execution]). The format is
+/// snapshot-shaped only: no Foundry test of the repository has these names, and `forge snapshot`
+/// records the gas of whole tests rather than that of the calls, so the output can only be diffed
+/// against other snapshots written by this function:
+///
+/// ```text
+/// StatefulSponge_Gas:test_hash_136Bytes_gas() (gas: 181560)
+/// ```
+pub fn write_gas_snapshot(mut writer: impl Write, points: &[GasPoint]) -> Result<()> {
+    for point in points {
+        writeln!(
+            writer,
+            "{SNAPSHOT_CONTRACT}:test_hash_{}Bytes_gas() (gas: {})",
+            point.len,
+            point.execution()
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Returns the cost curve as a standalone SVG line chart of the total gas by input length, along
+/// with the intrinsic gas of the `absorb` call (dashed).
+pub fn gas_curve_svg(points: &[GasPoint]) -> String {
+    let x_max = points
+        .iter()
+        .map(|point| point.len)
+        .max()
+        .unwrap_or_default();
+    let y_max = points
+        .iter()
+        .map(|point| point.gas.total())
+        .max()
+        .unwrap_or_default();
+    let plot = Plot::new(x_max as f64, y_max as f64);
+    let line = |gas: fn(&GasPoint) -> u64| {
+        points
+            .iter()
+            .map(|point| {
+                format!(
+                    "{:.1},{:.1}",
+                    plot.x(point.len as f64),
+                    plot.y(gas(point) as f64)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut content = String::new();
+    writeln!(
+        content,
+        "<polyline points=\"{}\" fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"1.5\"/>",
+        line(|point| point.gas.total())
+    )
+    .unwrap();
+    writeln!(
+        content,
+        "<polyline points=\"{}\" fill=\"none\" stroke=\"#ff7f0e\" stroke-width=\"1.5\" \
+         stroke-dasharray=\"6 3\"/>",
+        line(|point| point.absorb_intrinsic)
+    )
+    .unwrap();
+    // Mark the block boundaries, where every additional byte costs another permutation.
+    for boundary in (RATE_BYTES - 1..=x_max).step_by(RATE_BYTES) {
+        let x = plot.x(boundary as f64 + 0.5);
+        writeln!(
+            content,
+            "<line x1=\"{x:.1}\" y1=\"{:.1}\" x2=\"{x:.1}\" y2=\"{:.1}\" stroke=\"#ccc\" stroke-dasharray=\"4\"/>",
+            plot.y(0.0),
+            plot.y(y_max as f64)
+        )
+        .unwrap();
+    }
+    plot.render("input length (bytes)", "gas", &content)
+}
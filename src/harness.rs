@@ -0,0 +1,543 @@
+use crate::{
+    constants::{STATEFUL_SPONGE_ADDR, STATEFUL_SPONGE_BYTECODE},
+    gas::CallGas,
+    hashing::hash_input_tiny,
+};
+use alloy_sol_types::{sol, SolCall};
+use anyhow::{bail, Context, Result};
+use revm::{
+    db::{CacheDB, DatabaseRef, EmptyDB},
+    primitives::{
+        hex, AccountInfo, Address, Bytecode, ExecutionResult, Output, SpecId, TransactTo, U256,
+    },
+    Inspector, EVM,
+};
+use std::{path::Path, sync::OnceLock};
+
+sol! {
+    function absorb(bytes calldata input) external;
+    function squeeze() external returns (bytes32 digest);
+    function hash(bytes calldata input) external returns (bytes32 digest);
+    function reset() external;
+}
+
+/// The magic prefix of [EOF](https://eips.ethereum.org/EIPS/eip-3540) containers.
+pub const EOF_MAGIC: [u8; 2] = [0xef, 0x00];
+
+/// The account that sends the creation transaction of the contract (see
+/// [init_evm_with_initcode]).
+pub const DEPLOYER: Address = Address::repeat_byte(0xde);
+
+/// The database type that the harness' [EVM] runs on.
+pub type HarnessDB = CacheDB<EmptyDB>;
+
+/// The runtime bytecode of the stateful sponge contract under test, decoded from
+/// [STATEFUL_SPONGE_BYTECODE] unless loaded from a file first (see [load_contract_code]).
+static CONTRACT_CODE: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Returns the runtime bytecode of the stateful sponge contract under test: the one loaded with
+/// [load_contract_code], or else [STATEFUL_SPONGE_BYTECODE].
+pub fn contract_code() -> Result<&'static [u8]> {
+    if let Some(code) = CONTRACT_CODE.get() {
+        return Ok(code);
+    }
+    let code = hex::decode(STATEFUL_SPONGE_BYTECODE.trim())
+        .context("Invalid hex bytecode in testdata/stateful_sponge")?;
+    Ok(CONTRACT_CODE.get_or_init(|| code))
+}
+
+/// Replaces the bytecode of the stateful sponge contract under test, for the rest of the process,
+/// with the hex runtime bytecode in the file at `path` (see [read_bytecode]), e.g. a local build of
+/// the contract, so that it can be tested without rebuilding the harness. Must be called before the
+/// contract is first deployed.
+pub fn load_contract_code(path: &Path) -> Result<()> {
+    set_contract_code(read_bytecode(path)?)
+        .with_context(|| format!("Failed to load the contract's bytecode {}", path.display()))?;
+    tracing::info!(path = %path.display(), "Loaded the contract's bytecode");
+    Ok(())
+}
+
+/// Like [load_contract_code], but replaces the bytecode of the contract under test with `code`,
+/// e.g. the deployed bytecode of a Foundry artifact.
+pub fn set_contract_code(code: Vec<u8>) -> Result<()> {
+    ensure_not_eof(&code)?;
+    if CONTRACT_CODE.set(code).is_err() {
+        bail!("The contract's bytecode was already loaded");
+    }
+    Ok(())
+}
+
+/// Creates a new [EVM] with the stateful sponge contract deployed and the environment configured
+/// for differential testing. All transactions are directed at [STATEFUL_SPONGE_ADDR].
+pub fn init_evm() -> Result<EVM<HarnessDB>> {
+    init_evm_with_spec(SpecId::LATEST)
+}
+
+/// Like [init_evm], but executes the transactions under the given hard fork.
+pub fn init_evm_with_spec(spec: SpecId) -> Result<EVM<HarnessDB>> {
+    init_evm_with_code(spec, contract_code()?)
+}
+
+/// Like [init_evm_with_spec], but deploys `code`, another build of the stateful sponge contract,
+/// instead of [contract_code].
+pub fn init_evm_with_code(spec: SpecId, code: &[u8]) -> Result<EVM<HarnessDB>> {
+    init_evm_at(spec, code, &[STATEFUL_SPONGE_ADDR])
+}
+
+/// Like [init_evm_with_code], but deploys `code` at every one of `addresses` instead of
+/// [STATEFUL_SPONGE_ADDR], directing all transactions at the first one. The contracts can be hashed
+/// in interleaved sessions with [hash_sessions_evm_metered].
+pub fn init_evm_at(spec: SpecId, code: &[u8], addresses: &[Address]) -> Result<EVM<HarnessDB>> {
+    let Some(&first) = addresses.first() else {
+        bail!("At least one contract address is required");
+    };
+    let mut cache_db = CacheDB::new(EmptyDB::default());
+    for &address in addresses {
+        deploy_code_at(&mut cache_db, code, address)?;
+    }
+    let mut evm = configure_evm(spec, cache_db);
+    evm.env.tx.transact_to = TransactTo::Call(first);
+    Ok(evm)
+}
+
+/// Like [init_evm_with_spec], but deploys the contract with a creation transaction from
+/// [DEPLOYER] that executes `initcode`, like the contract reaches chains in reality, instead of
+/// inserting its runtime bytecode. All transactions are directed at the created contract.
+pub fn init_evm_with_initcode(spec: SpecId, initcode: &[u8]) -> Result<EVM<HarnessDB>> {
+    let mut evm = configure_evm(spec, CacheDB::new(EmptyDB::default()));
+    create_contract(&mut evm, initcode)?;
+    Ok(evm)
+}
+
+/// Creates an instance of the contract in `evm` with a creation transaction from [DEPLOYER]
+/// that executes `initcode`, like [init_evm_with_initcode], directing all transactions at it, and
+/// returns its address.
+pub fn create_contract(evm: &mut EVM<HarnessDB>, initcode: &[u8]) -> Result<Address> {
+    let _span = tracing::debug_span!("deploy").entered();
+    evm.env.tx.caller = DEPLOYER;
+    evm.env.tx.transact_to = TransactTo::create();
+    evm.env.tx.data = initcode.to_vec().into();
+    let address = match evm.transact_commit()? {
+        ExecutionResult::Success {
+            output: Output::Create(code, Some(address)),
+            gas_used,
+            ..
+        } => {
+            tracing::debug!(
+                %address,
+                initcode_bytes = initcode.len(),
+                code_bytes = code.len(),
+                gas = gas_used,
+                "Created the StatefulSponge contract"
+            );
+            address
+        }
+        r => bail!("The creation transaction of the contract failed: {r:?}"),
+    };
+
+    evm.env.tx.caller = Address::ZERO;
+    evm.env.tx.transact_to = TransactTo::Call(address);
+    Ok(address)
+}
+
+/// Executes `initcode` in a creation transaction from [DEPLOYER], without committing it, and
+/// returns the runtime bytecode that it deploys, e.g. that of the runtime sub-object of a Yul
+/// object.
+pub fn deployed_code(initcode: &[u8]) -> Result<Vec<u8>> {
+    let mut evm = configure_evm(SpecId::LATEST, CacheDB::new(EmptyDB::default()));
+    evm.env.tx.caller = DEPLOYER;
+    evm.env.tx.transact_to = TransactTo::create();
+    evm.env.tx.data = initcode.to_vec().into();
+    match evm.transact()?.result {
+        ExecutionResult::Success {
+            output: Output::Create(code, _),
+            ..
+        } => Ok(code.to_vec()),
+        r => bail!("The creation transaction failed: {r:?}"),
+    }
+}
+
+/// Returns initcode that deploys `code` as is, without running any constructor logic.
+pub fn wrap_initcode(code: &[u8]) -> Vec<u8> {
+    // PUSH4 len, DUP1, PUSH1 14, PUSH1 0, CODECOPY, PUSH1 0, RETURN, followed by the code.
+    let len = u32::try_from(code.len()).expect("code fits in 4 GiB");
+    let mut initcode = vec![0x63];
+    initcode.extend_from_slice(&len.to_be_bytes());
+    initcode.extend_from_slice(&[0x80, 0x60, 14, 0x60, 0x00, 0x39, 0x60, 0x00, 0xf3]);
+    initcode.extend_from_slice(code);
+    initcode
+}
+
+/// Creates a new [EVM] on `db`, with the environment configured for differential testing under
+/// `spec` and all transactions directed at [STATEFUL_SPONGE_ADDR].
+fn configure_evm(spec: SpecId, db: HarnessDB) -> EVM<HarnessDB> {
+    let mut evm = EVM::new();
+    evm.database(db);
+
+    evm.env.cfg.spec_id = spec;
+    evm.env.cfg.disable_base_fee = true;
+    evm.env.cfg.disable_gas_refund = true;
+    evm.env.cfg.disable_balance_check = true;
+    evm.env.cfg.disable_block_gas_limit = true;
+    evm.env.cfg.memory_limit = u64::MAX;
+    evm.env.tx.transact_to = TransactTo::Call(STATEFUL_SPONGE_ADDR);
+    evm
+}
+
+/// Deploys the stateful sponge contract to the given database.
+pub fn deploy_contract<T: DatabaseRef>(db: &mut CacheDB<T>) -> Result<()> {
+    deploy_code(db, contract_code()?)
+}
+
+/// Deploys `code`, a build of the stateful sponge contract, to the given database at
+/// [STATEFUL_SPONGE_ADDR].
+pub fn deploy_code<T: DatabaseRef>(db: &mut CacheDB<T>, code: &[u8]) -> Result<()> {
+    deploy_code_at(db, code, STATEFUL_SPONGE_ADDR)
+}
+
+/// Like [deploy_code], but deploys `code` at `address`.
+///
+/// The revm version that the harness is built on predates EOF, and would execute an EOF container
+/// as legacy bytecode starting with the invalid `0xEF` opcode, so EOF builds are rejected upfront
+/// (see [ensure_not_eof]).
+pub fn deploy_code_at<T: DatabaseRef>(
+    db: &mut CacheDB<T>,
+    code: &[u8],
+    address: Address,
+) -> Result<()> {
+    let _span = tracing::debug_span!("deploy").entered();
+    ensure_not_eof(code)?;
+    let mut code_hash = [0u8; 32];
+    hash_input_tiny(code, code_hash.as_mut());
+
+    let sponge_code_len = code.len();
+    let mut acc_info = AccountInfo {
+        balance: U256::ZERO,
+        nonce: 0,
+        code_hash: code_hash.into(),
+        code: Some(Bytecode::new_raw(code.to_vec().into())),
+    };
+    db.insert_contract(&mut acc_info);
+    db.insert_account_info(address, acc_info);
+    tracing::debug!(
+        %address,
+        code_bytes = sponge_code_len,
+        "Deployed the StatefulSponge contract"
+    );
+    Ok(())
+}
+
+/// Returns the Keccak256 hash of the stateful sponge contract's runtime bytecode, which identifies
+/// the contract build under test.
+pub fn contract_code_hash() -> Result<[u8; 32]> {
+    let mut code_hash = [0u8; 32];
+    hash_input_tiny(contract_code()?, code_hash.as_mut());
+    Ok(code_hash)
+}
+
+/// Reads a build of the stateful sponge contract from `path`, a file holding its runtime bytecode
+/// as hex (like `testdata/stateful_sponge`).
+pub fn read_bytecode(path: &Path) -> Result<Vec<u8>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read bytecode {}", path.display()))?;
+    hex::decode(text.trim()).with_context(|| format!("Invalid hex bytecode in {}", path.display()))
+}
+
+/// Returns `true` if `code` is an [EOF](https://eips.ethereum.org/EIPS/eip-3540) container rather
+/// than legacy bytecode.
+pub fn is_eof(code: &[u8]) -> bool {
+    code.starts_with(&EOF_MAGIC)
+}
+
+/// Fails if `code` is an EOF container (see [is_eof]), which the revm version of this build can't
+/// execute.
+pub fn ensure_not_eof(code: &[u8]) -> Result<()> {
+    if is_eof(code) {
+        bail!(
+            "The bytecode is an EOF container, which the revm version of this build cannot \
+             execute; EOF builds can be diffed once revm supports EOF behind a spec"
+        );
+    }
+    Ok(())
+}
+
+/// Returns `true` if `code` has an instruction with the given opcode, skipping over the immediates
+/// of `PUSH` instructions. Trailing metadata is scanned too, so this may report false positives.
+pub fn contains_opcode(code: &[u8], opcode: u8) -> bool {
+    let mut pc = 0;
+    while pc < code.len() {
+        if code[pc] == opcode {
+            return true;
+        }
+        // PUSH1 (0x60) to PUSH32 (0x7f) are followed by their immediate.
+        pc += match code[pc] {
+            push @ 0x60..=0x7f => (push - 0x5f) as usize + 1,
+            _ => 1,
+        };
+    }
+    false
+}
+
+/// Re-executes the absorb and squeeze calls of `chunks` in `evm`, which should be freshly
+/// initialized (see [crate::engine::Deployment::init_evm]), returning the gas used by each absorb
+/// call, followed by the gas used by the squeeze call.
+pub fn gas_chunks_evm(evm: &mut EVM<HarnessDB>, chunks: &[&[u8]]) -> Result<Vec<u64>> {
+    let mut gas = Vec::with_capacity(chunks.len() + 1);
+    hash_chunks_evm_with(evm, chunks, |evm| {
+        let result = evm.transact_commit()?;
+        gas.push(result.gas_used());
+        Ok(result)
+    })?;
+    Ok(gas)
+}
+
+/// Returns the ABI-encoded calldata of an `absorb(bytes)` call with the given chunk.
+pub fn absorb_calldata(chunk: &[u8]) -> Vec<u8> {
+    absorbCall {
+        input: chunk.to_vec(),
+    }
+    .abi_encode()
+}
+
+/// Returns the ABI-encoded calldata of a `squeeze()` call.
+pub fn squeeze_calldata() -> Vec<u8> {
+    squeezeCall {}.abi_encode()
+}
+
+/// Absorbs `chunk` into the `StatefulSponge` contract that `evm` directs its transactions at,
+/// without squeezing it afterwards, returning the gas used by the call.
+pub fn absorb_evm(evm: &mut EVM<HarnessDB>, chunk: &[u8]) -> Result<u64> {
+    let _span = tracing::trace_span!("absorb", len = chunk.len()).entered();
+    absorb_with(evm, chunk, &mut |evm| Ok(evm.transact_commit()?))
+}
+
+/// Calls `reset()` on the contract that `evm` directs its transactions at, an entrypoint that
+/// resets the sponge without squeezing it, which `StatefulSponge` doesn't expose. Returns the gas
+/// used by the call.
+pub fn reset_evm(evm: &mut EVM<HarnessDB>) -> Result<u64> {
+    let _span = tracing::trace_span!("reset").entered();
+    evm.env.tx.data = resetCall {}.abi_encode().into();
+    match evm.transact_commit()? {
+        r @ ExecutionResult::Success { .. } => Ok(r.gas_used()),
+        r => bail!("Reset call failed: {r:?}"),
+    }
+}
+
+/// Squeezes the `StatefulSponge` contract that `evm` directs its transactions at, without absorbing
+/// anything first, returning the digest.
+pub fn squeeze_evm(evm: &mut EVM<HarnessDB>) -> Result<[u8; 32]> {
+    let _span = tracing::trace_span!("squeeze").entered();
+    Ok(squeeze_with(evm, &mut |evm| Ok(evm.transact_commit()?))?.0)
+}
+
+/// Returns the ABI-encoded calldata of a `hash(bytes)` call of a one-shot contract with the given
+/// input.
+pub fn hash_calldata(input: &[u8]) -> Vec<u8> {
+    hashCall {
+        input: input.to_vec(),
+    }
+    .abi_encode()
+}
+
+/// Calls the contract that `evm` directs its transactions at with `calldata` in a single
+/// transaction, which must return a 32 byte digest, e.g. `hash(bytes)` (see [hash_calldata]).
+/// Returns the digest and the gas used by the call.
+pub fn call_digest_evm(evm: &mut EVM<HarnessDB>, calldata: Vec<u8>) -> Result<([u8; 32], u64)> {
+    evm.env.tx.data = calldata.into();
+    match evm.transact_commit()? {
+        ExecutionResult::Success {
+            output: Output::Call(output),
+            gas_used,
+            ..
+        } => match <[u8; 32]>::try_from(output.as_ref()) {
+            Ok(digest) => Ok((digest, gas_used)),
+            Err(_) => bail!(
+                "The call returned {} bytes instead of a 32 byte digest",
+                output.len()
+            ),
+        },
+        r => bail!("Hash call failed: {r:?}"),
+    }
+}
+
+/// Hashes the input bytes using the `StatefulSponge` contract, returning the digest and the gas
+/// used by the `absorb` and `squeeze` calls.
+pub fn hash_input_evm(evm: &mut EVM<HarnessDB>, input: &[u8]) -> Result<([u8; 32], CallGas)> {
+    hash_chunks_evm_metered(evm, &[input])
+}
+
+/// Hashes the input bytes using the `StatefulSponge` contract, running both the absorb and squeeze
+/// calls under the given [Inspector].
+pub fn hash_input_evm_inspected<I: Inspector<HarnessDB>>(
+    evm: &mut EVM<HarnessDB>,
+    input: &[u8],
+    inspector: &mut I,
+) -> Result<[u8; 32]> {
+    hash_chunks_evm_inspected(evm, &[input], inspector)
+}
+
+/// Absorbs each chunk into the `StatefulSponge` contract in its own transaction, and then squeezes
+/// the digest. Every `absorb` call pads its chunk, so the digest is not that of the concatenated
+/// chunks (see [crate::engine::DiffEngine::diff_chunked]).
+pub fn hash_chunks_evm(evm: &mut EVM<HarnessDB>, chunks: &[&[u8]]) -> Result<[u8; 32]> {
+    Ok(hash_chunks_evm_metered(evm, chunks)?.0)
+}
+
+/// Like [hash_chunks_evm], but also returns the gas used by the absorb and squeeze calls.
+pub fn hash_chunks_evm_metered(
+    evm: &mut EVM<HarnessDB>,
+    chunks: &[&[u8]],
+) -> Result<([u8; 32], CallGas)> {
+    hash_chunks_evm_with(evm, chunks, |evm| Ok(evm.transact_commit()?))
+}
+
+/// Like [hash_chunks_evm], but runs every call under the given [Inspector].
+pub fn hash_chunks_evm_inspected<I: Inspector<HarnessDB>>(
+    evm: &mut EVM<HarnessDB>,
+    chunks: &[&[u8]],
+    inspector: &mut I,
+) -> Result<[u8; 32]> {
+    Ok(hash_chunks_evm_inspected_metered(evm, chunks, inspector)?.0)
+}
+
+/// Like [hash_chunks_evm_inspected], but also returns the gas used by the absorb and squeeze
+/// calls.
+pub fn hash_chunks_evm_inspected_metered<I: Inspector<HarnessDB>>(
+    evm: &mut EVM<HarnessDB>,
+    chunks: &[&[u8]],
+    inspector: &mut I,
+) -> Result<([u8; 32], CallGas)> {
+    hash_chunks_evm_with(evm, chunks, |evm| Ok(evm.inspect_commit(&mut *inspector)?))
+}
+
+/// Like [hash_chunks_evm_metered], but hashes `chunks` in interleaved sessions of the contracts at
+/// every one of `addresses` (see [init_evm_at]): every chunk is absorbed into every contract in
+/// turn, and then every contract is squeezed in turn. Returns the digest and the gas used by the
+/// calls of every session, in the order of `addresses`.
+pub fn hash_sessions_evm_metered(
+    evm: &mut EVM<HarnessDB>,
+    addresses: &[Address],
+    chunks: &[&[u8]],
+) -> Result<Vec<([u8; 32], CallGas)>> {
+    hash_sessions_evm_with(evm, addresses, chunks, |evm| Ok(evm.transact_commit()?))
+}
+
+/// Like [hash_sessions_evm_metered], but runs every call under the given [Inspector].
+pub fn hash_sessions_evm_inspected_metered<I: Inspector<HarnessDB>>(
+    evm: &mut EVM<HarnessDB>,
+    addresses: &[Address],
+    chunks: &[&[u8]],
+    inspector: &mut I,
+) -> Result<Vec<([u8; 32], CallGas)>> {
+    hash_sessions_evm_with(evm, addresses, chunks, |evm| {
+        Ok(evm.inspect_commit(&mut *inspector)?)
+    })
+}
+
+/// Re-executes the absorb and squeeze calls of `chunks` in `evm`, which should be freshly
+/// initialized (see [crate::engine::Deployment::init_evm]), under an
+/// [EIP-3155](https://eips.ethereum.org/EIPS/eip-3155) tracer, writing one JSON line per executed
+/// opcode (plus a summary line per call) to `output`.
+#[cfg(feature = "trace")]
+pub fn trace_chunks_evm(
+    evm: &mut EVM<HarnessDB>,
+    chunks: &[&[u8]],
+    output: Box<dyn std::io::Write>,
+) -> Result<[u8; 32]> {
+    let mut tracer = revm::inspectors::TracerEip3155::new(output, true, true);
+    hash_chunks_evm_inspected(evm, chunks, &mut tracer)
+}
+
+/// Absorbs each chunk into the `StatefulSponge` contract and squeezes the digest, executing each
+/// transaction with `transact`. Returns the digest and the gas used by the calls.
+fn hash_chunks_evm_with(
+    evm: &mut EVM<HarnessDB>,
+    chunks: &[&[u8]],
+    mut transact: impl FnMut(&mut EVM<HarnessDB>) -> Result<ExecutionResult>,
+) -> Result<([u8; 32], CallGas)> {
+    let mut gas = CallGas::default();
+
+    // Absorb the data into the sponge.
+    for (i, chunk) in chunks.iter().enumerate() {
+        let _span = tracing::trace_span!("absorb", chunk = i, len = chunk.len()).entered();
+        gas.absorb += absorb_with(evm, chunk, &mut transact)?;
+    }
+
+    // Squeeze the sponge and retrieve the output digest.
+    let _span = tracing::trace_span!("squeeze").entered();
+    let (digest, squeeze) = squeeze_with(evm, &mut transact)?;
+    gas.squeeze = squeeze;
+    Ok((digest, gas))
+}
+
+/// Hashes `chunks` in a session of every contract at `addresses`, executing each transaction with
+/// `transact`. The sessions are interleaved: every chunk is absorbed into every contract in turn,
+/// and then every contract is squeezed in turn. Returns the digest and the gas used by the calls of
+/// every session, in the order of `addresses`, and directs the following transactions at the first
+/// address.
+fn hash_sessions_evm_with(
+    evm: &mut EVM<HarnessDB>,
+    addresses: &[Address],
+    chunks: &[&[u8]],
+    mut transact: impl FnMut(&mut EVM<HarnessDB>) -> Result<ExecutionResult>,
+) -> Result<Vec<([u8; 32], CallGas)>> {
+    let mut gas = vec![CallGas::default(); addresses.len()];
+    for (i, chunk) in chunks.iter().enumerate() {
+        for (session, address) in addresses.iter().enumerate() {
+            let _span =
+                tracing::trace_span!("absorb", chunk = i, len = chunk.len(), session).entered();
+            evm.env.tx.transact_to = TransactTo::Call(*address);
+            gas[session].absorb += absorb_with(evm, chunk, &mut transact)?;
+        }
+    }
+
+    let mut sessions = Vec::with_capacity(addresses.len());
+    for (session, (address, gas)) in addresses.iter().zip(gas).enumerate() {
+        let _span = tracing::trace_span!("squeeze", session).entered();
+        evm.env.tx.transact_to = TransactTo::Call(*address);
+        let (digest, squeeze) = squeeze_with(evm, &mut transact)?;
+        sessions.push((digest, CallGas { squeeze, ..gas }));
+    }
+    if let Some(address) = addresses.first() {
+        evm.env.tx.transact_to = TransactTo::Call(*address);
+    }
+    Ok(sessions)
+}
+
+/// Absorbs `chunk` into the `StatefulSponge` contract that `evm` directs its transactions at,
+/// executing the transaction with `transact`. Returns the gas used by the call.
+fn absorb_with(
+    evm: &mut EVM<HarnessDB>,
+    chunk: &[u8],
+    transact: &mut impl FnMut(&mut EVM<HarnessDB>) -> Result<ExecutionResult>,
+) -> Result<u64> {
+    evm.env.tx.data = absorb_calldata(chunk).into();
+    match transact(evm)? {
+        r @ ExecutionResult::Success { .. } => {
+            tracing::trace!(gas = r.gas_used(), "Absorbed chunk");
+            Ok(r.gas_used())
+        }
+        r => bail!("Absorb call failed: {r:?}"),
+    }
+}
+
+/// Squeezes the `StatefulSponge` contract that `evm` directs its transactions at, executing the
+/// transaction with `transact`. Returns the digest and the gas used by the call.
+fn squeeze_with(
+    evm: &mut EVM<HarnessDB>,
+    transact: &mut impl FnMut(&mut EVM<HarnessDB>) -> Result<ExecutionResult>,
+) -> Result<([u8; 32], u64)> {
+    evm.env.tx.data = squeeze_calldata().into();
+    match transact(evm)? {
+        ExecutionResult::Success {
+            output: Output::Call(hash),
+            gas_used,
+            ..
+        } => {
+            let return_data = squeezeCall::abi_decode_returns(hash.as_ref(), false)?;
+            tracing::trace!(gas = gas_used, digest = %return_data.digest, "Squeezed digest");
+            Ok((*return_data.digest, gas_used))
+        }
+        r => bail!("Squeeze call failed: {r:?}"),
+    }
+}
@@ -1,47 +1,165 @@
-use alloy_sol_types::{sol, SolCall};
-use anyhow::{bail, Result};
-use revm::{
-    db::{CacheDB, EmptyDB},
-    primitives::{ExecutionResult, Output},
-    EVM,
-};
+use crate::permutation;
+use sha3::{Digest, Keccak256};
 use tiny_keccak::Hasher;
 
-sol! {
-    function absorb(bytes calldata input) external;
-    function squeeze() external returns (bytes32 digest);
+/// A native Keccak256 implementation that the `StatefulSponge` contract is differentially tested
+/// against.
+pub trait HashBackend: Send {
+    /// The name of the backend, used to attribute mismatches.
+    fn name(&self) -> &'static str;
+
+    /// Hashes the input bytes, writing the 32 byte digest into `output`.
+    fn hash(&mut self, input: &[u8], output: &mut [u8]);
+}
+
+/// A constructor for a [HashBackend]. Each fuzzing task instantiates its own set of backends, so
+/// implementations are free to keep per-task state.
+pub type BackendFactory = fn() -> Box<dyn HashBackend>;
+
+/// Returns a factory for every reference backend compiled into this build.
+pub fn available_backends() -> Vec<BackendFactory> {
+    #[allow(unused_mut)]
+    let mut backends: Vec<BackendFactory> = vec![
+        || Box::new(TinyKeccak),
+        || Box::new(Sha3Keccak),
+        || Box::new(AlloyKeccak),
+        || Box::new(NativeKeccak),
+    ];
+    #[cfg(feature = "xkcp")]
+    backends.push(|| Box::new(XkcpKeccak));
+    #[cfg(feature = "keccak-asm")]
+    backends.push(|| Box::new(AsmKeccak));
+    backends
+}
+
+/// The [HashBackend] for [tiny_keccak]'s Keccak256 implementation.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TinyKeccak;
+
+impl HashBackend for TinyKeccak {
+    fn name(&self) -> &'static str {
+        "tiny-keccak"
+    }
+
+    fn hash(&mut self, input: &[u8], output: &mut [u8]) {
+        hash_input_tiny(input, output)
+    }
+}
+
+/// The [HashBackend] for the RustCrypto [sha3] crate's Keccak256 implementation.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Sha3Keccak;
+
+impl HashBackend for Sha3Keccak {
+    fn name(&self) -> &'static str {
+        "sha3"
+    }
+
+    fn hash(&mut self, input: &[u8], output: &mut [u8]) {
+        hash_input_sha3(input, output)
+    }
+}
+
+/// The [HashBackend] for [alloy_primitives::keccak256], the hash that most Optimism Rust code paths
+/// actually call.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AlloyKeccak;
+
+impl HashBackend for AlloyKeccak {
+    fn name(&self) -> &'static str {
+        "alloy"
+    }
+
+    fn hash(&mut self, input: &[u8], output: &mut [u8]) {
+        hash_input_alloy(input, output)
+    }
+}
+
+/// The [HashBackend] for the in-crate [permutation](crate::permutation) implementation.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NativeKeccak;
+
+impl HashBackend for NativeKeccak {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn hash(&mut self, input: &[u8], output: &mut [u8]) {
+        output.copy_from_slice(&permutation::keccak256(input));
+    }
+}
+
+/// The [HashBackend] for the XKCP C reference implementation, linked via FFI.
+#[cfg(feature = "xkcp")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct XkcpKeccak;
+
+#[cfg(feature = "xkcp")]
+impl HashBackend for XkcpKeccak {
+    fn name(&self) -> &'static str {
+        "xkcp"
+    }
+
+    fn hash(&mut self, input: &[u8], output: &mut [u8]) {
+        hash_input_xkcp(input, output)
+    }
+}
+
+/// The [HashBackend] for the assembly-backed `keccak-asm` implementation.
+#[cfg(feature = "keccak-asm")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AsmKeccak;
+
+#[cfg(feature = "keccak-asm")]
+impl HashBackend for AsmKeccak {
+    fn name(&self) -> &'static str {
+        "keccak-asm"
+    }
+
+    fn hash(&mut self, input: &[u8], output: &mut [u8]) {
+        hash_input_asm(input, output)
+    }
 }
 
 /// Hashes the input bytes using [tiny_keccak]'s Keccak256 implementation.
-pub(crate) fn hash_input_tiny(input: &[u8], output: &mut [u8]) {
+pub fn hash_input_tiny(input: &[u8], output: &mut [u8]) {
     let mut hasher = tiny_keccak::Keccak::v256();
     hasher.update(input);
     hasher.finalize(output);
 }
 
-/// Hashes the input bytes using the `StatefulSponge` contract.
-pub(crate) fn hash_input_evm(evm: &mut EVM<CacheDB<EmptyDB>>, input: &[u8]) -> Result<[u8; 32]> {
-    // Absorb the data into the sponge.
-    let absorb_call = absorbCall {
-        input: input.to_vec(),
-    };
-    evm.env.tx.data = absorb_call.abi_encode().into();
-    match evm.transact_commit()? {
-        ExecutionResult::Success { .. } => { /* continue */ }
-        r => bail!("Absorb call failed: {r:?}"),
-    }
-
-    // Squeeze the sponge and retrieve the output digest.
-    let squeeze_call = squeezeCall {};
-    evm.env.tx.data = squeeze_call.abi_encode().into();
-    match evm.transact_commit()? {
-        ExecutionResult::Success {
-            output: Output::Call(hash),
-            ..
-        } => {
-            let return_data = squeezeCall::abi_decode_returns(hash.as_ref(), false)?;
-            Ok(*return_data.digest)
-        }
-        r => bail!("Squeeze call failed: {r:?}"),
+/// Hashes the input bytes using the [sha3] crate's Keccak256 implementation.
+pub fn hash_input_sha3(input: &[u8], output: &mut [u8]) {
+    let mut hasher = Keccak256::new();
+    hasher.update(input);
+    output.copy_from_slice(&hasher.finalize());
+}
+
+/// Hashes the input bytes using [alloy_primitives::keccak256].
+pub fn hash_input_alloy(input: &[u8], output: &mut [u8]) {
+    output.copy_from_slice(alloy_primitives::keccak256(input).as_slice());
+}
+
+/// Hashes the input bytes using the XKCP C reference implementation.
+#[cfg(feature = "xkcp")]
+pub fn hash_input_xkcp(input: &[u8], output: &mut [u8]) {
+    extern "C" {
+        fn lib_keccak_xkcp_keccak256(input: *const u8, input_len: usize, output: *mut u8);
     }
+
+    assert!(
+        output.len() >= 32,
+        "output buffer must hold a 32 byte digest"
+    );
+    // SAFETY: `input` is valid for `input.len()` bytes, and `output` was checked to hold the 32
+    // byte digest that the shim writes.
+    unsafe { lib_keccak_xkcp_keccak256(input.as_ptr(), input.len(), output.as_mut_ptr()) }
+}
+
+/// Hashes the input bytes using the `keccak-asm` implementation.
+#[cfg(feature = "keccak-asm")]
+pub fn hash_input_asm(input: &[u8], output: &mut [u8]) {
+    let mut hasher = keccak_asm::Keccak256::new();
+    hasher.update(input);
+    output.copy_from_slice(&hasher.finalize());
 }
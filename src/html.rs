@@ -0,0 +1,356 @@
+//! Self-contained HTML campaign reports, for sharing results with the contracts team.
+//!
+//! The report renders the campaign's summary, a gas-vs-length scatter plot, a histogram of the input
+//! lengths, the throughput over time and a table of every mismatch. The charts are inline SVG, so
+//! the page has no external dependencies. The length and throughput charts need a profiled campaign
+//! (see [DiffFuzzer::profile]).
+
+use crate::{
+    report::{Report, Signature},
+    DiffFuzzer,
+};
+use alloy_primitives::hex;
+use anyhow::{Context, Result};
+use std::{fmt::Write, fs, path::Path};
+
+/// The width of every chart, in pixels.
+const WIDTH: f64 = 720.0;
+
+/// The height of every chart, in pixels.
+const HEIGHT: f64 = 300.0;
+
+/// The margin around the plot area of every chart, in pixels: left, right, top and bottom.
+const MARGINS: (f64, f64, f64, f64) = (80.0, 20.0, 20.0, 40.0);
+
+/// The maximum number of points in the scatter plot. Longer length profiles are downsampled.
+const MAX_POINTS: usize = 2000;
+
+/// The maximum number of bars in the length histogram.
+const MAX_BINS: usize = 64;
+
+/// The number of leading input bytes shown in the failures table.
+const INPUT_PREVIEW_BYTES: usize = 32;
+
+/// Returns the HTML report of a campaign run by `config`, which finished with `outcome`.
+pub fn html_report(config: &DiffFuzzer, outcome: &Result<Report>) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>lib-keccak campaign report</title>\n<style>\n\
+         body { font-family: sans-serif; margin: 2em; color: #222; }\n\
+         table { border-collapse: collapse; margin-bottom: 1em; }\n\
+         td, th { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n\
+         code { font-size: 0.9em; }\n\
+         svg { display: block; margin-bottom: 1.5em; }\n\
+         </style>\n</head>\n<body>\n<h1>lib-keccak campaign report</h1>\n",
+    );
+
+    let report = match outcome {
+        Ok(report) => report,
+        Err(e) => {
+            writeln!(
+                html,
+                "<p>The campaign failed:</p>\n<pre>{}</pre>",
+                escape(&format!("{e:#}"))
+            )
+            .unwrap();
+            html.push_str(&summary_table(config, None));
+            html.push_str("</body>\n</html>\n");
+            return html;
+        }
+    };
+
+    writeln!(html, "<p>{}</p>", escape(&report.summary())).unwrap();
+    html.push_str(&summary_table(config, Some(report)));
+
+    html.push_str("<h2>Gas vs. input length</h2>\n");
+    html.push_str(&gas_scatter(report));
+    html.push_str("<h2>Input lengths</h2>\n");
+    html.push_str(&length_histogram(report));
+    html.push_str("<h2>Throughput</h2>\n");
+    html.push_str(&throughput_chart(report));
+    html.push_str("<h2>Mismatches</h2>\n");
+    html.push_str(&failures_table(report));
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Writes the HTML report of a campaign to `path` (see [html_report]).
+pub fn write_html(path: &Path, config: &DiffFuzzer, outcome: &Result<Report>) -> Result<()> {
+    fs::write(path, html_report(config, outcome))
+        .with_context(|| format!("Failed to write HTML report {}", path.display()))
+}
+
+/// Returns a table of the campaign's parameters and, if it finished, its gas statistics.
+fn summary_table(config: &DiffFuzzer, report: Option<&Report>) -> String {
+    let mut rows = vec![
+        ("tool version", env!("CARGO_PKG_VERSION").to_string()),
+        (
+            "seed",
+            config
+                .seed
+                .map_or_else(|| "none".to_string(), |seed| seed.to_string()),
+        ),
+        ("threads", config.thread_count.to_string()),
+        ("diff count", config.diff_count.to_string()),
+        (
+            "input lengths",
+            format!("{}..{}", config.min_input_bytes, config.max_input_bytes),
+        ),
+        ("strategy", format!("{:?}", config.strategy).to_lowercase()),
+        (
+            "backends",
+            config
+                .backends
+                .iter()
+                .map(|factory| factory().name())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+    ];
+    if let Some(report) = report.filter(|report| report.gas.samples > 0) {
+        rows.push(("total gas", report.gas.total.to_string()));
+        for (key, gas) in [
+            ("gas per input", &report.gas),
+            ("absorb gas per input", &report.absorb_gas),
+            ("squeeze gas per input", &report.squeeze_gas),
+        ] {
+            rows.push((
+                key,
+                format!(
+                    "{} min, {:.0} mean, {} max",
+                    gas.min,
+                    gas.mean().unwrap_or_default(),
+                    gas.max
+                ),
+            ));
+        }
+    }
+
+    let mut table = String::from("<table>\n");
+    for (key, value) in rows {
+        writeln!(table, "<tr><th>{key}</th><td>{}</td></tr>", escape(&value)).unwrap();
+    }
+    table.push_str("</table>\n");
+    table
+}
+
+/// Returns a scatter plot of the least and most gas used at every input length.
+fn gas_scatter(report: &Report) -> String {
+    let points = report
+        .lengths
+        .iter()
+        .filter(|(_, profile)| profile.gas.samples > 0)
+        .collect::<Vec<_>>();
+    if points.is_empty() {
+        return no_data("No gas was recorded. Profiling and the `evm` feature are required.");
+    }
+
+    let step = points.len().div_ceil(MAX_POINTS);
+    let x_max = *points.last().unwrap().0 as f64;
+    let y_max = points
+        .iter()
+        .map(|(_, profile)| profile.gas.max)
+        .max()
+        .unwrap_or_default() as f64;
+    let plot = Plot::new(x_max, y_max);
+
+    let mut content = String::new();
+    for (len, profile) in points.into_iter().step_by(step) {
+        for gas in [profile.gas.min, profile.gas.max] {
+            writeln!(
+                content,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"2\" fill=\"#1f77b4\"/>",
+                plot.x(*len as f64),
+                plot.y(gas as f64)
+            )
+            .unwrap();
+        }
+    }
+    plot.render("input length (bytes)", "gas", &content)
+}
+
+/// Returns a histogram of the number of diffed inputs by length.
+fn length_histogram(report: &Report) -> String {
+    let Some((&max_len, _)) = report.lengths.last_key_value() else {
+        return no_data("No lengths were recorded. Profiling is required.");
+    };
+
+    let bin_width = (max_len + 1).div_ceil(MAX_BINS);
+    let mut bins = vec![0u64; (max_len + 1).div_ceil(bin_width)];
+    for (len, profile) in &report.lengths {
+        bins[len / bin_width] += profile.diffs;
+    }
+
+    let x_max = (bins.len() * bin_width) as f64;
+    let plot = Plot::new(x_max, bins.iter().copied().max().unwrap_or_default() as f64);
+    let mut content = String::new();
+    for (i, count) in bins.iter().enumerate() {
+        let (x, y) = (plot.x((i * bin_width) as f64), plot.y(*count as f64));
+        writeln!(
+            content,
+            "<rect x=\"{x:.1}\" y=\"{y:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#2ca02c\"/>",
+            (plot.x(((i + 1) * bin_width) as f64) - x - 1.0).max(1.0),
+            plot.y(0.0) - y
+        )
+        .unwrap();
+    }
+    plot.render("input length (bytes)", "inputs", &content)
+}
+
+/// Returns a line chart of the throughput between consecutive samples of the timeline.
+fn throughput_chart(report: &Report) -> String {
+    let rates = report
+        .timeline
+        .windows(2)
+        .filter_map(|window| {
+            let [(t0, d0), (t1, d1)] = window else {
+                return None;
+            };
+            let secs = (*t1 - *t0).as_secs_f64();
+            (secs > 0.0).then(|| (t1.as_secs_f64(), (d1 - d0) as f64 / secs))
+        })
+        .collect::<Vec<_>>();
+    if rates.is_empty() {
+        return no_data("No throughput was recorded. Profiling is required.");
+    }
+
+    let x_max = rates.last().unwrap().0;
+    let y_max = rates.iter().map(|(_, rate)| *rate).fold(0.0, f64::max);
+    let plot = Plot::new(x_max, y_max);
+    let points = rates
+        .iter()
+        .map(|(t, rate)| format!("{:.1},{:.1}", plot.x(*t), plot.y(*rate)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let content = format!(
+        "<polyline points=\"{points}\" fill=\"none\" stroke=\"#d62728\" stroke-width=\"2\"/>\n"
+    );
+    plot.render("elapsed time (s)", "diffs per second", &content)
+}
+
+/// Returns a table of every mismatch.
+fn failures_table(report: &Report) -> String {
+    if report.failures.is_empty() {
+        return "<p>No mismatches were found.</p>\n".to_string();
+    }
+
+    let mut table = String::from(
+        "<table>\n<tr><th>context</th><th>length</th><th>stability</th><th>description</th>\
+         <th>signature</th><th>input</th></tr>\n",
+    );
+    for failure in &report.failures {
+        let preview = &failure.input[..failure.input.len().min(INPUT_PREVIEW_BYTES)];
+        let ellipsis = if preview.len() < failure.input.len() {
+            "..."
+        } else {
+            ""
+        };
+        writeln!(
+            table,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+             <td><code>0x{}{ellipsis}</code></td></tr>",
+            escape(&failure.context),
+            failure.input.len(),
+            escape(&failure.stability.to_string()),
+            escape(&failure.description),
+            escape(&Signature::of(failure).to_string()),
+            hex::encode(preview),
+        )
+        .unwrap();
+    }
+    table.push_str("</table>\n");
+    table
+}
+
+/// Returns a placeholder for a chart without data.
+fn no_data(message: &str) -> String {
+    format!("<p><em>{}</em></p>\n", escape(message))
+}
+
+/// The coordinate system of a chart, with both axes starting at zero.
+pub(crate) struct Plot {
+    /// The largest value on the x axis.
+    x_max: f64,
+    /// The largest value on the y axis.
+    y_max: f64,
+}
+
+impl Plot {
+    /// Creates a chart whose axes end at `x_max` and `y_max`.
+    pub(crate) fn new(x_max: f64, y_max: f64) -> Self {
+        Self {
+            x_max: x_max.max(1.0),
+            y_max: y_max.max(1.0),
+        }
+    }
+
+    /// Returns the horizontal pixel coordinate of `value`.
+    pub(crate) fn x(&self, value: f64) -> f64 {
+        let (left, right, _, _) = MARGINS;
+        left + value / self.x_max * (WIDTH - left - right)
+    }
+
+    /// Returns the vertical pixel coordinate of `value`.
+    pub(crate) fn y(&self, value: f64) -> f64 {
+        let (_, _, top, bottom) = MARGINS;
+        HEIGHT - bottom - value / self.y_max * (HEIGHT - top - bottom)
+    }
+
+    /// Renders the chart, with axes, tick labels and `content`, as an inline SVG element.
+    pub(crate) fn render(&self, x_label: &str, y_label: &str, content: &str) -> String {
+        let (x0, y0) = (self.x(0.0), self.y(0.0));
+        let (x1, y1) = (self.x(self.x_max), self.y(self.y_max));
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\" \
+             font-size=\"11\">\n\
+             <line x1=\"{x0:.1}\" y1=\"{y0:.1}\" x2=\"{x1:.1}\" y2=\"{y0:.1}\" stroke=\"#444\"/>\n\
+             <line x1=\"{x0:.1}\" y1=\"{y0:.1}\" x2=\"{x0:.1}\" y2=\"{y1:.1}\" stroke=\"#444\"/>\n"
+        );
+        for fraction in [0.0, 0.5, 1.0] {
+            let (x, y) = (self.x(self.x_max * fraction), self.y(self.y_max * fraction));
+            writeln!(
+                svg,
+                "<text x=\"{x:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{:.0}</text>",
+                y0 + 14.0,
+                self.x_max * fraction
+            )
+            .unwrap();
+            writeln!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{:.0}</text>",
+                x0 - 4.0,
+                y + 4.0,
+                self.y_max * fraction
+            )
+            .unwrap();
+        }
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            (x0 + x1) / 2.0,
+            HEIGHT - 6.0,
+            escape(x_label)
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<text x=\"12\" y=\"{:.1}\" text-anchor=\"middle\" transform=\"rotate(-90 12 {:.1})\">{}</text>",
+            (y0 + y1) / 2.0,
+            (y0 + y1) / 2.0,
+            escape(y_label)
+        )
+        .unwrap();
+        svg.push_str(content);
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Escapes `text` for use in HTML attributes and text.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,204 @@
+//! Machine-readable JSON run reports, so that pipelines and dashboards can consume the results of a
+//! campaign without scraping its progress output.
+//!
+//! A run report holds the campaign's parameters and seed, and either its aggregate, per-thread and
+//! gas statistics along with every mismatch, or the error that the campaign failed with (such as
+//! the first mismatch outside of keep-going mode).
+
+use crate::{
+    failure::{Coordinates, Failure},
+    report::{GasStats, Report, Signature, ThreadStats},
+    DiffFuzzer,
+};
+use alloy_primitives::hex;
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::{fs, path::Path, time::Duration};
+
+/// Returns the JSON run report of a campaign run by `config`, which finished with `outcome`.
+pub fn run_json(config: &DiffFuzzer, outcome: &Result<Report>) -> Value {
+    let (result, error) = match outcome {
+        Ok(report) => (report_json(report), Value::Null),
+        Err(e) => (Value::Null, Value::String(format!("{e:#}"))),
+    };
+    json!({
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "seed": config.seed,
+        "config": config_json(config),
+        "result": result,
+        "error": error,
+    })
+}
+
+/// Writes a JSON value to `path`, pretty-printed.
+pub fn write_json(path: &Path, value: &Value) -> Result<()> {
+    let mut text = serde_json::to_string_pretty(value)?;
+    text.push('\n');
+    fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Returns the parameters of a campaign.
+pub fn config_json(config: &DiffFuzzer) -> Value {
+    json!({
+        "thread_count": config.thread_count,
+        "shards": config.shards,
+        "diff_count": config.diff_count,
+        "duration_secs": config.duration.map(|duration| duration.as_secs_f64()),
+        "min_input_bytes": config.min_input_bytes,
+        "max_input_bytes": config.max_input_bytes,
+        "input_sizes": config.input_sizes,
+        "backends": config
+            .backends
+            .iter()
+            .map(|factory| factory().name())
+            .collect::<Vec<_>>(),
+        "strategy": format!("{:?}", config.strategy).to_lowercase(),
+        "length_distribution": format!("{:?}", config.length_distribution).to_lowercase(),
+        "pattern_ratio": config.pattern_ratio,
+        "coverage_guided": config.coverage_guided,
+        "randomize_env": config.randomize_env,
+        "expected_code_hash": config
+            .expected_code_hash
+            .map(|hash| format!("0x{}", hex::encode(hash))),
+        "specs": config.specs.iter().map(|spec| spec.name()).collect::<Vec<_>>(),
+        "build_matrix": config
+            .build_matrix
+            .iter()
+            .map(|build| build.label.as_str())
+            .collect::<Vec<_>>(),
+        "targets": config
+            .targets
+            .iter()
+            .map(|target| json!({ "label": target.label, "abi": target.abi.name() }))
+            .collect::<Vec<_>>(),
+        "contract_addresses": config
+            .contract_addresses
+            .iter()
+            .map(|address| format!("0x{}", hex::encode(address)))
+            .collect::<Vec<_>>(),
+        "absorb_chunk_bytes": config.absorb_chunk_bytes,
+        "random_chunks": config.random_chunks,
+        "tiny_chunks": config.tiny_chunks,
+        "chunking_invariance": config.chunking_invariance,
+        "squeeze_idempotence": config.squeeze_idempotence,
+        "keep_going": config.keep_going,
+        "retries": config.retries,
+    })
+}
+
+/// Returns the statistics and mismatches of a finished campaign.
+pub fn report_json(report: &Report) -> Value {
+    json!({
+        "diffs": report.diffs,
+        "regressions": report.regressions,
+        "mismatches": report.failures.len(),
+        "elapsed_secs": report.elapsed.as_secs_f64(),
+        "diffs_per_sec": throughput(report.diffs, report.elapsed),
+        "interrupted": report.interrupted,
+        "gas": gas_json(&report.gas),
+        "absorb_gas": gas_json(&report.absorb_gas),
+        "squeeze_gas": gas_json(&report.squeeze_gas),
+        "variant_gas": gas_json(&report.variant_gas),
+        "matrix_gas": report.matrix_gas.iter().map(gas_json).collect::<Vec<_>>(),
+        "target_gas": report.target_gas.iter().map(gas_json).collect::<Vec<_>>(),
+        "threads": report.threads.iter().map(thread_json).collect::<Vec<_>>(),
+        "clusters": report
+            .clusters()
+            .iter()
+            .map(|cluster| json!({
+                "signature": cluster.signature.to_string(),
+                "count": cluster.failures.len(),
+            }))
+            .collect::<Vec<_>>(),
+        "failures": report.failures.iter().map(failure_json).collect::<Vec<_>>(),
+        "gas_anomalies": report
+            .gas_anomalies
+            .iter()
+            .map(|anomaly| json!({
+                "context": anomaly.context,
+                "input_len": anomaly.len,
+                "execution_gas": anomaly.gas,
+                "mean": anomaly.mean,
+                "std_dev": anomaly.std_dev,
+                "z_score": anomaly.z_score(),
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Returns a mismatch, with everything needed to reproduce it.
+pub fn failure_json(failure: &Failure) -> Value {
+    json!({
+        "context": failure.context,
+        "input": format!("0x{}", hex::encode(&failure.input)),
+        "input_len": failure.input.len(),
+        "absorb_chunk_bytes": failure.absorb_chunk_bytes,
+        "chunk_lengths": failure.chunk_lengths,
+        "kind": failure.kind.to_string(),
+        "description": failure.description,
+        "stability": failure.stability.to_string(),
+        "signature": Signature::of(failure).to_string(),
+        "digests": digests_json(&failure.digests),
+        "cross_checks": digests_json(&failure.cross_checks),
+        "seed": failure.seed,
+        "coordinates": failure.coordinates.map(|Coordinates { shard, iteration }| json!({
+            "shard": shard,
+            "iteration": iteration,
+        })),
+        "env": failure.env.map(|env| json!({
+            "block_number": env.block_number,
+            "timestamp": env.timestamp,
+            "coinbase": format!("0x{}", hex::encode(env.coinbase)),
+            "basefee": env.basefee,
+            "origin": format!("0x{}", hex::encode(env.origin)),
+            "gas_price": env.gas_price,
+        })),
+    })
+}
+
+/// Returns the statistics of a single task.
+fn thread_json(stats: &ThreadStats) -> Value {
+    json!({
+        "thread": stats.thread,
+        "diffs": stats.diffs,
+        "mismatches": stats.mismatches,
+        "elapsed_secs": stats.elapsed.as_secs_f64(),
+        "diffs_per_sec": throughput(stats.diffs, stats.elapsed),
+        "gas": gas_json(&stats.gas),
+    })
+}
+
+/// Returns gas aggregates, with `null` minimum, maximum and mean if no gas was recorded.
+fn gas_json(gas: &GasStats) -> Value {
+    let recorded = gas.samples > 0;
+    json!({
+        "total": gas.total,
+        "min": recorded.then_some(gas.min),
+        "max": recorded.then_some(gas.max),
+        "mean": gas.mean(),
+    })
+}
+
+/// Returns the digests of named implementations as an object keyed by name.
+fn digests_json(digests: &[(&'static str, [u8; 32])]) -> Value {
+    let digests = digests
+        .iter()
+        .map(|(name, digest)| {
+            (
+                name.to_string(),
+                json!(format!("0x{}", hex::encode(digest))),
+            )
+        })
+        .collect::<Map<_, _>>();
+    Value::Object(digests)
+}
+
+/// Returns the number of diffs per second, or zero if no time elapsed.
+fn throughput(diffs: u64, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 {
+        diffs as f64 / secs
+    } else {
+        0.0
+    }
+}
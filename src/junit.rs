@@ -0,0 +1,109 @@
+//! JUnit-style XML reports, so that campaigns surface directly in standard test-result viewers.
+//!
+//! Every report has a single test suite, with a `campaign` test case that fails if the campaign
+//! errored (such as on the first mismatch outside of keep-going mode), plus a failed test case per
+//! mismatch recorded in keep-going mode:
+//!
+//! ```text
+//! <testsuites name="lib-keccak" tests="2" failures="1" errors="0" time="1.204">
+//!   <testsuite name="differential" tests="2" failures="1" errors="0" time="1.204">
+//!     <testcase classname="lib-keccak.differential" name="campaign" time="1.204"/>
+//!     <testcase classname="lib-keccak.mismatch" name="at iteration 4 of shard 24">
+//!       <failure type="HashMismatch" message="dissenting: evm">...</failure>
+//!     </testcase>
+//!   </testsuite>
+//! </testsuites>
+//! ```
+
+use crate::{
+    report::{Report, Signature},
+    DiffFuzzer,
+};
+use anyhow::{Context, Result};
+use std::{fmt::Write, fs, path::Path};
+
+/// Returns the JUnit XML report of a campaign run by `config`, which finished with `outcome`.
+pub fn junit_xml(config: &DiffFuzzer, outcome: &Result<Report>) -> String {
+    let (failures, time) = match outcome {
+        Ok(report) => (report.failures.as_slice(), report.elapsed.as_secs_f64()),
+        Err(_) => (&[][..], 0.0),
+    };
+    let errors = usize::from(outcome.is_err());
+    let counts = format!(
+        "tests=\"{}\" failures=\"{}\" errors=\"{errors}\" time=\"{time:.3}\"",
+        failures.len() + 1,
+        failures.len(),
+    );
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(xml, "<testsuites name=\"lib-keccak\" {counts}>").unwrap();
+    writeln!(xml, "  <testsuite name=\"differential\" {counts}>").unwrap();
+    if let Some(seed) = config.seed {
+        xml.push_str("    <properties>\n");
+        writeln!(xml, "      <property name=\"seed\" value=\"{seed}\"/>").unwrap();
+        xml.push_str("    </properties>\n");
+    }
+
+    let campaign = "    <testcase classname=\"lib-keccak.differential\" name=\"campaign\"";
+    match outcome {
+        Ok(_) => writeln!(xml, "{campaign} time=\"{time:.3}\"/>").unwrap(),
+        Err(e) => {
+            let message = format!("{e:#}");
+            writeln!(xml, "{campaign}>").unwrap();
+            writeln!(
+                xml,
+                "      <error type=\"CampaignError\" message=\"{}\">{}</error>",
+                escape(&message),
+                escape(&message)
+            )
+            .unwrap();
+            xml.push_str("    </testcase>\n");
+        }
+    }
+
+    for failure in failures {
+        writeln!(
+            xml,
+            "    <testcase classname=\"lib-keccak.mismatch\" name=\"{}\">",
+            escape(&failure.context)
+        )
+        .unwrap();
+        writeln!(
+            xml,
+            "      <failure type=\"HashMismatch\" message=\"{}\">{}&#10;signature: {}</failure>",
+            escape(&failure.description),
+            escape(&failure.to_string()),
+            escape(&Signature::of(failure).to_string())
+        )
+        .unwrap();
+        xml.push_str("    </testcase>\n");
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Writes the JUnit XML report of a campaign to `path` (see [junit_xml]).
+pub fn write_junit(path: &Path, config: &DiffFuzzer, outcome: &Result<Report>) -> Result<()> {
+    fs::write(path, junit_xml(config, outcome))
+        .with_context(|| format!("Failed to write JUnit report {}", path.display()))
+}
+
+/// Escapes `text` for use in XML attributes and character data, preserving line breaks.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' | '\r' | '\t' => write!(escaped, "&#{};", c as u32).unwrap(),
+            // Other control characters can't be represented in XML 1.0 at all.
+            c if c.is_control() => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,104 @@
+//! Length distributions for randomly generated inputs.
+
+use crate::permutation::RATE_BYTES;
+use rand::Rng;
+use std::ops::Range;
+
+/// The mean of [LengthDistribution::Geometric] lengths, as a fraction of the maximum length.
+const GEOMETRIC_MEAN_DIVISOR: usize = 8;
+
+/// How the lengths of randomly generated inputs are sampled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum LengthDistribution {
+    /// Lengths are uniformly distributed below the maximum.
+    #[default]
+    Uniform,
+    /// Lengths sit one byte short of, exactly on, or one byte past a multiple of the rate, where the
+    /// `10*1` padding logic is most fragile.
+    Boundary,
+    /// Lengths are geometrically distributed, biased towards small inputs, with a mean of an eighth
+    /// of the maximum.
+    Geometric,
+    /// Every length bucket below the maximum, i.e. every number of full blocks followed by a final
+    /// block that is empty, one byte short of full, or anything else, is equally likely, so that
+    /// inputs spanning many blocks are sampled as often as short ones.
+    Bucketed,
+}
+
+impl LengthDistribution {
+    /// Samples an input length within `range`, which must not be empty.
+    pub fn sample<R: Rng>(self, rng: &mut R, range: Range<usize>) -> usize {
+        let len = match self {
+            Self::Uniform => rng.gen_range(range.clone()),
+            Self::Boundary => {
+                let multiple =
+                    rng.gen_range(range.start / RATE_BYTES..=range.end / RATE_BYTES) * RATE_BYTES;
+                match rng.gen_range(0..3) {
+                    0 => multiple.saturating_sub(1),
+                    1 => multiple,
+                    _ => multiple + 1,
+                }
+            }
+            Self::Geometric => {
+                let mean = (range.len() / GEOMETRIC_MEAN_DIVISOR).max(1) as f64;
+                let uniform = 1.0 - rng.gen::<f64>();
+                range.start + (uniform.ln() / (1.0 - 1.0 / (mean + 1.0)).ln()) as usize
+            }
+            Self::Bucketed => {
+                let start = rng.gen_range(range.start / RATE_BYTES..=(range.end - 1) / RATE_BYTES)
+                    * RATE_BYTES;
+                match rng.gen_range(0..3) {
+                    0 => start,
+                    1 => start + RATE_BYTES - 1,
+                    _ => rng.gen_range(start..start + RATE_BYTES),
+                }
+            }
+        };
+        len.clamp(range.start, range.end - 1)
+    }
+}
+
+/// A schedule that ramps the exclusive upper bound on input lengths up from `start_bytes` to the
+/// campaign's maximum, so that early iterations cheaply cover the small-length padding cases before
+/// the campaign moves on to expensive large inputs. Lengths sampled from an explicit set of input
+/// sizes are not ramped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ramp {
+    /// The exclusive upper bound on input lengths at the start of the ramp.
+    pub start_bytes: usize,
+    /// The number of diffs, across the whole campaign, that the ramp spans. Defaults to the diff
+    /// count of the campaign, and is required for time-limited and unbounded campaigns.
+    pub diffs: Option<u64>,
+    /// How the bound grows over the ramp.
+    pub curve: RampCurve,
+}
+
+/// How the bound of a [Ramp] grows from its start to the maximum.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum RampCurve {
+    /// The bound grows by the same number of bytes at every iteration.
+    #[default]
+    Linear,
+    /// The bound grows by the same factor at every iteration, spending as many iterations below a
+    /// single block as between, e.g., one and two hundred blocks.
+    Exponential,
+}
+
+impl Ramp {
+    /// Returns the exclusive upper bound on input lengths at `progress` through the ramp, between 0
+    /// and 1, towards a final bound of `max_bytes`.
+    pub fn max_input_bytes(&self, progress: f64, max_bytes: usize) -> usize {
+        let progress = progress.clamp(0.0, 1.0);
+        let start = self.start_bytes.clamp(1, max_bytes.max(1));
+        let bound = match self.curve {
+            RampCurve::Linear => start + ((max_bytes - start) as f64 * progress) as usize,
+            RampCurve::Exponential => {
+                let start = start as f64;
+                (start * (max_bytes as f64 / start).powf(progress)) as usize
+            }
+        };
+        bound.clamp(start, max_bytes.max(1))
+    }
+}
@@ -0,0 +1,75 @@
+//! Differential fuzzing harness for `LibKeccak`.
+//!
+//! The harness deploys the `StatefulSponge` contract into an in-memory [revm] instance and compares
+//! its digests against native Keccak256 implementations. The [DiffFuzzer] drives the campaign, and
+//! the lower-level [harness] and [hashing] modules are exposed for embedding the engine elsewhere.
+//!
+//! Everything that depends on revm is gated behind the default `evm` feature. Without it, the crate
+//! is a slim native-only diff engine that compares the reference backends against each other.
+
+pub mod arbitration;
+pub mod baseline;
+pub mod checkpoint;
+pub mod compare;
+#[cfg(all(feature = "evm", feature = "json"))]
+pub mod compile;
+#[cfg(feature = "evm")]
+pub mod constants;
+pub mod corpus;
+#[cfg(feature = "evm")]
+pub mod coverage;
+pub mod doctor;
+pub mod engine;
+pub mod failure;
+pub mod fuzzer;
+pub mod gas;
+#[cfg(feature = "evm")]
+pub mod harness;
+pub mod hashing;
+pub mod html;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod junit;
+pub mod lengths;
+pub mod lifecycle;
+pub mod manifest;
+pub mod metrics;
+pub mod minimize;
+pub mod model;
+pub mod mutation;
+pub mod oog;
+#[cfg(feature = "evm")]
+pub mod opcodes;
+pub mod patterns;
+pub mod payloads;
+pub mod permutation;
+#[cfg(feature = "proptest")]
+pub mod property;
+pub mod report;
+pub mod reporter;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod selftest;
+pub mod soak;
+#[cfg(all(feature = "evm", feature = "json"))]
+pub mod sourcemap;
+pub mod spec;
+pub mod sponge;
+pub mod stats;
+#[cfg(feature = "arbitrary")]
+pub mod structured;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod xof;
+
+pub use engine::DiffEngine;
+pub use fuzzer::DiffFuzzer;
+#[cfg(feature = "evm")]
+pub use harness::{
+    deploy_contract, hash_chunks_evm, hash_chunks_evm_inspected, hash_chunks_evm_inspected_metered,
+    hash_chunks_evm_metered, hash_input_evm, hash_input_evm_inspected, init_evm,
+};
+pub use hashing::{
+    available_backends, hash_input_alloy, hash_input_sha3, hash_input_tiny, AlloyKeccak,
+    BackendFactory, HashBackend, NativeKeccak, Sha3Keccak, TinyKeccak,
+};
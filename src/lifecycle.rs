@@ -0,0 +1,383 @@
+//! An explicit model of the `StatefulSponge` contract across calls, to diff what the contract does
+//! when `absorb` is called again after `squeeze`.
+//!
+//! Every campaign reuses a single deployment of the contract for all of its inputs, so it relies on
+//! `squeeze` leaving the contract as if it had just been deployed, as the
+//! [crate::sponge::SpongeModel] spells out.
+//!
+//! [diff_lifecycle] runs random schedules of calls, such as absorb-absorb-squeeze-absorb-squeeze
+//! or several squeezes in a row, against both the contract and the model, and requires every
+//! squeeze to return the digest of the model. The model is in turn checked against the intended
+//! semantics, the reference digests of the chunks absorbed since the last squeeze (see
+//! [crate::sponge::SpongeModel::intended_digest]), and a diverging schedule is shrunk into a
+//! minimal reproducer.
+//!
+//! The calls can also be interleaved among several instances of the contract, deployed at
+//! different addresses of the same EVM, each with an independent input stream and a model of its
+//! own, so that any state shared across the instances, e.g. through a storage layout mistake, makes
+//! an instance diverge from its model.
+//!
+//! For targets that expose a `reset()` entrypoint, the schedules can also reset the sponge without
+//! squeezing it, after which it must behave exactly like a fresh deployment.
+
+use crate::{engine::Deployment, sponge::SpongeCall, DiffFuzzer};
+use anyhow::{bail, Result};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::fmt;
+
+/// The largest number of instances of the contract that calls can be interleaved among.
+pub const MAX_INSTANCES: usize = 8;
+
+/// A call of one of the instances of the contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceCall {
+    /// The index of the instance, counted from 0.
+    pub instance: usize,
+    /// The call.
+    pub call: SpongeCall,
+}
+
+impl fmt::Display for InstanceCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {}", self.instance + 1, self.call)
+    }
+}
+
+/// A squeeze that an instance of the contract returned another digest for than its model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecycleDivergence {
+    /// The index of the sequence of calls.
+    pub sequence: u64,
+    /// The number of calls of the sequence up to and including the diverging squeeze.
+    pub original_calls: usize,
+    /// The calls of the sequence up to and including the diverging squeeze, shrunk into a minimal
+    /// sequence that diverges from a fresh deployment, if it does so at all.
+    pub calls: Vec<InstanceCall>,
+    /// The digest squeezed by the contract.
+    pub contract: [u8; 32],
+    /// The digest squeezed by the model.
+    pub model: [u8; 32],
+}
+
+/// The outcome of diffing sequences of calls against the model (see [diff_lifecycle]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LifecycleReport {
+    /// The number of instances of the contract that the calls were interleaved among.
+    pub instances: usize,
+    /// The number of sequences that were diffed.
+    pub sequences: u64,
+    /// The number of squeezes that were diffed.
+    pub squeezes: u64,
+    /// The number of squeezes that directly followed another squeeze of the same instance, i.e. of
+    /// the reset state.
+    pub repeated_squeezes: u64,
+    /// The number of absorbs that directly followed a squeeze of the same instance, i.e. into the
+    /// reset state.
+    pub absorbs_after_squeeze: u64,
+    /// The number of resets that were called.
+    pub resets: u64,
+    /// The number of absorbs that directly followed a reset of the same instance.
+    pub absorbs_after_reset: u64,
+    /// The first squeeze that the contract diverged from the model on, if any.
+    pub divergence: Option<LifecycleDivergence>,
+}
+
+/// Generates sequence `sequence` of `calls` random calls, each of a random one of `instances`
+/// instances of the contract, followed by a final squeeze of every instance. The chunks are the
+/// inputs that the fuzzer generates from scratch (see [DiffFuzzer::generate_fresh]), with the
+/// sequence as the shard, so that they follow its length distribution, and so that every instance
+/// absorbs inputs of its own. With `resets`, half of the calls that don't absorb are resets
+/// instead of squeezes. Requires the fuzzer's seed.
+pub fn random_calls(
+    fuzzer: &DiffFuzzer,
+    sequence: u64,
+    calls: usize,
+    instances: usize,
+    resets: bool,
+) -> Result<Vec<InstanceCall>> {
+    let Some(seed) = fuzzer.seed else {
+        bail!("Generating calls requires the seed of the campaign");
+    };
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    rng.set_stream(sequence);
+    let mut sequence_calls = Vec::with_capacity(calls + instances);
+    for call in 0..calls as u64 {
+        let instance = if instances > 1 {
+            rng.gen_range(0..instances)
+        } else {
+            0
+        };
+        let call = if rng.gen_bool(2.0 / 3.0) {
+            SpongeCall::Absorb(fuzzer.generate_fresh(sequence, call)?)
+        } else if resets && rng.gen_bool(0.5) {
+            SpongeCall::Reset
+        } else {
+            SpongeCall::Squeeze
+        };
+        sequence_calls.push(InstanceCall { instance, call });
+    }
+    sequence_calls.extend((0..instances).map(|instance| InstanceCall {
+        instance,
+        call: SpongeCall::Squeeze,
+    }));
+    Ok(sequence_calls)
+}
+
+/// Runs `sequences` random sequences of `calls` calls each (see [random_calls]) against `instances`
+/// instances of the fuzzer's contract, deployed once in the same EVM under the latest spec as
+/// configured (see [DiffFuzzer::deployment]), with a fresh model per instance and sequence,
+/// stopping at the first squeeze that diverges from the model of its instance. As every sequence
+/// ends with a squeeze of every instance, every sequence past the first also checks that the
+/// instances were reset. Every squeeze of a model must also return its
+/// [crate::sponge::SpongeModel::intended_digest], which fails the run otherwise, as the model
+/// itself is broken then.
+///
+/// With `resets`, the sequences also reset the instances (see [random_calls]), which requires the
+/// contract to expose a `reset()` entrypoint.
+///
+/// A diverging sequence is shrunk into a minimal one that diverges from a fresh deployment (see
+/// [shrink_calls]), unless it only diverges after the sequences before it. Requires the `evm`
+/// feature, and the fuzzer's seed.
+pub fn diff_lifecycle(
+    fuzzer: &DiffFuzzer,
+    sequences: u64,
+    calls: usize,
+    instances: usize,
+    resets: bool,
+) -> Result<LifecycleReport> {
+    if !(1..=MAX_INSTANCES).contains(&instances) {
+        bail!("Between 1 and {MAX_INSTANCES} instances are supported, got {instances}");
+    }
+    #[cfg(feature = "evm")]
+    {
+        use anyhow::Context;
+
+        let deployment = fuzzer.deployment();
+        let mut contracts = init_instances(&deployment, instances)?;
+        if resets {
+            crate::harness::reset_evm(&mut init_instances(&deployment, 1)?.evm)
+                .context("Resetting requires a `reset()` entrypoint, which the contract lacks")?;
+        }
+        let mut report = LifecycleReport {
+            instances,
+            ..Default::default()
+        };
+        for sequence in 0..sequences {
+            let sequence_calls = random_calls(fuzzer, sequence, calls, instances, resets)?;
+            report.sequences += 1;
+            // The previous sequence ended with a squeeze of every instance.
+            let mut previous = vec![(sequence > 0).then_some(SpongeCall::Squeeze); instances];
+            for InstanceCall { instance, call } in &sequence_calls {
+                let previous = &mut previous[*instance];
+                match call {
+                    SpongeCall::Absorb(_) => {
+                        report.absorbs_after_squeeze +=
+                            u64::from(*previous == Some(SpongeCall::Squeeze));
+                        report.absorbs_after_reset +=
+                            u64::from(*previous == Some(SpongeCall::Reset));
+                    }
+                    SpongeCall::Squeeze => {
+                        report.squeezes += 1;
+                        report.repeated_squeezes +=
+                            u64::from(*previous == Some(SpongeCall::Squeeze));
+                    }
+                    SpongeCall::Reset => report.resets += 1,
+                }
+                *previous = Some(call.clone());
+            }
+            let Some((squeeze, contract, model)) = run_calls(&mut contracts, &sequence_calls)?
+            else {
+                continue;
+            };
+            let mut calls = sequence_calls[..=squeeze].to_vec();
+            let original_calls = calls.len();
+            let (mut contract, mut model) = (contract, model);
+            if run_calls(&mut init_instances(&deployment, instances)?, &calls)?.is_some() {
+                calls = shrink_calls(&deployment, calls, instances)?;
+                if let Some((_, shrunk_contract, shrunk_model)) =
+                    run_calls(&mut init_instances(&deployment, instances)?, &calls)?
+                {
+                    (contract, model) = (shrunk_contract, shrunk_model);
+                }
+            }
+            report.divergence = Some(LifecycleDivergence {
+                sequence,
+                original_calls,
+                calls,
+                contract,
+                model,
+            });
+            return Ok(report);
+        }
+        Ok(report)
+    }
+    #[cfg(not(feature = "evm"))]
+    {
+        let _ = (fuzzer, sequences, calls, resets);
+        bail!("Diffing the lifecycle of the contract requires the `evm` feature")
+    }
+}
+
+/// Shrinks `calls` of `instances` instances of the contract, which must diverge from the models on
+/// a fresh deployment as in `deployment`, into a minimal sequence that still does, ending with the
+/// diverging squeeze: every call that the divergence doesn't depend on is removed, and then every
+/// absorbed chunk is shrunk like an input (see [crate::minimize::shrink_bytes]). Requires the `evm`
+/// feature.
+pub fn shrink_calls(
+    deployment: &Deployment,
+    calls: Vec<InstanceCall>,
+    instances: usize,
+) -> Result<Vec<InstanceCall>> {
+    #[cfg(feature = "evm")]
+    {
+        use crate::minimize::shrink_bytes;
+
+        let diverges = |calls: &[InstanceCall]| -> Result<bool> {
+            Ok(run_calls(&mut init_instances(deployment, instances)?, calls)?.is_some())
+        };
+        let mut calls = calls;
+        let mut i = 0;
+        while i + 1 < calls.len() {
+            let mut candidate = calls.clone();
+            candidate.remove(i);
+            if diverges(&candidate)? {
+                calls = candidate;
+            } else {
+                i += 1;
+            }
+        }
+        for i in 0..calls.len() {
+            let SpongeCall::Absorb(chunk) = &calls[i].call else {
+                continue;
+            };
+            let chunk = shrink_bytes(chunk, |candidate| {
+                let mut candidate_calls = calls.clone();
+                candidate_calls[i].call = SpongeCall::Absorb(candidate.to_vec());
+                diverges(&candidate_calls)
+            })?;
+            calls[i].call = SpongeCall::Absorb(chunk);
+        }
+        // Drop the calls past the first diverging squeeze, which shrinking may have moved forward.
+        if let Some((squeeze, _, _)) =
+            run_calls(&mut init_instances(deployment, instances)?, &calls)?
+        {
+            calls.truncate(squeeze + 1);
+        }
+        Ok(calls)
+    }
+    #[cfg(not(feature = "evm"))]
+    {
+        let _ = (deployment, calls, instances);
+        bail!("Shrinking calls of the contract requires the `evm` feature")
+    }
+}
+
+/// Returns the address of instance `instance` of the contract when no addresses are configured:
+/// the usual address of the contract for the first instance, and a distinct one for every other.
+#[cfg(feature = "evm")]
+fn instance_address(instance: usize) -> revm::primitives::Address {
+    match instance {
+        0 => crate::constants::STATEFUL_SPONGE_ADDR,
+        instance => revm::primitives::Address::repeat_byte(0x11 * instance as u8),
+    }
+}
+
+/// An EVM with several instances of the contract deployed (see [init_instances]).
+#[cfg(feature = "evm")]
+struct Instances {
+    evm: revm::EVM<crate::harness::HarnessDB>,
+    /// The address of every instance, by index.
+    addresses: Vec<revm::primitives::Address>,
+}
+
+/// Initializes an EVM with `instances` instances of the contract under the latest spec, deployed
+/// as in `deployment`: with a creation transaction each if it has initcode, and otherwise at its
+/// addresses, followed by those of [instance_address] for the instances past them.
+#[cfg(feature = "evm")]
+fn init_instances(deployment: &Deployment, instances: usize) -> Result<Instances> {
+    use revm::primitives::{Address, SpecId, TransactTo};
+
+    if let Some(initcode) = deployment.initcode.as_deref() {
+        let mut evm = crate::harness::init_evm_with_initcode(SpecId::LATEST, initcode)?;
+        let TransactTo::Call(first) = evm.env.tx.transact_to else {
+            bail!("The creation transaction of the contract didn't create it");
+        };
+        let mut addresses = vec![first];
+        for _ in 1..instances {
+            addresses.push(crate::harness::create_contract(&mut evm, initcode)?);
+        }
+        return Ok(Instances { evm, addresses });
+    }
+
+    let mut addresses = deployment
+        .addresses
+        .iter()
+        .map(|&address| Address::from(address))
+        .take(instances)
+        .collect::<Vec<_>>();
+    addresses.extend((addresses.len()..instances).map(instance_address));
+    if let Some(address) = addresses
+        .iter()
+        .enumerate()
+        .find_map(|(i, address)| addresses[..i].contains(address).then_some(address))
+    {
+        bail!("Two instances of the contract would be deployed at {address}");
+    }
+    let evm =
+        crate::harness::init_evm_at(SpecId::LATEST, crate::harness::contract_code()?, &addresses)?;
+    Ok(Instances { evm, addresses })
+}
+
+/// The index of a squeeze that the contract and the model diverge on, with the digest of each.
+#[cfg(feature = "evm")]
+type SqueezeDivergence = (usize, [u8; 32], [u8; 32]);
+
+/// Executes `calls` against the instances of the contract in `instances` and a fresh model of each
+/// side by side, returning the index of the first squeeze that an instance
+/// diverges from its model on, with the digests of the instance and the model, if any.
+#[cfg(feature = "evm")]
+fn run_calls(
+    instances: &mut Instances,
+    calls: &[InstanceCall],
+) -> Result<Option<SqueezeDivergence>> {
+    use crate::{
+        harness::{absorb_evm, reset_evm, squeeze_evm},
+        sponge::SpongeModel,
+    };
+    use alloy_primitives::hex;
+    use revm::primitives::TransactTo;
+
+    let Instances { evm, addresses } = instances;
+    let mut models = vec![SpongeModel::new(); MAX_INSTANCES];
+    for (i, InstanceCall { instance, call }) in calls.iter().enumerate() {
+        evm.env.tx.transact_to = TransactTo::Call(addresses[*instance]);
+        let model = &mut models[*instance];
+        match call {
+            SpongeCall::Absorb(chunk) => {
+                absorb_evm(evm, chunk)?;
+                model.absorb(chunk);
+            }
+            SpongeCall::Reset => {
+                reset_evm(evm)?;
+                model.reset();
+            }
+            SpongeCall::Squeeze => {
+                let contract = squeeze_evm(evm)?;
+                let intended = model.intended_digest();
+                let digest = model.squeeze();
+                if digest != intended {
+                    bail!(
+                        "The model squeezed 0x{} at call {i}, but the intended digest is 0x{}",
+                        hex::encode(digest),
+                        hex::encode(intended)
+                    );
+                }
+                if contract != digest {
+                    return Ok(Some((i, contract, digest)));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
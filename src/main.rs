@@ -1,132 +1,1753 @@
-use std::fmt::Write;
-
-use anyhow::{bail, Result};
-use clap::Parser;
-use indicatif::{MultiProgress, ProgressBar, ProgressState, ProgressStyle};
-use rand::Rng;
-use revm::{
-    db::{CacheDB, DatabaseRef, EmptyDB},
-    primitives::{hex, AccountInfo, Bytecode, TransactTo, U256},
-    EVM,
+use alloy_primitives::hex;
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use indicatif::{MultiProgress, ProgressDrawTarget};
+#[cfg(feature = "json")]
+use lib_keccak::reporter::{JsonReporter, JsonlReporter};
+#[cfg(feature = "tui")]
+use lib_keccak::tui::Dashboard;
+use lib_keccak::{
+    baseline::{GasBaseline, GasBaselineCheck, GasBaselineRecorder},
+    checkpoint::Checkpoint,
+    compare::{bisect_builds, compare_builds, Regression},
+    engine::{absorb_chunks, MatrixBuild, Target, TargetAbi},
+    failure::read_input,
+    gas::{
+        block_costs, gas_curve_svg, measure_gas_curve, write_gas_curve_csv, write_gas_snapshot,
+        GasCsv,
+    },
+    hashing,
+    lengths::{LengthDistribution, Ramp, RampCurve},
+    lifecycle::diff_lifecycle,
+    manifest::ManifestReporter,
+    minimize::minimize,
+    model::GasModelReporter,
+    mutation::Strategy,
+    oog::{max_input, measure_oog_thresholds, write_oog_csv, GasFit},
+    payloads::Payload,
+    reporter::{ConsoleReporter, HtmlReporter, JunitReporter},
+    soak::Soak,
+    spec::EvmSpec,
+    sponge::SpongeCall,
+    xof::{diff_squeezes, XofPadding},
+    BackendFactory, DiffFuzzer,
 };
-use tokio::task::JoinSet;
-
-mod constants;
-use constants::{STATEFUL_SPONGE_ADDR, STATEFUL_SPONGE_BYTECODE};
-
-mod hashing;
-use hashing::{hash_input_evm, hash_input_tiny};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::IsTerminal,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::Duration,
+};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
 /// CLI args for the fuzzing tool.
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(short, long, default_value = "4")]
     thread_count: u64,
 
+    /// The number of shards that the campaign's iterations are divided into, and the maximum
+    /// thread count. The generated inputs depend on the shard count, but not on the thread count.
+    #[arg(long, default_value = "64")]
+    shards: u64,
+
+    /// The total number of diffs to perform. If zero, fuzz until interrupted.
     #[arg(short, long, default_value = "100000")]
     diff_count: u64,
 
+    /// Fuzz until interrupted, printing periodic status lines. Equivalent to `--diff-count 0`.
+    #[arg(long, conflicts_with_all = ["diff_count", "duration"])]
+    forever: bool,
+
+    /// Fuzz for a fixed amount of wall time instead of a fixed number of diffs, e.g. `90s`, `30m`,
+    /// `2h` or `1h30m`.
+    #[arg(long, value_parser = parse_duration, conflicts_with = "diff_count")]
+    duration: Option<Duration>,
+
     #[arg(short, long, default_value = "100")]
     max_input_bytes: usize,
+
+    /// The inclusive lower bound on the length of randomly generated inputs.
+    #[arg(long, default_value = "0")]
+    min_input_bytes: usize,
+
+    /// A comma-separated list of lengths to sample randomly generated inputs from, overriding the
+    /// length bounds and distribution.
+    #[arg(long, value_delimiter = ',')]
+    input_sizes: Vec<usize>,
+
+    /// Fill every input with exactly this many random bytes, e.g. to chase a suspected bug at a
+    /// specific padding boundary. Implies the `random` strategy.
+    #[arg(
+        long,
+        conflicts_with_all = ["min_input_bytes", "input_sizes", "strategy", "length_distribution"]
+    )]
+    fixed_length: Option<usize>,
+
+    /// The reference implementations to diff the contract against. Defaults to every backend
+    /// compiled into this build.
+    #[arg(short, long, value_enum, value_delimiter = ',')]
+    backends: Vec<Backend>,
+
+    /// How new inputs are generated.
+    #[arg(short, long, value_enum, default_value_t = Strategy::default())]
+    strategy: Strategy,
+
+    /// The kinds of payload generated by the `payloads` strategy. Defaults to every kind.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    payloads: Vec<Payload>,
+
+    /// How the lengths of randomly generated inputs are sampled.
+    #[arg(short, long, value_enum, default_value_t = LengthDistribution::default())]
+    length_distribution: LengthDistribution,
+
+    /// Ramp the maximum input length up from this many bytes to `--max-input-bytes` over the
+    /// campaign, so that small-length padding cases are covered first.
+    #[arg(long)]
+    ramp_from: Option<usize>,
+
+    /// The number of diffs that the ramp spans. Defaults to `--diff-count`, and is required with
+    /// `--duration` and `--forever`.
+    #[arg(long, requires = "ramp_from")]
+    ramp_diffs: Option<u64>,
+
+    /// How the maximum input length grows over the ramp.
+    #[arg(long, value_enum, default_value_t = RampCurve::default(), requires = "ramp_from")]
+    ramp_curve: RampCurve,
+
+    /// The fraction of randomly generated inputs, between 0 and 1, that are filled with
+    /// deterministic byte patterns (all-zero, all-0xFF, repeating, counting, ...) instead.
+    #[arg(long, default_value = "0")]
+    pattern_ratio: f64,
+
+    /// Use program-counter coverage feedback to decide which inputs are novel enough to keep in
+    /// the corpus that inputs are mutated from.
+    #[arg(short, long)]
+    coverage: bool,
+
+    /// Also diff the contract under each of these EVM hard forks, e.g. `london,shanghai,cancun`,
+    /// and require every fork to squeeze the same digest.
+    #[arg(long = "spec", value_delimiter = ',')]
+    specs: Vec<EvmSpec>,
+
+    /// A file with the hex runtime bytecode of the contract to test, e.g. a local build, instead of
+    /// the one that the tool was built with (`testdata/stateful_sponge`).
+    #[arg(long, value_name = "FILE")]
+    bytecode_path: Option<PathBuf>,
+
+    /// The Foundry artifact of the contract to test, e.g. `out/StatefulSponge.sol/StatefulSponge.json`,
+    /// whose deployed bytecode (and creation bytecode, with `--deploy-via-create`) is tested
+    /// instead of the one that the tool was built with. Requires the `json` feature.
+    #[arg(long, value_name = "FILE", conflicts_with = "bytecode_path")]
+    artifact: Option<PathBuf>,
+
+    /// Compile the contract to test before fuzzing it: a Foundry project root is built with `forge
+    /// build`, and a Solidity source is compiled with `solc`. Requires the `json` feature.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["bytecode_path", "artifact"])]
+    build: Option<PathBuf>,
+
+    /// The contract to compile with `--build`.
+    #[arg(long, default_value = "StatefulSponge", requires = "build")]
+    build_contract: String,
+
+    /// A file with the hex runtime bytecode of another build of the contract, e.g. one that copies
+    /// memory with MCOPY, to diff against the contract under the Cancun spec and compare gas with.
+    #[arg(long, value_name = "FILE")]
+    variant_bytecode: Option<PathBuf>,
+
+    /// Files with other builds of the contract, e.g. built by other solc versions, to diff against
+    /// the contract on every input and compare gas with: Foundry artifacts (`.json`, with the
+    /// `json` feature) or hex runtime bytecode. Up to 8.
+    #[arg(long = "matrix-build", value_name = "FILE", value_delimiter = ',')]
+    matrix_builds: Vec<PathBuf>,
+
+    /// Also compile the `--build` contract under each of these optimizer settings, e.g.
+    /// `runs=200,runs=200+via-ir,via-ir`, and add every build to the build matrix, to diff the
+    /// legacy and via-IR pipelines side by side. Requires the `json` feature.
+    #[arg(
+        long,
+        value_name = "SETTINGS",
+        value_delimiter = ',',
+        requires = "build"
+    )]
+    matrix_settings: Vec<String>,
+
+    /// Other contracts of the `LibKeccak` family to run every input through, as `<ABI>:<FILE>`
+    /// with the interface of the contract, `sponge` (`absorb` and `squeeze`), `hash-bytes` (a
+    /// one-shot `hash(bytes)`) or `raw` (the input as calldata, returning the digest), and its
+    /// Foundry artifact (`.json`, with the `json` feature) or hex runtime bytecode. Up to 8.
+    #[arg(long = "target", value_name = "ABI:FILE", value_delimiter = ',')]
+    targets: Vec<String>,
+
+    /// A Huff implementation of the sponge to compile with `huffc` and run every input through as
+    /// a target, reporting its gas next to the contract's. Requires the `json` feature.
+    #[arg(long, value_name = "FILE")]
+    huff: Option<PathBuf>,
+
+    /// The interface of the `--huff` contract: `sponge`, `hash-bytes` or `raw` (see `--target`).
+    #[arg(long, default_value = "sponge", requires = "huff")]
+    huff_abi: TargetAbi,
+
+    /// A pure-Yul object of the sponge to compile with `solc --strict-assembly`, deploy through its
+    /// initcode and run every input through as a target. Requires the `json` feature.
+    #[arg(long, value_name = "FILE")]
+    yul: Option<PathBuf>,
+
+    /// The interface of the `--yul` object's runtime code: `sponge`, `hash-bytes` or `raw` (see
+    /// `--target`).
+    #[arg(long, default_value = "sponge", requires = "yul")]
+    yul_abi: TargetAbi,
+
+    /// Deploy the contract with a creation transaction instead of inserting its runtime bytecode.
+    /// Without `--creation-bytecode`, the initcode just returns the runtime bytecode.
+    #[arg(long)]
+    deploy_via_create: bool,
+
+    /// Deploy the contract at these addresses instead of the default one. With several
+    /// comma-separated addresses, every input is hashed in interleaved sessions of the contracts, which
+    /// must all squeeze the same digest.
+    #[arg(
+        long = "contract-address",
+        value_name = "ADDRESS",
+        value_delimiter = ','
+    )]
+    contract_addresses: Vec<alloy_primitives::Address>,
+
+    /// A file with the hex creation bytecode of the contract (e.g. `bytecode.object` of its
+    /// Foundry artifact), to deploy it with, running its constructor. Implies
+    /// `--deploy-via-create`.
+    #[arg(long, value_name = "FILE")]
+    creation_bytecode: Option<PathBuf>,
+
+    /// Fail before fuzzing unless the Keccak256 hash of the contract's runtime bytecode is this one,
+    /// so that a campaign can't run against a stale or wrong `testdata/stateful_sponge`.
+    #[arg(long, value_name = "HASH")]
+    expected_codehash: Option<alloy_primitives::B256>,
+
+    /// Execute the contract calls of every iteration with a random block number, timestamp,
+    /// coinbase, base fee, origin and gas price, which the digests must not depend on.
+    #[arg(long)]
+    randomize_env: bool,
+
+    /// Split inputs longer than this many bytes across several absorb calls, e.g. to test
+    /// multi-megabyte inputs with `--min-input-bytes 1048576 -m 16777216`.
+    #[arg(long)]
+    absorb_chunk_bytes: Option<usize>,
+
+    /// Split every generated input at random into 1 to this many chunks, each absorbed in its own
+    /// call, to exercise the sponge across arbitrary call boundaries. The lengths of the chunks of
+    /// every mismatch are recorded with it.
+    #[arg(long, value_name = "N", conflicts_with = "absorb_chunk_bytes")]
+    random_chunks: Option<usize>,
+
+    /// Interleave empty and 1 byte absorb calls among the chunks of every generated input, whose
+    /// padding the digest must account for. Requires `--random-chunks` or `--absorb-chunk-bytes`.
+    #[arg(long)]
+    tiny_chunks: bool,
+
+    /// Also absorb every chunked input in a single call, which must squeeze the same digest as the
+    /// chunked calls, to tell bugs at the call boundaries from padding bugs. Requires
+    /// `--random-chunks` or `--absorb-chunk-bytes`.
+    #[arg(long)]
+    chunking_invariance: bool,
+
+    /// Squeeze the contract a second time after every input, which must return the all-zero digest
+    /// of the reset sponge, and report any other digest as a squeeze corruption.
+    #[arg(long)]
+    squeeze_idempotence: bool,
+
+    /// A directory of seed inputs to replay before generating random inputs.
+    #[arg(long)]
+    corpus: Option<PathBuf>,
+
+    /// A JSON-RPC endpoint to fetch recent transaction calldata and log data from, which is
+    /// replayed as seed inputs. Requires the `rpc` feature.
+    #[arg(long)]
+    rpc_url: Option<String>,
+
+    /// The number of recent blocks to fetch payloads from with `--rpc-url`.
+    #[arg(long, default_value = "8", requires = "rpc_url")]
+    rpc_blocks: u64,
+
+    /// A directory of previously-failing inputs to replay before anything else. Every new failing
+    /// input is added to it. Defaults to `regressions` in the current directory, which is created
+    /// on the first failure.
+    #[arg(long, default_value = "regressions")]
+    regressions: PathBuf,
+
+    /// Don't replay or add to the regression directory.
+    #[arg(long)]
+    no_regressions: bool,
+
+    /// A directory to write novel inputs (new length buckets or new coverage) to.
+    #[arg(long)]
+    corpus_out: Option<PathBuf>,
+
+    /// A directory to write failing inputs and their run metadata to.
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// A file to write the run's manifest to when the campaign starts: the tool and revm versions,
+    /// the contract code hash, the command line and the seed. Defaults to `manifest.json` in
+    /// `--output-dir`, if set.
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// A file to write a machine-readable JSON report of the campaign to when it exits. Requires
+    /// the `json` feature.
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    /// A file to stream every mismatch to as a single line of JSON as soon as it is found, or `-`
+    /// for stdout. Requires the `json` feature.
+    #[arg(long)]
+    mismatch_stream: Option<PathBuf>,
+
+    /// A file to write a JUnit XML report of the campaign to when it exits, with a failed test case
+    /// per mismatch.
+    #[arg(long)]
+    junit: Option<PathBuf>,
+
+    /// A file to write a self-contained HTML report of the campaign to when it exits, with charts
+    /// of the gas and lengths of its inputs and of its throughput.
+    #[arg(long)]
+    html: Option<PathBuf>,
+
+    /// A CSV file to stream the gas of every hash to, one row per input with its length, absorb
+    /// gas, squeeze gas and total gas.
+    #[arg(long)]
+    gas_csv: Option<PathBuf>,
+
+    /// Check that every input of a length uses the same gas, less the calldata gas of its bytes,
+    /// as the first input of that length, and report any divergence like a mismatch.
+    #[arg(long)]
+    gas_invariance: bool,
+
+    /// Flag every input whose execution gas is more than this many standard deviations from the
+    /// mean of the inputs with its number of blocks, even when the digests match.
+    #[arg(long, value_name = "Z")]
+    gas_anomaly_threshold: Option<f64>,
+
+    /// A gas baseline file to check the campaign against (see `--record-gas-baseline`). The run
+    /// fails if the most gas used at any number of blocks exceeds the baseline by more than
+    /// `--gas-tolerance`.
+    #[arg(long)]
+    gas_baseline: Option<PathBuf>,
+
+    /// The increase over `--gas-baseline`, in percent, above which the gas of a number of blocks
+    /// regresses.
+    #[arg(long, default_value_t = 1.0)]
+    gas_tolerance: f64,
+
+    /// A file to record the gas baseline of the campaign to when it finishes, with the most gas
+    /// used at every number of blocks and the hash of the contract bytecode.
+    #[arg(long)]
+    record_gas_baseline: Option<PathBuf>,
+
+    /// Show a live dashboard of the campaign instead of the per-task progress bars, with per-thread
+    /// throughput, gas percentiles and a feed of mismatches. Requires the `tui` feature.
+    #[arg(long)]
+    tui: bool,
+
+    /// Record every mismatch and keep going instead of stopping at the first one. The report is
+    /// written to `report.txt` in `--output-dir`, if set.
+    #[arg(long)]
+    keep_going: bool,
+
+    /// The number of times a failing input is retried through a fresh EVM before it is reported,
+    /// to tell stable mismatches from flaky ones.
+    #[arg(long, default_value = "3")]
+    retries: usize,
+
+    /// The master seed of the campaign, for reproducing a previous campaign exactly. A random seed
+    /// is drawn and printed if unset.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// A file to periodically write the progress of the campaign to, for resuming it later with
+    /// `--resume`.
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Resume the campaign from a checkpoint file, with the same parameters as the original
+    /// campaign. Progress keeps being written to the same file. Coverage-guided campaigns can't be
+    /// resumed.
+    #[arg(long)]
+    resume: Option<PathBuf>,
+
+    /// Run as an always-on soak campaign, dumping rolling statistics (hashes/sec, total gas and
+    /// mismatch count) to this JSON file every `--soak-interval`. Fuzzes until interrupted, unless
+    /// `--duration` is set.
+    #[arg(long, conflicts_with_all = ["diff_count", "forever"])]
+    soak: Option<PathBuf>,
+
+    /// The interval between soak statistics dumps, e.g. `30s` or `5m`.
+    #[arg(long, value_parser = parse_duration, default_value = "5m", requires = "soak")]
+    soak_interval: Duration,
+
+    /// A log file that every soak statistics dump is appended to.
+    #[arg(long, requires = "soak")]
+    soak_log: Option<PathBuf>,
+
+    /// The size, in bytes, past which the soak log is rotated.
+    #[arg(long, default_value = "10485760", requires = "soak_log")]
+    soak_log_max_bytes: u64,
+
+    /// The number of rotated soak logs to keep.
+    #[arg(long, default_value = "5", requires = "soak_log")]
+    soak_log_keep: usize,
+
+    /// An address to serve Prometheus metrics of the campaign on while it runs, e.g.
+    /// `127.0.0.1:9090`: `hashes_total`, `mismatches_total`, `gas_used_total` and
+    /// `hashes_per_second`.
+    #[arg(long)]
+    metrics_addr: Option<SocketAddr>,
+
+    /// The log filter, e.g. `debug` or `info,lib_keccak::harness=trace`. Defaults to `RUST_LOG`,
+    /// or `info` if unset. The `diff`, `deploy`, `absorb` and `squeeze` spans are logged at the
+    /// `debug` and `trace` levels.
+    #[arg(long)]
+    log_level: Option<String>,
+
+    /// A file to write the log to, at `--log-level`, while the console only logs `info` and above.
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+}
+
+/// The alternative modes of the fuzzing tool. Without a subcommand, a random fuzzing campaign is
+/// run.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Exhaustively test every input length up to a maximum, with several fill patterns each.
+    Sweep {
+        /// The largest input length to test.
+        #[arg(long, default_value = "1088")]
+        max_len: usize,
+
+        /// A file to write the gas model fitted to the sweep to, as a markdown table.
+        #[arg(long)]
+        model_markdown: Option<PathBuf>,
+
+        /// A file to write the gas budget fitted to the sweep to, as Solidity constants.
+        #[arg(long)]
+        model_solidity: Option<PathBuf>,
+    },
+    /// Re-execute a single input through the contract and every backend, e.g. one written to
+    /// `--output-dir`.
+    Replay {
+        /// The input, as the path of a raw or `.hex` file, or as a hex string.
+        input: String,
+
+        /// A file to write an EIP-3155 trace of the contract calls to. Requires the `trace`
+        /// feature.
+        #[arg(long)]
+        trace: Option<PathBuf>,
+    },
+    /// Regenerate the input of a campaign iteration from its coordinates, given the seed and
+    /// parameters of the campaign.
+    Regenerate {
+        /// The shard that generated the input, counted from 0.
+        #[arg(long)]
+        shard: u64,
+
+        /// The iteration of the shard that generated the input, counted from 0.
+        #[arg(long)]
+        iteration: u64,
+
+        /// A file to write the raw input to, instead of printing it as hex.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Measure the gas used to hash an input of every length up to a maximum, charting the
+    /// contract's cost curve and the incremental cost of every block.
+    GasCurve {
+        /// The largest input length to measure.
+        #[arg(long, default_value = "1088")]
+        max_len: usize,
+
+        /// The step between the measured lengths.
+        #[arg(long, default_value = "1")]
+        step: usize,
+
+        /// A file to write the curve to as CSV, instead of printing it.
+        #[arg(long)]
+        csv: Option<PathBuf>,
+
+        /// A file to chart the curve in, as SVG.
+        #[arg(long)]
+        svg: Option<PathBuf>,
+
+        /// A file to write the curve to in the line format of a `forge snapshot` `.gas-snapshot`,
+        /// with one made-up `StatefulSponge_Gas:test_hash_<len>Bytes_gas()` entry per length and
+        /// the execution gas of the calls. The format is snapshot-shaped only: the entries match no
+        /// Foundry test, and their gas isn't whole-test gas, so the file can only be diffed against
+        /// other snapshots written by the harness.
+        #[arg(long)]
+        snapshot: Option<PathBuf>,
+    },
+    /// Discover the smallest gas limit at which the absorb and squeeze calls of every input length
+    /// succeed, cross-checked with random gas limits. Requires the `evm` feature.
+    OogCurve {
+        /// The largest input length to measure.
+        #[arg(long, default_value = "1088")]
+        max_len: usize,
+
+        /// The step between the measured lengths.
+        #[arg(long, default_value = "1")]
+        step: usize,
+
+        /// The number of random gas limits to cross-check every threshold with.
+        #[arg(long, default_value = "16")]
+        probes: usize,
+
+        /// A file to write the thresholds to as CSV, instead of printing them.
+        #[arg(long)]
+        csv: Option<PathBuf>,
+    },
+    /// Binary-search the largest input that can be hashed in a single call, and across many calls
+    /// in the same block, under a gas limit. Requires the `evm` feature.
+    MaxInput {
+        /// The gas limit that the calls must fit, e.g. the block gas limit.
+        #[arg(long, default_value = "30000000")]
+        gas_limit: u64,
+
+        /// The largest number of bytes absorbed per call when splitting the input across calls.
+        #[arg(long, default_value = "13600")]
+        chunk_bytes: usize,
+    },
+    /// Break down the gas used to hash a single input by opcode, to see where the contract spends
+    /// it. Requires the `evm` feature.
+    OpcodeGas {
+        /// The input, as the path of a raw or `.hex` file, or as a hex string.
+        input: String,
+    },
+    /// Attribute the gas used to hash a single input to the Solidity and Yul functions of the
+    /// contract, through the source maps of its Foundry artifact. Requires the `evm` and `json`
+    /// features.
+    FunctionGas {
+        /// The input, as the path of a raw or `.hex` file, or as a hex string.
+        input: String,
+
+        /// The Foundry artifact of the `StatefulSponge` contract, built with `forge build --ast`,
+        /// e.g. `out/StatefulSponge.sol/StatefulSponge.json`.
+        #[arg(long)]
+        artifact: PathBuf,
+
+        /// The artifacts of the other sources whose functions to attribute gas to, e.g.
+        /// `out/LibKeccak.sol/LibKeccak.json`.
+        #[arg(long)]
+        sources: Vec<PathBuf>,
+    },
+    /// Check that the bytecode decodes, the contract deploys, the absorb and squeeze calls
+    /// reproduce known vectors and all backends agree, and print the versions, as a sanity check
+    /// before a long campaign.
+    Doctor,
+    /// Run an identical seeded input stream against two builds of the contract, e.g. main against
+    /// an optimization PR, checking that they squeeze the same digests and tabulating their gas
+    /// per input size. Requires the `evm` feature.
+    CompareArtifacts {
+        /// Build A, as a Foundry artifact (`.json`, with the `json` feature) or hex runtime
+        /// bytecode.
+        a: PathBuf,
+
+        /// Build B, like build A.
+        b: PathBuf,
+
+        /// The number of inputs to run against both builds.
+        #[arg(long, default_value = "10000")]
+        inputs: u64,
+    },
+    /// Run random sequences of absorb and squeeze calls, such as absorbs after a squeeze or several
+    /// squeezes in a row, against a single deployment of the contract, and diff every squeeze
+    /// against an explicit model in which `squeeze` resets the sponge. Requires the `evm` feature.
+    Lifecycle {
+        /// The number of sequences to run.
+        #[arg(long, default_value = "1000")]
+        sequences: u64,
+
+        /// The number of random calls per sequence, before its final squeeze.
+        #[arg(long, default_value = "8")]
+        calls: usize,
+
+        /// The number of instances of the contract to deploy in the same EVM, at different
+        /// addresses, and to interleave the calls among, each with inputs and a model of its own.
+        #[arg(long, default_value = "1")]
+        instances: usize,
+
+        /// Also reset the instances without squeezing them, after which they must behave like a
+        /// fresh deployment. Requires a contract with a `reset()` entrypoint, which
+        /// `StatefulSponge` lacks.
+        #[arg(long)]
+        resets: bool,
+    },
+    /// Squeeze every input repeatedly and compare the concatenated digests against the output
+    /// stream of the reference sponge, for contracts whose `squeeze` returns the next 32 bytes of
+    /// output rather than resetting the sponge. Requires the `evm` feature.
+    MultiSqueeze {
+        /// The number of inputs to absorb.
+        #[arg(long, default_value = "1000")]
+        inputs: u64,
+
+        /// The number of squeezes per input. Required, since every number past 1 fails against
+        /// contracts that reset the sponge on squeeze, like `StatefulSponge`.
+        #[arg(long)]
+        squeezes: usize,
+
+        /// The padding of the reference stream: `keccak` or `shake`.
+        #[arg(long, default_value = "keccak")]
+        padding: XofPadding,
+    },
+    /// Binary-search a directory of builds of the contract, in the order of their file names (e.g.
+    /// prefixed with their date), for the first one that mismatches on an input, or regresses its
+    /// gas by more than `--gas-tolerance` over the oldest build. Requires the `evm` feature.
+    BisectArtifacts {
+        /// The directory of builds, as Foundry artifacts (`.json`, with the `json` feature) or hex
+        /// runtime bytecode.
+        dir: PathBuf,
+
+        /// The input, as the path of a raw or `.hex` file, or as a hex string.
+        input: String,
+
+        /// Bisect a gas regression instead of a mismatch.
+        #[arg(long)]
+        gas: bool,
+    },
+    /// Shrink a failing input into a minimal reproducer, while the mismatch persists.
+    Minimize {
+        /// The input, as the path of a raw or `.hex` file, or as a hex string.
+        input: String,
+
+        /// A file to write the raw minimized input to.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+/// The reference implementations that can be selected from the CLI.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    TinyKeccak,
+    Sha3,
+    Alloy,
+    Native,
+    Xkcp,
+    KeccakAsm,
+}
+
+impl Backend {
+    /// Returns the factory for the backend, or an error if it was not compiled into this build.
+    fn factory(self) -> Result<BackendFactory> {
+        let factory: BackendFactory = match self {
+            Self::TinyKeccak => || Box::new(hashing::TinyKeccak),
+            Self::Sha3 => || Box::new(hashing::Sha3Keccak),
+            Self::Alloy => || Box::new(hashing::AlloyKeccak),
+            Self::Native => || Box::new(hashing::NativeKeccak),
+            #[cfg(feature = "xkcp")]
+            Self::Xkcp => || Box::new(hashing::XkcpKeccak),
+            #[cfg(feature = "keccak-asm")]
+            Self::KeccakAsm => || Box::new(hashing::AsmKeccak),
+            #[allow(unreachable_patterns)]
+            backend => {
+                let name = backend.to_possible_value().expect("no skipped variants");
+                bail!(
+                    "The `{0}` backend requires the `{0}` cargo feature",
+                    name.get_name()
+                )
+            }
+        };
+        Ok(factory)
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let Args {
+        command,
         thread_count,
+        shards,
         diff_count,
+        forever,
+        duration,
         max_input_bytes,
+        min_input_bytes,
+        input_sizes,
+        fixed_length,
+        backends,
+        strategy,
+        payloads,
+        length_distribution,
+        ramp_from,
+        ramp_diffs,
+        ramp_curve,
+        pattern_ratio,
+        coverage,
+        specs,
+        bytecode_path,
+        artifact,
+        build,
+        build_contract,
+        variant_bytecode,
+        matrix_builds,
+        matrix_settings,
+        targets,
+        huff,
+        huff_abi,
+        yul,
+        yul_abi,
+        randomize_env,
+        expected_codehash,
+        deploy_via_create,
+        creation_bytecode,
+        contract_addresses,
+        absorb_chunk_bytes,
+        random_chunks,
+        tiny_chunks,
+        chunking_invariance,
+        squeeze_idempotence,
+        corpus,
+        rpc_url,
+        rpc_blocks,
+        regressions,
+        no_regressions,
+        corpus_out,
+        output_dir,
+        manifest,
+        json,
+        mismatch_stream,
+        junit,
+        html,
+        gas_csv,
+        gas_invariance,
+        gas_anomaly_threshold,
+        gas_baseline,
+        gas_tolerance,
+        record_gas_baseline,
+        tui,
+        keep_going,
+        retries,
+        seed,
+        checkpoint,
+        resume,
+        soak,
+        soak_interval,
+        soak_log,
+        soak_log_max_bytes,
+        soak_log_keep,
+        metrics_addr,
+        log_level,
+        log_file,
     } = Args::parse();
+    init_logging(log_level, log_file.as_deref(), tui)?;
+
+    let unbounded = forever || (soak.is_some() && duration.is_none());
+    let diff_count = if unbounded { 0 } else { diff_count };
+    let mut fuzzer = DiffFuzzer::new(thread_count, diff_count, max_input_bytes);
+    fuzzer.shards = shards;
+    fuzzer.duration = duration;
+    fuzzer.min_input_bytes = min_input_bytes;
+    fuzzer.input_sizes = input_sizes;
+    fuzzer.strategy = strategy;
+    fuzzer.payloads = payloads;
+    fuzzer.length_distribution = length_distribution;
+    fuzzer.ramp = ramp_from.map(|start_bytes| Ramp {
+        start_bytes,
+        diffs: ramp_diffs,
+        curve: ramp_curve,
+    });
+    fuzzer.pattern_ratio = pattern_ratio;
+    fuzzer.coverage_guided = coverage;
+    fuzzer.specs = specs;
+    fuzzer.randomize_env = randomize_env;
+    fuzzer.expected_code_hash = expected_codehash.map(<[u8; 32]>::from);
+    if !contract_addresses.is_empty() && !cfg!(feature = "evm") {
+        bail!("Configuring the contract address requires the `evm` cargo feature");
+    }
+    fuzzer.contract_addresses = contract_addresses
+        .into_iter()
+        .map(<[u8; 20]>::from)
+        .collect();
+    if let Some(path) = bytecode_path {
+        #[cfg(feature = "evm")]
+        lib_keccak::harness::load_contract_code(&path)?;
+        #[cfg(not(feature = "evm"))]
+        bail!(
+            "Testing the contract bytecode {} requires the `evm` cargo feature",
+            path.display()
+        );
+    }
+    if let Some(path) = artifact.as_ref().or(build.as_ref()) {
+        #[cfg(all(feature = "evm", feature = "json"))]
+        {
+            let artifact = match build.as_ref() {
+                Some(path) => lib_keccak::compile::compile(path, &build_contract)?,
+                None => lib_keccak::sourcemap::Artifact::read_target(path)?,
+            };
+            tracing::info!(
+                compiler = artifact.compiler.as_deref().unwrap_or("unknown"),
+                code_bytes = artifact.code.len(),
+                source_map_entries = artifact.source_map.len(),
+                "Testing the contract of {}",
+                path.display()
+            );
+            lib_keccak::harness::set_contract_code(artifact.code)?;
+            if deploy_via_create {
+                fuzzer.initcode = artifact.creation_code;
+            }
+        }
+        #[cfg(not(all(feature = "evm", feature = "json")))]
+        {
+            let _ = build_contract;
+            bail!(
+                "Testing the contract of {} requires the `evm` and `json` cargo features",
+                path.display()
+            );
+        }
+    }
+    if deploy_via_create || creation_bytecode.is_some() {
+        #[cfg(feature = "evm")]
+        {
+            use lib_keccak::harness;
+
+            // The creation bytecode of the artifact, if any, is the default initcode.
+            match creation_bytecode {
+                Some(path) => fuzzer.initcode = Some(harness::read_bytecode(&path)?),
+                None if fuzzer.initcode.is_none() => {
+                    fuzzer.initcode = Some(harness::wrap_initcode(harness::contract_code()?));
+                }
+                None => {}
+            }
+        }
+        #[cfg(not(feature = "evm"))]
+        bail!(
+            "Deploying the contract with a creation transaction requires the `evm` cargo feature"
+        );
+    }
+    if let Some(path) = variant_bytecode {
+        #[cfg(feature = "evm")]
+        {
+            use lib_keccak::harness;
+
+            // MCOPY is 0x5e.
+            let code = harness::read_bytecode(&path)?;
+            let uses_mcopy = |code: &[u8]| harness::contains_opcode(code, 0x5e);
+            tracing::info!(
+                contract_uses_mcopy = uses_mcopy(harness::contract_code()?),
+                variant_uses_mcopy = uses_mcopy(&code),
+                "Diffing the variant build {}",
+                path.display()
+            );
+            fuzzer.variant_code = Some(code);
+        }
+        #[cfg(not(feature = "evm"))]
+        bail!(
+            "Diffing the variant build {} requires the `evm` cargo feature",
+            path.display()
+        );
+    }
+    if !matrix_builds.is_empty() && !cfg!(feature = "evm") {
+        bail!("Diffing a build matrix requires the `evm` cargo feature");
+    }
+    fuzzer.build_matrix = matrix_builds
+        .iter()
+        .map(|path| read_matrix_build(path))
+        .collect::<Result<_>>()?;
+    if let Some(path) = build.as_ref().filter(|_| !matrix_settings.is_empty()) {
+        #[cfg(all(feature = "evm", feature = "json"))]
+        for settings in &matrix_settings {
+            let settings = settings.parse::<lib_keccak::compile::CompilerSettings>()?;
+            let artifact =
+                lib_keccak::compile::compile_with(path, &build_contract, Some(&settings))?;
+            fuzzer.build_matrix.push(MatrixBuild {
+                label: format!(
+                    "{build_contract} ({})",
+                    artifact
+                        .build_info()
+                        .unwrap_or_else(|| settings.to_string())
+                ),
+                code: artifact.code,
+            });
+        }
+        #[cfg(not(all(feature = "evm", feature = "json")))]
+        bail!(
+            "Compiling {} under other settings requires the `evm` and `json` cargo features",
+            path.display()
+        );
+    }
+    for (i, build) in fuzzer.build_matrix.iter().enumerate() {
+        tracing::info!("Diffing {} as build#{}", build.label, i + 1);
+    }
+    if !targets.is_empty() && !cfg!(feature = "evm") {
+        bail!("Diffing other target contracts requires the `evm` cargo feature");
+    }
+    for target in &targets {
+        let Some((abi, path)) = target.split_once(':') else {
+            bail!(
+                "Invalid target `{target}`, expected `<ABI>:<FILE>`, e.g. `hash-bytes:Hash.json`"
+            );
+        };
+        let build = read_matrix_build(Path::new(path))?;
+        fuzzer.targets.push(Target {
+            label: build.label,
+            abi: abi.parse()?,
+            code: build.code,
+        });
+    }
+    if let Some(path) = huff {
+        #[cfg(all(feature = "evm", feature = "json"))]
+        fuzzer.targets.push(Target {
+            label: format!("{} (Huff)", file_label(&path)),
+            abi: huff_abi,
+            code: lib_keccak::compile::compile_huff(&path)?,
+        });
+        #[cfg(not(all(feature = "evm", feature = "json")))]
+        {
+            let _ = huff_abi;
+            bail!(
+                "Compiling the Huff contract {} requires the `evm` and `json` cargo features",
+                path.display()
+            );
+        }
+    }
+    if let Some(path) = yul {
+        #[cfg(all(feature = "evm", feature = "json"))]
+        {
+            let code =
+                lib_keccak::harness::deployed_code(&lib_keccak::compile::compile_yul(&path)?)
+                    .with_context(|| {
+                        format!("Failed to deploy the Yul object {}", path.display())
+                    })?;
+            if code.is_empty() {
+                bail!(
+                    "The Yul object {} deployed no code, does it return its runtime sub-object?",
+                    path.display()
+                );
+            }
+            fuzzer.targets.push(Target {
+                label: format!("{} (Yul)", file_label(&path)),
+                abi: yul_abi,
+                code,
+            });
+        }
+        #[cfg(not(all(feature = "evm", feature = "json")))]
+        {
+            let _ = yul_abi;
+            bail!(
+                "Compiling the Yul object {} requires the `evm` and `json` cargo features",
+                path.display()
+            );
+        }
+    }
+    for (i, target) in fuzzer.targets.iter().enumerate() {
+        tracing::info!(
+            "Diffing {} through its {} interface as target#{}",
+            target.label,
+            target.abi,
+            i + 1
+        );
+    }
+    fuzzer.absorb_chunk_bytes = absorb_chunk_bytes;
+    fuzzer.random_chunks = random_chunks;
+    fuzzer.tiny_chunks = tiny_chunks;
+    fuzzer.chunking_invariance = chunking_invariance;
+    fuzzer.squeeze_idempotence = squeeze_idempotence;
+    fuzzer.corpus_dir = corpus;
+    fuzzer.regressions_dir = (!no_regressions).then_some(regressions);
+    fuzzer.corpus_out = corpus_out;
+    fuzzer.output_dir = output_dir;
+    fuzzer.keep_going = keep_going;
+    fuzzer.retries = retries;
+    fuzzer.seed = seed;
+    fuzzer.profile = html.is_some() || gas_baseline.is_some() || record_gas_baseline.is_some();
+    fuzzer.gas_invariance = gas_invariance;
+    fuzzer.gas_anomaly_threshold = gas_anomaly_threshold;
+    if let Some(path) = gas_csv {
+        fuzzer.gas_csv = Some(Arc::new(GasCsv::create(&path, fuzzer.targets.len())?));
+    }
+    fuzzer.soak = soak.map(|stats_file| Soak {
+        stats_file,
+        interval: soak_interval,
+        log_file: soak_log,
+        max_log_bytes: soak_log_max_bytes,
+        max_logs: soak_log_keep,
+    });
+    fuzzer.metrics_addr = metrics_addr;
+    if let Some(path) = resume {
+        fuzzer.resume = Some(Checkpoint::read(&path)?);
+        fuzzer.seed = fuzzer.seed.or(fuzzer.resume.as_ref().map(|c| c.seed));
+        fuzzer.checkpoint = Some(checkpoint.unwrap_or(path));
+    } else {
+        fuzzer.checkpoint = checkpoint;
+    }
+    #[cfg(feature = "rpc")]
+    if let Some(url) = rpc_url {
+        fuzzer.seeds = lib_keccak::rpc::fetch_payloads(&url, rpc_blocks).await?;
+    }
+    #[cfg(not(feature = "rpc"))]
+    if rpc_url.is_some() {
+        let _ = rpc_blocks;
+        bail!("The `--rpc-url` option requires the `rpc` cargo feature");
+    }
+    if let Some(len) = fixed_length {
+        fuzzer.input_sizes = vec![len];
+        fuzzer.strategy = Strategy::Random;
+    }
+    if !backends.is_empty() {
+        fuzzer.backends = backends
+            .into_iter()
+            .map(Backend::factory)
+            .collect::<Result<_>>()?;
+    }
 
-    let progress_group = MultiProgress::new();
-    let progress_style = ProgressStyle::with_template(
-        "{spinner:.green} [{elapsed_precise}] [{bar:60.cyan/blue}] ({msg} | eta: {eta})",
-    )?
-    .with_key("eta", |state: &ProgressState, w: &mut dyn Write| {
-        write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap()
-    })
-    .progress_chars("#>-");
+    // Stop the campaign gracefully on the first Ctrl-C, and exit immediately on the second.
+    let stop = fuzzer.stop.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            tracing::warn!("Stopping after the current iterations, press Ctrl-C again to exit now");
+            stop.store(true, Ordering::Relaxed);
+        }
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
 
-    let num_hashes = diff_count / thread_count;
+    #[cfg(not(feature = "json"))]
+    if json.is_some() || mismatch_stream.is_some() {
+        bail!("The `--json` and `--mismatch-stream` options require the `json` cargo feature");
+    }
+    #[cfg(not(feature = "tui"))]
+    if tui {
+        bail!("The `--tui` option requires the `tui` cargo feature");
+    }
+    // The dashboard draws the progress itself.
+    let progress = if tui {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    };
 
-    let mut join_set = JoinSet::new();
-    for i in 0..thread_count {
-        let pb = progress_group.add(ProgressBar::new(num_hashes));
-        pb.set_style(progress_style.clone());
-        pb.set_message(format!("Thread {}", i + 1));
+    // The dashboard restores the terminal before the console reporter prints the summary.
+    #[cfg(feature = "tui")]
+    if tui {
+        fuzzer.reporters.push(Arc::new(Dashboard::default()));
+    }
+    fuzzer.reporters.push(Arc::new(ConsoleReporter));
+    let manifest = manifest.or_else(|| {
+        fuzzer
+            .output_dir
+            .as_ref()
+            .map(|dir| dir.join("manifest.json"))
+    });
+    if let Some(path) = manifest {
+        let args = std::env::args().collect();
+        fuzzer
+            .reporters
+            .push(Arc::new(ManifestReporter { path, args }));
+    }
+    #[cfg(feature = "json")]
+    if let Some(path) = json {
+        fuzzer.reporters.push(Arc::new(JsonReporter { path }));
+    }
+    #[cfg(feature = "json")]
+    match mismatch_stream {
+        Some(path) if path.as_os_str() == "-" => {
+            fuzzer.reporters.push(Arc::new(JsonlReporter::stdout()))
+        }
+        Some(path) => fuzzer
+            .reporters
+            .push(Arc::new(JsonlReporter::create(&path)?)),
+        None => {}
+    }
+    if let Some(path) = junit {
+        fuzzer.reporters.push(Arc::new(JunitReporter { path }));
+    }
+    if let Some(path) = html {
+        fuzzer.reporters.push(Arc::new(HtmlReporter { path }));
+    }
+    if let Some(path) = record_gas_baseline {
+        fuzzer
+            .reporters
+            .push(Arc::new(GasBaselineRecorder { path }));
+    }
+    if let Some(path) = gas_baseline {
+        fuzzer.reporters.push(Arc::new(GasBaselineCheck {
+            baseline: GasBaseline::read(&path)?,
+            tolerance_percent: gas_tolerance,
+        }));
+    }
 
-        join_set.spawn(fuzz_task(pb, num_hashes, max_input_bytes));
+    let report = match command {
+        Some(Command::Sweep {
+            max_len,
+            model_markdown,
+            model_solidity,
+        }) => {
+            if model_markdown.is_some() || model_solidity.is_some() {
+                fuzzer.profile = true;
+                fuzzer.reporters.push(Arc::new(GasModelReporter {
+                    markdown: model_markdown,
+                    solidity: model_solidity,
+                }));
+            }
+            fuzzer.sweep(max_len, &progress).await
+        }
+        Some(Command::Replay { input, trace }) => {
+            return replay(&fuzzer, &read_input(&input)?, trace)
+        }
+        Some(Command::Regenerate {
+            shard,
+            iteration,
+            out,
+        }) => {
+            let input = fuzzer.regenerate(shard, iteration)?;
+            match out {
+                Some(out) => std::fs::write(out, &input)?,
+                None => println!("0x{}", hex::encode(&input)),
+            }
+            return Ok(());
+        }
+        Some(Command::GasCurve {
+            max_len,
+            step,
+            csv,
+            svg,
+            snapshot,
+        }) => return gas_curve(&fuzzer, max_len, step, csv, svg, snapshot),
+        Some(Command::OogCurve {
+            max_len,
+            step,
+            probes,
+            csv,
+        }) => {
+            let seed = fuzzer.seed.unwrap_or_else(rand::random);
+            return oog_curve(&fuzzer, max_len, step, probes, seed, csv);
+        }
+        Some(Command::MaxInput {
+            gas_limit,
+            chunk_bytes,
+        }) => return max_input_len(&fuzzer, gas_limit, chunk_bytes),
+        Some(Command::OpcodeGas { input }) => return opcode_gas(&fuzzer, &read_input(&input)?),
+        Some(Command::FunctionGas {
+            input,
+            artifact,
+            sources,
+        }) => return function_gas(&fuzzer, &read_input(&input)?, &artifact, &sources),
+        Some(Command::Doctor) => return doctor(&fuzzer),
+        Some(Command::CompareArtifacts { a, b, inputs }) => {
+            pick_seed(&mut fuzzer, "inputs");
+            return compare_artifacts(&fuzzer, &a, &b, inputs);
+        }
+        Some(Command::Lifecycle {
+            sequences,
+            calls,
+            instances,
+            resets,
+        }) => {
+            pick_seed(&mut fuzzer, "calls");
+            return lifecycle(&fuzzer, sequences, calls, instances, resets);
+        }
+        Some(Command::MultiSqueeze {
+            inputs,
+            squeezes,
+            padding,
+        }) => {
+            pick_seed(&mut fuzzer, "inputs");
+            return multi_squeeze(&fuzzer, inputs, squeezes, padding);
+        }
+        Some(Command::BisectArtifacts { dir, input, gas }) => {
+            let regression = if gas {
+                Regression::Gas {
+                    tolerance_percent: gas_tolerance,
+                }
+            } else {
+                Regression::Mismatch
+            };
+            return bisect_artifacts(&fuzzer, &dir, &read_input(&input)?, regression);
+        }
+        Some(Command::Minimize { input, out }) => {
+            return minimize_input(&fuzzer, &read_input(&input)?, out)
+        }
+        None => {
+            pick_seed(&mut fuzzer, "campaign");
+            fuzzer.run(&progress).await
+        }
+    }?;
+
+    if !report.is_clean() {
+        if let Some(dir) = fuzzer.output_dir.as_ref() {
+            report.write(&dir.join("report.txt"))?;
+        }
+        bail!("Found {} mismatches", report.failures.len());
+    }
+    Ok(())
+}
+
+/// Logs to the console, unless the dashboard is shown, and to `log_file`, if set. The console logs
+/// at `log_level` without a log file, and at `info` with one.
+fn init_logging(log_level: Option<String>, log_file: Option<&Path>, tui: bool) -> Result<()> {
+    let filter = || match log_level.as_deref() {
+        Some(directives) => EnvFilter::try_new(directives),
+        None => Ok(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))),
+    };
+
+    let file = log_file
+        .map(|path| -> Result<_> {
+            let file = File::create(path)
+                .with_context(|| format!("Failed to create log file {}", path.display()))?;
+            Ok(fmt::layer()
+                .with_writer(Mutex::new(file))
+                .with_ansi(false)
+                .with_filter(filter()?))
+        })
+        .transpose()?;
+    let console_filter = match log_file {
+        Some(_) => EnvFilter::new("info"),
+        None => filter()?,
+    };
+    let console = (!tui).then(|| {
+        fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_ansi(std::io::stderr().is_terminal())
+            .with_target(false)
+            .without_time()
+            .with_filter(console_filter)
+    });
+
+    tracing_subscriber::registry()
+        .with(file)
+        .with(console)
+        .try_init()?;
+    Ok(())
+}
+
+/// Picks a random seed for the fuzzer if none is set, logging how to reproduce the `what` that it
+/// generates.
+fn pick_seed(fuzzer: &mut DiffFuzzer, what: &str) {
+    if fuzzer.seed.is_none() {
+        let seed = rand::random();
+        tracing::info!("Using random seed {seed}, pass `--seed {seed}` to reproduce the {what}");
+        fuzzer.seed = Some(seed);
+    }
+}
+
+/// Parses a duration made of one or more `<number><unit>` components, with the units `s`, `m`, `h`
+/// and `d`. A bare number is a number of seconds.
+fn parse_duration(arg: &str) -> Result<Duration> {
+    if let Ok(secs) = arg.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
     }
 
-    while let Some(res) = join_set.join_next().await {
-        res??;
+    let mut secs = 0u64;
+    let mut rest = arg.trim();
+    if rest.is_empty() {
+        bail!("The duration must not be empty");
+    }
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            bail!("Invalid duration `{arg}`, expected e.g. `90s`, `30m`, `2h` or `1h30m`");
+        }
+        let Ok(value) = rest[..digits].parse::<u64>() else {
+            bail!("The duration `{arg}` is too long");
+        };
+        let unit = match rest[digits..].chars().next() {
+            Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 60 * 60,
+            Some('d') => 24 * 60 * 60,
+            _ => bail!("Invalid duration `{arg}`, expected e.g. `90s`, `30m`, `2h` or `1h30m`"),
+        };
+        secs = match value.checked_mul(unit).and_then(|s| secs.checked_add(s)) {
+            Some(secs) => secs,
+            None => bail!("The duration `{arg}` is too long"),
+        };
+        rest = &rest[digits + 1..];
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// Measures the gas curve up to `max_len` bytes against the fuzzer's deployment, writing it as CSV
+/// to `csv` (or stdout), as SVG to `svg` and as a gas snapshot to `snapshot`, if set, and prints
+/// the cost of every block.
+fn gas_curve(
+    fuzzer: &DiffFuzzer,
+    max_len: usize,
+    step: usize,
+    csv: Option<PathBuf>,
+    svg: Option<PathBuf>,
+    snapshot: Option<PathBuf>,
+) -> Result<()> {
+    if step == 0 {
+        bail!("The step between lengths must be non-zero");
     }
 
+    let points = measure_gas_curve(&fuzzer.deployment(), (0..=max_len).step_by(step))?;
+    match csv {
+        Some(path) => write_gas_curve_csv(std::io::BufWriter::new(File::create(path)?), &points)?,
+        None => write_gas_curve_csv(std::io::stdout().lock(), &points)?,
+    }
+    if let Some(path) = svg {
+        std::fs::write(path, gas_curve_svg(&points))?;
+    }
+    if let Some(path) = snapshot {
+        write_gas_snapshot(std::io::BufWriter::new(File::create(path)?), &points)?;
+    }
+
+    eprintln!("blocks  gas      intrinsic        incremental");
+    for cost in block_costs(&points) {
+        let incremental = cost
+            .incremental
+            .map_or_else(|| "-".to_string(), |gas| format!("{gas:+}"));
+        let intrinsic = format!(
+            "{} ({:.1}%)",
+            cost.absorb_intrinsic,
+            cost.absorb_intrinsic as f64 * 100.0 / cost.gas.max(1) as f64
+        );
+        eprintln!(
+            "{:<7} {:<8} {intrinsic:<16} {incremental}",
+            cost.blocks, cost.gas
+        );
+    }
     Ok(())
 }
 
-/// Spawns a task that runs through `num_hashes` iterations of differential testing `tiny-keccak` vs.
-/// the `StatefulSponge` contract.
-#[allow(unused)]
-async fn fuzz_task(pb: ProgressBar, num_hashes: u64, max_input_bytes: usize) -> Result<()> {
-    // Init EVM
-    let mut cache_db = CacheDB::new(EmptyDB::default());
-    deploy_contract(&mut cache_db);
-    let mut evm = EVM::new();
-    evm.database(cache_db);
+/// Discovers the out-of-gas thresholds of every `step`th length up to `max_len` against the
+/// fuzzer's deployment, writing them as CSV to `csv` (or stdout) and printing the largest
+/// thresholds of every block count.
+fn oog_curve(
+    fuzzer: &DiffFuzzer,
+    max_len: usize,
+    step: usize,
+    probes: usize,
+    seed: u64,
+    csv: Option<PathBuf>,
+) -> Result<()> {
+    if step == 0 {
+        bail!("The step between lengths must be non-zero");
+    }
 
-    // Init EVM
-    evm.env.cfg.disable_base_fee = true;
-    evm.env.cfg.disable_gas_refund = true;
-    evm.env.cfg.disable_balance_check = true;
-    evm.env.cfg.disable_block_gas_limit = true;
-    evm.env.cfg.memory_limit = u64::MAX;
-    evm.env.tx.transact_to = TransactTo::Call(STATEFUL_SPONGE_ADDR);
+    let thresholds = measure_oog_thresholds(
+        &fuzzer.deployment(),
+        (0..=max_len).step_by(step),
+        probes,
+        seed,
+    )?;
+    match csv {
+        Some(path) => write_oog_csv(std::io::BufWriter::new(File::create(path)?), &thresholds)?,
+        None => write_oog_csv(std::io::stdout().lock(), &thresholds)?,
+    }
 
-    // Init thread RNG
-    let mut rng = rand::thread_rng();
+    let mut blocks = BTreeMap::<usize, (u64, u64, u64)>::new();
+    for threshold in &thresholds {
+        let (absorb, squeeze, margin) = blocks.entry(threshold.blocks()).or_default();
+        *absorb = threshold.absorb.min_gas_limit.max(*absorb);
+        *squeeze = threshold.squeeze.min_gas_limit.max(*squeeze);
+        *margin = threshold
+            .absorb
+            .margin()
+            .max(threshold.squeeze.margin())
+            .max(*margin);
+    }
+    eprintln!("blocks  absorb limit  squeeze limit  margin");
+    for (blocks, (absorb, squeeze, margin)) in blocks {
+        eprintln!("{blocks:<7} {absorb:<13} {squeeze:<14} {margin}");
+    }
+    Ok(())
+}
 
-    // Re-use the same memory for the input slice and tiny-keccak hash outputs.
-    let mut hash_tiny: [u8; 32] = [0u8; 32];
-    let mut bytes = vec![0u8; max_input_bytes];
+/// Prints the largest inputs that can be hashed under `gas_limit`, in a single call and across calls
+/// of at most `chunk_bytes` bytes.
+fn max_input_len(fuzzer: &DiffFuzzer, gas_limit: u64, chunk_bytes: usize) -> Result<()> {
+    let deployment = fuzzer.deployment();
+    let single = max_input(&deployment, gas_limit, GasFit::SingleCall)?;
+    let multi = max_input(&deployment, gas_limit, GasFit::MultiCall { chunk_bytes })?;
+    println!("Largest inputs hashable under a gas limit of {gas_limit}:");
+    println!(
+        "- in a single call: {} bytes ({} blocks), with a gas limit of {} for the largest call",
+        single.len,
+        single.blocks(),
+        single.max_gas_limit
+    );
+    println!(
+        "- across calls of at most {chunk_bytes} bytes: {} bytes in {} absorb calls, with a total \
+         gas limit of {}",
+        multi.len, multi.absorb_calls, multi.total_gas_limit
+    );
+    Ok(())
+}
 
-    for i in 0..num_hashes {
-        let in_slice = bytes[0..rng.gen_range(0..max_input_bytes)].as_mut();
-        rng.fill(in_slice);
+/// Returns the file name of `path` to label a build or target with, or the whole path if it has
+/// none.
+fn file_label(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
 
-        hash_input_tiny(in_slice, hash_tiny.as_mut());
-        let hash_evm = hash_input_evm(&mut evm, in_slice)?;
+/// Reads a build of the contract for the build matrix from `path`, a Foundry artifact if it has the
+/// `.json` extension, or else hex runtime bytecode. The build is labelled with its file name, and
+/// the compiler version and settings of the artifact, if known.
+fn read_matrix_build(path: &Path) -> Result<MatrixBuild> {
+    let name = file_label(path);
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        #[cfg(all(feature = "evm", feature = "json"))]
+        {
+            let artifact = lib_keccak::sourcemap::Artifact::read_target(path)?;
+            return Ok(MatrixBuild {
+                label: match artifact.build_info() {
+                    Some(info) => format!("{name} ({info})"),
+                    None => name,
+                },
+                code: artifact.code,
+            });
+        }
+        #[cfg(not(all(feature = "evm", feature = "json")))]
+        bail!(
+            "Reading the artifact {} requires the `evm` and `json` cargo features",
+            path.display()
+        );
+    }
+    #[cfg(feature = "evm")]
+    return Ok(MatrixBuild {
+        label: name,
+        code: lib_keccak::harness::read_bytecode(path)?,
+    });
+    #[cfg(not(feature = "evm"))]
+    bail!("Reading the build {name} requires the `evm` cargo feature");
+}
 
-        if hash_tiny != hash_evm {
-            bail!(
-                "Hash mismatch at iteration {} - input: {}",
-                i,
-                hex::encode(bytes)
-            );
+/// Compares the builds at `a` and `b` on `inputs` inputs of the fuzzer's input stream, printing the
+/// gas table and failing if they squeeze different digests.
+fn compare_artifacts(fuzzer: &DiffFuzzer, a: &Path, b: &Path, inputs: u64) -> Result<()> {
+    let (a, b) = (read_matrix_build(a)?, read_matrix_build(b)?);
+    let comparison = compare_builds(fuzzer, &a.code, &b.code, inputs)?;
+    println!("A: {}\nB: {}", a.label, b.label);
+    print!("{}", comparison.gas_table());
+    if comparison.is_equivalent() {
+        println!(
+            "The builds squeezed the same digests for all {} inputs",
+            comparison.inputs
+        );
+        return Ok(());
+    }
+    for mismatch in &comparison.mismatches {
+        let agrees = |digest| {
+            if digest == mismatch.reference {
+                " (reference)"
+            } else {
+                ""
+            }
+        };
+        println!(
+            "Mismatch on the {} byte input 0x{}: A 0x{}{}, B 0x{}{}",
+            mismatch.input.len(),
+            hex::encode(&mismatch.input),
+            hex::encode(mismatch.digest_a),
+            agrees(mismatch.digest_a),
+            hex::encode(mismatch.digest_b),
+            agrees(mismatch.digest_b)
+        );
+    }
+    bail!(
+        "The builds squeezed different digests for {} of {} inputs",
+        comparison.mismatch_count,
+        comparison.inputs
+    )
+}
+
+/// Diffs `sequences` random sequences of `calls` calls against the model of the contract, printing
+/// the shrunk calls up to the first diverging squeeze, if any.
+fn lifecycle(
+    fuzzer: &DiffFuzzer,
+    sequences: u64,
+    calls: usize,
+    instances: usize,
+    resets: bool,
+) -> Result<()> {
+    let report = diff_lifecycle(fuzzer, sequences, calls, instances, resets)?;
+    let Some(divergence) = report.divergence else {
+        println!(
+            "The contract matched the model on all {} squeezes of {} sequences{} ({} absorbs after \
+             a squeeze, {} repeated squeezes{})",
+            report.squeezes,
+            report.sequences,
+            if report.instances > 1 {
+                format!(" across {} instances", report.instances)
+            } else {
+                String::new()
+            },
+            report.absorbs_after_squeeze,
+            report.repeated_squeezes,
+            if report.resets > 0 {
+                format!(
+                    ", {} resets, {} absorbs after a reset",
+                    report.resets, report.absorbs_after_reset
+                )
+            } else {
+                String::new()
+            }
+        );
+        return Ok(());
+    };
+    println!(
+        "Sequence {} diverged after {} calls, shrunk into {}:",
+        divergence.sequence,
+        divergence.original_calls,
+        divergence.calls.len()
+    );
+    for call in &divergence.calls {
+        match &call.call {
+            SpongeCall::Absorb(chunk) => println!("{call}: 0x{}", hex::encode(chunk)),
+            SpongeCall::Squeeze | SpongeCall::Reset => println!("{call}"),
+        }
+    }
+    bail!(
+        "The contract squeezed 0x{}, but the model squeezed 0x{}",
+        hex::encode(divergence.contract),
+        hex::encode(divergence.model)
+    )
+}
+
+/// Squeezes `inputs` inputs `squeezes` times each and compares the output against the reference
+/// stream under `padding`, printing the first divergence, if any.
+fn multi_squeeze(
+    fuzzer: &DiffFuzzer,
+    inputs: u64,
+    squeezes: usize,
+    padding: XofPadding,
+) -> Result<()> {
+    let report = diff_squeezes(fuzzer, inputs, squeezes, padding)?;
+    let Some(divergence) = report.divergence else {
+        println!(
+            "The squeezes of all {} inputs matched the first {} bytes of the {padding} output \
+             stream",
+            report.inputs, report.stream_bytes
+        );
+        return Ok(());
+    };
+    println!(
+        "Input ({} bytes): 0x{}",
+        divergence.input.len(),
+        hex::encode(&divergence.input)
+    );
+    println!("Contract:  0x{}", hex::encode(&divergence.contract));
+    println!("Reference: 0x{}", hex::encode(&divergence.reference));
+    bail!(
+        "Squeeze {} of the {} byte input diverged from the {padding} output stream{}",
+        divergence.squeeze + 1,
+        divergence.input.len(),
+        if divergence.beyond_first_block() {
+            ", past the first block of output"
+        } else {
+            ""
+        }
+    )
+}
+
+/// Bisects the builds in `dir`, ordered by file name, for the first one that regresses on `input`,
+/// printing every build that the input was hashed with.
+fn bisect_artifacts(
+    fuzzer: &DiffFuzzer,
+    dir: &Path,
+    input: &[u8],
+    regression: Regression,
+) -> Result<()> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read the builds in {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if path.is_file() && !hidden {
+            paths.push(path);
         }
+    }
+    paths.sort();
+    let builds = paths
+        .iter()
+        .map(|path| read_matrix_build(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    let bisection = bisect_builds(&builds, input, fuzzer.absorb_chunk_bytes, regression)?;
+    if regression == Regression::Mismatch {
+        println!("Reference digest 0x{}", hex::encode(bisection.reference));
+    }
+    for probe in &bisection.probes {
+        println!(
+            "[{}/{}] {}: 0x{}, {} gas, {}",
+            probe.build + 1,
+            builds.len(),
+            builds[probe.build].label,
+            hex::encode(probe.digest),
+            probe.gas,
+            if probe.regressed { "regressed" } else { "good" }
+        );
+    }
+    println!(
+        "The first build that regresses is {}, after {}",
+        builds[bisection.first_regressed].label,
+        builds[bisection.first_regressed - 1].label
+    );
+    Ok(())
+}
+
+/// Runs the preflight checks against the fuzzer's contract and backends, printing every outcome.
+fn doctor(fuzzer: &DiffFuzzer) -> Result<()> {
+    println!("{}", lib_keccak::doctor::versions());
+    let checks = lib_keccak::doctor::run_checks(fuzzer);
+    for check in &checks {
+        println!("{check}");
+    }
+    lib_keccak::doctor::ensure_passed(&checks)
+}
+
+/// Prints the opcode-level gas breakdown of hashing `input`, split into the fuzzer's absorb chunks.
+fn opcode_gas(fuzzer: &DiffFuzzer, input: &[u8]) -> Result<()> {
+    #[cfg(feature = "evm")]
+    {
+        use lib_keccak::opcodes::{opcode_table, profile_opcodes};
+
+        let chunks = absorb_chunks(input, fuzzer.absorb_chunk_bytes);
+        let (inspector, gas) = profile_opcodes(&fuzzer.deployment(), &chunks)?;
+        print!("{}", opcode_table(&inspector, gas));
+        Ok(())
+    }
+    #[cfg(not(feature = "evm"))]
+    {
+        let _ = (fuzzer, input);
+        bail!("The opcode gas breakdown requires the `evm` feature");
+    }
+}
+
+/// Prints the gas used to hash `input`, split into the fuzzer's absorb chunks, by function of the
+/// contract's `artifact` and the artifacts of its `sources`.
+fn function_gas(
+    fuzzer: &DiffFuzzer,
+    input: &[u8],
+    artifact: &Path,
+    sources: &[PathBuf],
+) -> Result<()> {
+    #[cfg(all(feature = "evm", feature = "json"))]
+    {
+        use lib_keccak::{
+            harness::contract_code,
+            opcodes::profile_opcodes,
+            sourcemap::{function_table, Artifact},
+        };
 
-        pb.inc(1);
+        let artifact = Artifact::read(artifact, sources)?;
+        if artifact.code != contract_code()? {
+            bail!("The artifact's deployed bytecode is not that of the contract under test");
+        }
+        let chunks = absorb_chunks(input, fuzzer.absorb_chunk_bytes);
+        let (inspector, _) = profile_opcodes(&fuzzer.deployment(), &chunks)?;
+        print!("{}", function_table(&artifact.attribute(&inspector)));
+        Ok(())
+    }
+    #[cfg(not(all(feature = "evm", feature = "json")))]
+    {
+        let _ = (fuzzer, input, artifact, sources);
+        bail!("The function gas attribution requires the `evm` and `json` features");
     }
+}
+
+/// Shrinks a failing input with the fuzzer's backends, printing the minimal reproducer and writing
+/// it to `out` if set.
+fn minimize_input(fuzzer: &DiffFuzzer, input: &[u8], out: Option<PathBuf>) -> Result<()> {
+    let mut engine = fuzzer.engine(false)?;
+    let minimized = minimize(&mut engine, input, fuzzer.absorb_chunk_bytes)?;
 
-    pb.finish_with_message("DONE");
+    println!(
+        "Minimized the {} byte input to {} bytes: 0x{}",
+        input.len(),
+        minimized.len(),
+        hex::encode(&minimized)
+    );
+    if let Some(out) = out {
+        std::fs::write(out, &minimized)?;
+    }
     Ok(())
 }
 
-/// Deploys the stateful sponge contract to the given database.
-fn deploy_contract<T: DatabaseRef>(db: &mut CacheDB<T>) -> Result<()> {
-    let sponge_code = hex::decode(STATEFUL_SPONGE_BYTECODE.trim())?;
+/// Diffs a single input with the fuzzer's backends, printing every implementation's digest, and
+/// writes the EIP-3155 trace of the contract calls to `trace` if set.
+fn replay(fuzzer: &DiffFuzzer, input: &[u8], trace: Option<PathBuf>) -> Result<()> {
+    let chunks = absorb_chunks(input, fuzzer.absorb_chunk_bytes);
+    if let Some(path) = trace {
+        #[cfg(feature = "trace")]
+        {
+            let file = std::fs::File::create(&path)?;
+            lib_keccak::harness::trace_chunks_evm(
+                &mut fuzzer.deployment().init_evm()?,
+                &chunks,
+                Box::new(std::io::BufWriter::new(file)),
+            )?;
+        }
+        #[cfg(not(feature = "trace"))]
+        bail!(
+            "Writing the trace to {} requires the `trace` cargo feature",
+            path.display()
+        );
+    }
 
-    let mut code_hash: [u8; 32] = [0u8; 32];
-    hash_input_tiny(sponge_code.as_slice(), code_hash.as_mut());
+    let mut engine = fuzzer.engine(false)?;
+    let verdict = engine.diff_chunked(&chunks)?;
 
-    let mut acc_info = AccountInfo {
-        balance: U256::ZERO,
-        nonce: 0,
-        code_hash: code_hash.into(),
-        code: Some(Bytecode::new_raw(sponge_code.into())),
-    };
-    db.insert_contract(&mut acc_info);
-    db.insert_account_info(STATEFUL_SPONGE_ADDR, acc_info);
+    for (name, digest) in engine.names().iter().zip(engine.digests()) {
+        println!("{name}: 0x{}", hex::encode(digest));
+    }
+    if !verdict.is_unanimous() {
+        bail!("Hash mismatch ({})", engine.describe(&verdict));
+    }
+
+    println!(
+        "All implementations agree on the {} byte input",
+        input.len()
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bare_seconds_and_components() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::from_secs(172_800));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        for arg in [
+            "213503982334602d",
+            "99999999999999999999s",
+            "18446744073709551615s1s",
+        ] {
+            let err = parse_duration(arg).unwrap_err();
+            assert!(err.to_string().contains("is too long"), "{arg}: {err}");
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Run manifests, which tie the results of a run to the exact code and artifact that produced them.
+//!
+//! A manifest is a JSON object written when a campaign starts, so that even runs that crash or are
+//! killed leave one behind:
+//!
+//! ```text
+//! {
+//!   "tool_version": "0.1.0",
+//!   "revm_version": "3.5.0",
+//!   "contract_code_hash": "0x5b1d...",
+//!   "args": ["evm-keccak-fuzz", "--seed", "42"],
+//!   "seed": 42,
+//!   "started_at": 1706745600
+//! }
+//! ```
+//!
+//! The revm version is the one locked in the crate's `Cargo.lock` when it was built, and both it and
+//! the contract code hash are `null` without the `evm` feature.
+
+use crate::{reporter::Reporter, DiffFuzzer};
+use anyhow::{Context, Result};
+use std::{
+    fmt::Write,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The revm version that the crate was built against.
+pub const REVM_VERSION: &str = env!("LIB_KECCAK_REVM_VERSION");
+
+/// Writes the manifest of every campaign to a file when it starts.
+#[derive(Debug, Clone)]
+pub struct ManifestReporter {
+    /// The file to write the manifest to.
+    pub path: PathBuf,
+    /// The command line arguments of the run, including the program name.
+    pub args: Vec<String>,
+}
+
+impl Reporter for ManifestReporter {
+    fn on_start(&self, config: &DiffFuzzer) -> Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, manifest_json(&self.args, config.seed)?)
+            .with_context(|| format!("Failed to write manifest {}", self.path.display()))
+    }
+}
+
+/// Returns the manifest of a run with the command line `args` and the master seed `seed`.
+pub fn manifest_json(args: &[String], seed: Option<u64>) -> Result<String> {
+    #[cfg(feature = "evm")]
+    let (revm_version, code_hash) = (
+        quote(REVM_VERSION),
+        quote(&format!(
+            "0x{}",
+            alloy_primitives::hex::encode(crate::harness::contract_code_hash()?)
+        )),
+    );
+    #[cfg(not(feature = "evm"))]
+    let (revm_version, code_hash) = ("null".to_string(), "null".to_string());
+
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let args = args.iter().map(|arg| quote(arg)).collect::<Vec<_>>();
+    let seed = seed.map_or_else(|| "null".to_string(), |seed| seed.to_string());
+
+    let mut json = String::from("{\n");
+    writeln!(
+        json,
+        "  \"tool_version\": {},",
+        quote(env!("CARGO_PKG_VERSION"))
+    )?;
+    writeln!(json, "  \"revm_version\": {revm_version},")?;
+    writeln!(json, "  \"contract_code_hash\": {code_hash},")?;
+    writeln!(json, "  \"args\": [{}],", args.join(", "))?;
+    writeln!(json, "  \"seed\": {seed},")?;
+    writeln!(json, "  \"started_at\": {started_at}")?;
+    json.push_str("}\n");
+    Ok(json)
+}
+
+/// Returns `text` as a JSON string literal.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
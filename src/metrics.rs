@@ -0,0 +1,135 @@
+//! A Prometheus metrics endpoint, so that long campaigns can be monitored and alerted on like any
+//! other service.
+//!
+//! The endpoint serves the campaign's [LiveStats] in the Prometheus text exposition format on every
+//! HTTP `GET` request:
+//!
+//! ```text
+//! # HELP hashes_total The number of inputs that were diffed.
+//! # TYPE hashes_total counter
+//! hashes_total 1204512
+//! ...
+//! ```
+//!
+//! `hashes_per_second` is the throughput over the last five seconds.
+
+use crate::stats::{LiveStats, StatsSnapshot};
+use anyhow::{Context, Result};
+use std::{
+    fmt::Write,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Notify,
+};
+
+/// The interval over which `hashes_per_second` is measured.
+const RATE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The largest request that is read before responding, in bytes.
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// Binds the metrics endpoint to `addr`.
+pub(crate) async fn bind_metrics(addr: SocketAddr) -> Result<TcpListener> {
+    TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind the metrics endpoint to {addr}"))
+}
+
+/// Serves the metrics of `stats` on `listener` until `done` is notified.
+pub(crate) async fn metrics_task(listener: TcpListener, stats: Arc<LiveStats>, done: Arc<Notify>) {
+    let mut ticker = tokio::time::interval(RATE_INTERVAL);
+    let mut last = (Instant::now(), stats.snapshot());
+    let mut rate = 0.0;
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let now = (Instant::now(), stats.snapshot());
+                let secs = now.0.duration_since(last.0).as_secs_f64();
+                if secs > 0.0 {
+                    rate = (now.1.diffs - last.1.diffs) as f64 / secs;
+                }
+                last = now;
+            }
+            accepted = listener.accept() => {
+                // A failed connection is the scraper's problem, and never stops the campaign.
+                if let Ok((stream, _)) = accepted {
+                    let body = exposition(stats.snapshot(), rate);
+                    tokio::spawn(async move {
+                        let _ = respond(stream, &body).await;
+                    });
+                }
+            }
+            _ = done.notified() => return,
+        }
+    }
+}
+
+/// Returns the metrics in the Prometheus text exposition format.
+fn exposition(stats: StatsSnapshot, hashes_per_second: f64) -> String {
+    let metrics = [
+        (
+            "hashes_total",
+            "counter",
+            "The number of inputs that were diffed.",
+            stats.diffs.to_string(),
+        ),
+        (
+            "mismatches_total",
+            "counter",
+            "The number of mismatches that were found.",
+            stats.mismatches.to_string(),
+        ),
+        (
+            "gas_used_total",
+            "counter",
+            "The total gas used by the contract calls of every diffed input.",
+            stats.gas_used.to_string(),
+        ),
+        (
+            "hashes_per_second",
+            "gauge",
+            "The number of inputs diffed per second, over the last five seconds.",
+            format!("{hashes_per_second:.1}"),
+        ),
+    ];
+
+    let mut body = String::new();
+    for (name, kind, help, value) in metrics {
+        writeln!(body, "# HELP {name} {help}").unwrap();
+        writeln!(body, "# TYPE {name} {kind}").unwrap();
+        writeln!(body, "{name} {value}").unwrap();
+    }
+    body
+}
+
+/// Reads an HTTP request from `stream` and responds with `body` to `GET` requests.
+async fn respond(mut stream: TcpStream, body: &str) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || request.len() + n > MAX_REQUEST_BYTES {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let response = if request.starts_with(b"GET ") {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len()
+        )
+    } else {
+        "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            .to_string()
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Shrinking of failing inputs into minimal reproducers.
+
+use crate::{engine::DiffEngine, fuzzer::diff_input, permutation::RATE_BYTES};
+use anyhow::{bail, Result};
+
+/// Shrinks `input` while the implementations still disagree on it, and returns the minimal
+/// reproducer. The input is first truncated to the shortest failing prefix with a binary search on
+/// its length, then shrunk further by removing whole blocks (padding bugs tend to depend only on the
+/// length modulo the rate) and ever smaller windows of bytes, and finally simplified by zeroing out
+/// every byte that is not needed for the mismatch.
+///
+/// Returns an error if the implementations agree on `input` in the first place.
+pub fn minimize(
+    engine: &mut DiffEngine,
+    input: &[u8],
+    absorb_chunk_bytes: Option<usize>,
+) -> Result<Vec<u8>> {
+    let mut fails = |candidate: &[u8]| {
+        Ok::<_, anyhow::Error>(!diff_input(engine, candidate, absorb_chunk_bytes)?.is_unanimous())
+    };
+
+    if !fails(input)? {
+        bail!("The implementations agree on the input, so there is nothing to minimize");
+    }
+    shrink_bytes(input, fails)
+}
+
+/// Shrinks `input`, which must fail, while `fails` still holds for it, like [minimize] does, and
+/// returns the shrunk input.
+pub fn shrink_bytes(input: &[u8], mut fails: impl FnMut(&[u8]) -> Result<bool>) -> Result<Vec<u8>> {
+    // Binary search for the shortest failing prefix. `hi` always fails.
+    let (mut lo, mut hi) = (0, input.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if fails(&input[..mid])? {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    let mut input = input[..hi].to_vec();
+
+    // Remove runs of whole blocks, and then windows of bytes of halving sizes.
+    let blocks = (1..=input.len() / RATE_BYTES).rev().map(|n| n * RATE_BYTES);
+    let halvings = std::iter::successors(Some(input.len() / 2), |n| Some(n / 2));
+    for window in blocks.chain(halvings.take_while(|n| *n > 0)) {
+        let mut start = 0;
+        while start + window <= input.len() {
+            let mut candidate = input.clone();
+            candidate.drain(start..start + window);
+            if fails(&candidate)? {
+                input = candidate;
+            } else {
+                start += window;
+            }
+        }
+    }
+
+    // Zero out every byte that the mismatch does not depend on.
+    for i in 0..input.len() {
+        if input[i] == 0 {
+            continue;
+        }
+        let byte = std::mem::take(&mut input[i]);
+        if !fails(&input)? {
+            input[i] = byte;
+        }
+    }
+
+    Ok(input)
+}
@@ -0,0 +1,264 @@
+//! Fitted gas cost models of the `StatefulSponge` contract, so that downstream contracts can budget
+//! gas for preimages of arbitrary sizes.
+//!
+//! The model is `gas = base + per_block * blocks + per_byte * len`, where `blocks = len / 136 + 1`
+//! is the number of permutations of the padded input, fitted by least squares to the most gas used
+//! by an input of every length of a profiled campaign, typically a sweep. The per-byte term covers
+//! the calldata and the copying of the input, and the per-block term the permutations.
+//!
+//! Besides the exact fit, the model carries an integer budget: the fitted rates rounded up, with
+//! the smallest base that makes the budget an upper bound of every measured length.
+
+use crate::{permutation::RATE_BYTES, report::Report, reporter::Reporter, DiffFuzzer};
+use anyhow::{bail, Context, Result};
+use std::{fmt::Write, fs, path::PathBuf};
+
+/// A gas cost model fitted to measured gas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasModel {
+    /// The fitted fixed cost.
+    pub base: f64,
+    /// The fitted cost of every permutation.
+    pub per_block: f64,
+    /// The fitted cost of every input byte.
+    pub per_byte: f64,
+    /// The number of lengths that the model was fitted to.
+    pub samples: usize,
+    /// The longest length that the model was fitted to.
+    pub max_len: usize,
+    /// The root mean square of the residuals of the fit.
+    pub rms_residual: f64,
+    /// The largest absolute residual of the fit.
+    pub max_residual: f64,
+    /// The integer upper bound of the measured gas (see [GasBudget]).
+    pub budget: GasBudget,
+}
+
+/// An integer gas budget, `base + per_block * blocks + per_byte * len`, that is at least the
+/// measured gas of every length that its model was fitted to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GasBudget {
+    /// The fixed cost.
+    pub base: u64,
+    /// The cost of every permutation.
+    pub per_block: u64,
+    /// The cost of every input byte.
+    pub per_byte: u64,
+}
+
+impl GasBudget {
+    /// Returns the budget of a `len` byte input.
+    pub fn gas(&self, len: usize) -> u64 {
+        self.base + self.per_block * blocks(len) as u64 + self.per_byte * len as u64
+    }
+}
+
+impl GasModel {
+    /// Fits the model to the most gas used by an input of every length in `report`, which must be
+    /// profiled (see [DiffFuzzer::profile]).
+    pub fn of(report: &Report) -> Result<Self> {
+        let points = report
+            .lengths
+            .iter()
+            .filter(|(_, profile)| profile.gas.samples > 0)
+            .map(|(len, profile)| (*len, profile.gas.max))
+            .collect::<Vec<_>>();
+        Self::fit(&points)
+    }
+
+    /// Fits the model to the gas of every `(len, gas)` point by least squares.
+    pub fn fit(points: &[(usize, u64)]) -> Result<Self> {
+        let rows = points
+            .iter()
+            .map(|&(len, gas)| ([1.0, blocks(len) as f64, len as f64], gas as f64))
+            .collect::<Vec<_>>();
+
+        // Solve the normal equations, (XᵀX) β = Xᵀy.
+        let mut normal = [[0.0; 4]; 3];
+        for (x, y) in &rows {
+            for i in 0..3 {
+                for j in 0..3 {
+                    normal[i][j] += x[i] * x[j];
+                }
+                normal[i][3] += x[i] * y;
+            }
+        }
+        let Some([base, per_block, per_byte]) = solve(normal) else {
+            bail!(
+                "Fitting the gas model requires lengths spanning at least two blocks, measured {} \
+                 lengths",
+                points.len()
+            );
+        };
+
+        let residuals = rows
+            .iter()
+            .map(|(x, y)| y - (base * x[0] + per_block * x[1] + per_byte * x[2]))
+            .collect::<Vec<_>>();
+        let rms_residual =
+            (residuals.iter().map(|r| r * r).sum::<f64>() / residuals.len() as f64).sqrt();
+        let max_residual = residuals.iter().fold(0.0f64, |max, r| max.max(r.abs()));
+
+        let mut budget = GasBudget {
+            base: 0,
+            per_block: per_block.max(0.0).ceil() as u64,
+            per_byte: per_byte.max(0.0).ceil() as u64,
+        };
+        budget.base = points
+            .iter()
+            .map(|&(len, gas)| gas.saturating_sub(budget.gas(len)))
+            .max()
+            .unwrap_or_default();
+
+        Ok(Self {
+            base,
+            per_block,
+            per_byte,
+            samples: points.len(),
+            max_len: points.iter().map(|(len, _)| *len).max().unwrap_or_default(),
+            rms_residual,
+            max_residual,
+            budget,
+        })
+    }
+
+    /// Returns the gas of a `len` byte input predicted by the fit.
+    pub fn predict(&self, len: usize) -> f64 {
+        self.base + self.per_block * blocks(len) as f64 + self.per_byte * len as f64
+    }
+
+    /// Renders the model as a markdown table of its coefficients, followed by its residuals.
+    pub fn markdown(&self) -> String {
+        let mut markdown = String::new();
+        writeln!(markdown, "| Term | Fitted gas | Budget gas |").unwrap();
+        writeln!(markdown, "| --- | ---: | ---: |").unwrap();
+        for (term, fitted, budget) in [
+            ("base", self.base, self.budget.base),
+            ("per block", self.per_block, self.budget.per_block),
+            ("per byte", self.per_byte, self.budget.per_byte),
+        ] {
+            writeln!(markdown, "| {term} | {fitted:.2} | {budget} |").unwrap();
+        }
+        writeln!(markdown).unwrap();
+        writeln!(
+            markdown,
+            "`gas = base + per_block * (len / {RATE_BYTES} + 1) + per_byte * len`, fitted to {} \
+             lengths up to {} bytes, with an RMS residual of {:.1} gas and a largest residual of \
+             {:.1} gas. The budget is at least the measured gas of every length.",
+            self.samples, self.max_len, self.rms_residual, self.max_residual
+        )
+        .unwrap();
+        markdown
+    }
+
+    /// Renders the budget as Solidity constants, along with a function that budgets a preimage.
+    pub fn solidity(&self) -> String {
+        let mut solidity = String::new();
+        writeln!(solidity, "// SPDX-License-Identifier: MIT").unwrap();
+        writeln!(solidity, "pragma solidity ^0.8.0;").unwrap();
+        writeln!(solidity).unwrap();
+        writeln!(
+            solidity,
+            "// Gas budget of the `StatefulSponge` contract, fitted by `evm-keccak-fuzz` {} to {} \
+             lengths up to {} bytes.",
+            env!("CARGO_PKG_VERSION"),
+            self.samples,
+            self.max_len
+        )
+        .unwrap();
+        writeln!(
+            solidity,
+            "uint256 constant KECCAK_GAS_BASE = {};",
+            self.budget.base
+        )
+        .unwrap();
+        writeln!(
+            solidity,
+            "uint256 constant KECCAK_GAS_PER_BLOCK = {};",
+            self.budget.per_block
+        )
+        .unwrap();
+        writeln!(
+            solidity,
+            "uint256 constant KECCAK_GAS_PER_BYTE = {};",
+            self.budget.per_byte
+        )
+        .unwrap();
+        writeln!(solidity).unwrap();
+        writeln!(
+            solidity,
+            "/// @notice Returns the gas budget of hashing a `_len` byte preimage."
+        )
+        .unwrap();
+        writeln!(
+            solidity,
+            "function keccakGasBudget(uint256 _len) pure returns (uint256) {{"
+        )
+        .unwrap();
+        writeln!(
+            solidity,
+            "    return KECCAK_GAS_BASE + KECCAK_GAS_PER_BLOCK * (_len / {RATE_BYTES} + 1) + \
+             KECCAK_GAS_PER_BYTE * _len;"
+        )
+        .unwrap();
+        writeln!(solidity, "}}").unwrap();
+        solidity
+    }
+}
+
+/// Fits a [GasModel] to every campaign when it finishes successfully, prints it to stderr and
+/// writes it to the configured files. The campaign must be profiled (see [DiffFuzzer::profile]).
+#[derive(Debug, Clone, Default)]
+pub struct GasModelReporter {
+    /// The file to write the markdown table to, if any.
+    pub markdown: Option<PathBuf>,
+    /// The file to write the Solidity constants to, if any.
+    pub solidity: Option<PathBuf>,
+}
+
+impl Reporter for GasModelReporter {
+    fn on_finish(&self, _: &DiffFuzzer, outcome: &Result<Report>) -> Result<()> {
+        let Ok(report) = outcome else {
+            return Ok(());
+        };
+        let model = GasModel::of(report)?;
+        eprint!("Gas model:\n{}", model.markdown());
+        if let Some(path) = self.markdown.as_ref() {
+            fs::write(path, model.markdown())
+                .with_context(|| format!("Failed to write gas model {}", path.display()))?;
+        }
+        if let Some(path) = self.solidity.as_ref() {
+            fs::write(path, model.solidity())
+                .with_context(|| format!("Failed to write gas model {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the number of permutations of a padded `len` byte input.
+fn blocks(len: usize) -> usize {
+    len / RATE_BYTES + 1
+}
+
+/// Solves the 3x3 linear system in the augmented matrix `m` by Gaussian elimination with partial
+/// pivoting, or returns `None` if it is singular.
+fn solve(mut m: [[f64; 4]; 3]) -> Option<[f64; 3]> {
+    let scale = m.iter().flatten().fold(0.0f64, |max, x| max.max(x.abs()));
+    for col in 0..3 {
+        let pivot = (col..3).max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))?;
+        if m[pivot][col].abs() <= scale * 1e-12 {
+            return None;
+        }
+        m.swap(col, pivot);
+        let pivot_row = m[col];
+        for (row, values) in m.iter_mut().enumerate() {
+            if row != col {
+                let factor = values[col] / pivot_row[col];
+                for (value, pivot_value) in values.iter_mut().zip(pivot_row).skip(col) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+    Some([m[0][3] / m[0][0], m[1][3] / m[1][1], m[2][3] / m[2][2]])
+}
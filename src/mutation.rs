@@ -0,0 +1,100 @@
+//! Mutation operators that derive new fuzz inputs from existing corpus entries.
+
+use rand::{seq::SliceRandom, Rng};
+
+/// The maximum number of mutations stacked onto a single input.
+const MAX_STACKED_MUTATIONS: usize = 4;
+
+/// How the fuzzer generates new inputs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Strategy {
+    /// Every input is a uniformly random buffer.
+    Random,
+    /// Inputs are mutated from corpus entries, falling back to random buffers while the corpus is
+    /// empty.
+    Mutate,
+    /// Inputs are mutated from corpus entries or generated randomly with equal probability.
+    #[default]
+    Mixed,
+    /// Every input is an Ethereum-shaped payload, such as an RLP-encoded transaction, an ABI-encoded
+    /// call or a Merkle Patricia Trie node.
+    Payloads,
+}
+
+impl Strategy {
+    /// Returns `true` if any inputs are mutated from corpus entries, i.e. if generated inputs
+    /// depend on the corpus.
+    pub fn mutates(self) -> bool {
+        !matches!(self, Self::Random | Self::Payloads)
+    }
+
+    /// Returns `true` if the next input should be mutated from a corpus entry.
+    pub fn should_mutate<R: Rng>(self, rng: &mut R) -> bool {
+        match self {
+            Self::Random | Self::Payloads => false,
+            Self::Mutate => true,
+            Self::Mixed => rng.gen_bool(0.5),
+        }
+    }
+}
+
+/// Applies between 1 and [MAX_STACKED_MUTATIONS] random mutations to `input` in place, keeping its
+/// length strictly below `max_len`. `corpus` supplies the donor inputs for crossover.
+pub fn mutate<R: Rng>(rng: &mut R, input: &mut Vec<u8>, corpus: &[Vec<u8>], max_len: usize) {
+    for _ in 0..rng.gen_range(1..=MAX_STACKED_MUTATIONS) {
+        match rng.gen_range(0..9) {
+            // Flip a single bit.
+            0 if !input.is_empty() => {
+                let idx = rng.gen_range(0..input.len());
+                input[idx] ^= 1 << rng.gen_range(0..8);
+            }
+            // Overwrite a byte with a random value.
+            1 if !input.is_empty() => {
+                let idx = rng.gen_range(0..input.len());
+                input[idx] = rng.gen();
+            }
+            // Swap two bytes.
+            2 if input.len() >= 2 => {
+                let (a, b) = (rng.gen_range(0..input.len()), rng.gen_range(0..input.len()));
+                input.swap(a, b);
+            }
+            // Insert a random byte.
+            3 => {
+                let idx = rng.gen_range(0..=input.len());
+                input.insert(idx, rng.gen());
+            }
+            // Remove a byte.
+            4 if !input.is_empty() => {
+                input.remove(rng.gen_range(0..input.len()));
+            }
+            // Truncate the input.
+            5 if !input.is_empty() => {
+                input.truncate(rng.gen_range(0..input.len()));
+            }
+            // Duplicate a chunk of the input in place.
+            6 if !input.is_empty() => {
+                let start = rng.gen_range(0..input.len());
+                let end = rng.gen_range(start + 1..=input.len());
+                let at = rng.gen_range(0..=input.len());
+                let chunk = input[start..end].to_vec();
+                input.splice(at..at, chunk);
+            }
+            // Crossover: splice the head of the input onto the tail of a donor corpus entry.
+            7 if !corpus.is_empty() => {
+                let donor = corpus.choose(rng).expect("corpus is not empty");
+                let head = rng.gen_range(0..=input.len());
+                let tail = rng.gen_range(0..=donor.len());
+                input.truncate(head);
+                input.extend_from_slice(&donor[tail..]);
+            }
+            // Extend the input with random bytes.
+            _ => {
+                let extra = rng.gen_range(1..=32);
+                input.extend((0..extra).map(|_| rng.gen::<u8>()));
+            }
+        }
+    }
+
+    input.truncate(max_len.saturating_sub(1));
+}
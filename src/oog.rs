@@ -0,0 +1,316 @@
+//! Out-of-gas threshold discovery: the smallest transaction gas limit at which every call of the
+//! `StatefulSponge` contract succeeds, so that callers can budget the gas of their calls.
+//!
+//! Campaigns execute the calls with an unlimited gas limit. Here, the limit of every call is
+//! bisected down to the smallest one at which the call still succeeds, which can exceed the gas
+//! that the call uses (e.g. `SSTORE` requires more than 2300 gas left even when it costs less). The
+//! threshold is then cross-checked with random gas limits around it: the call must fail under every
+//! limit below it, and succeed under every limit at or above it.
+//!
+//! On top of the thresholds, [max_input] binary-searches the largest input that can be hashed
+//! under a gas limit, such as that of a block, for practical upper bounds of on-chain preimage
+//! hashing.
+
+use crate::{engine::Deployment, permutation::RATE_BYTES};
+use anyhow::Result;
+use std::io::Write;
+
+/// The gas used by a contract call, and the smallest gas limit at which it succeeds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallThreshold {
+    /// The gas used by the call under an unlimited gas limit.
+    pub gas_used: u64,
+    /// The smallest gas limit at which the call succeeds.
+    pub min_gas_limit: u64,
+}
+
+impl CallThreshold {
+    /// Returns the gas that the call must have available beyond the gas that it uses.
+    pub fn margin(&self) -> u64 {
+        self.min_gas_limit.saturating_sub(self.gas_used)
+    }
+}
+
+/// The out-of-gas thresholds of hashing an input of a single length in a single `absorb` call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OogThreshold {
+    /// The input length, in bytes.
+    pub len: usize,
+    /// The threshold of the `absorb` call.
+    pub absorb: CallThreshold,
+    /// The threshold of the `squeeze` call.
+    pub squeeze: CallThreshold,
+}
+
+impl OogThreshold {
+    /// Returns the number of permutations of the padded input.
+    pub fn blocks(&self) -> usize {
+        self.len / RATE_BYTES + 1
+    }
+}
+
+/// Discovers the out-of-gas thresholds of hashing an input of every length in `lengths`, each in a
+/// fresh EVM with the contract deployed as in `deployment`, cross-checking every threshold with
+/// `probes` random gas limits drawn from an RNG seeded with `seed`. The inputs are made of non-zero
+/// bytes, like those of [crate::gas::measure_gas_curve]. Requires the `evm` feature.
+pub fn measure_oog_thresholds(
+    deployment: &Deployment,
+    lengths: impl IntoIterator<Item = usize>,
+    probes: usize,
+    seed: u64,
+) -> Result<Vec<OogThreshold>> {
+    #[cfg(feature = "evm")]
+    {
+        use rand::SeedableRng;
+
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(seed);
+        lengths
+            .into_iter()
+            .map(|len| {
+                let input = (0..len).map(|i| (i % 255) as u8 + 1).collect::<Vec<_>>();
+                let thresholds = evm::call_thresholds(deployment, &[&input], probes, &mut rng)?;
+                Ok(OogThreshold {
+                    len,
+                    absorb: thresholds[0],
+                    squeeze: thresholds[1],
+                })
+            })
+            .collect()
+    }
+    #[cfg(not(feature = "evm"))]
+    {
+        let _ = (deployment, lengths, probes, seed);
+        anyhow::bail!("Out-of-gas threshold discovery requires the `evm` feature")
+    }
+}
+
+/// The largest input that can be hashed by calls that fit a gas limit (see [max_input]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaxInput {
+    /// The length of the input, in bytes.
+    pub len: usize,
+    /// The number of `absorb` calls that the input is split across.
+    pub absorb_calls: usize,
+    /// The largest out-of-gas threshold of the calls.
+    pub max_gas_limit: u64,
+    /// The sum of the out-of-gas thresholds of the calls.
+    pub total_gas_limit: u64,
+}
+
+impl MaxInput {
+    /// Returns the number of permutations of the padded input, were it absorbed in a single call.
+    pub fn blocks(&self) -> usize {
+        self.len / RATE_BYTES + 1
+    }
+}
+
+/// How the calls of an input must fit the gas limit of [max_input].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasFit {
+    /// The input is absorbed in a single call, and every call fits the gas limit on its own, e.g.
+    /// the transaction gas limit or the gas that a caller forwards.
+    SingleCall,
+    /// The input is split across absorb calls of at most `chunk_bytes` bytes each, and all calls
+    /// fit the gas limit together, e.g. that of the block that they are included in.
+    MultiCall {
+        /// The largest number of bytes absorbed per call.
+        chunk_bytes: usize,
+    },
+}
+
+/// Binary-searches the largest input, made of non-zero bytes, whose calls fit `gas_limit` as
+/// required by `fit`, with the contract deployed as in `deployment`. Assumes that the gas of the
+/// calls grows with the input length. Requires the `evm` feature.
+pub fn max_input(deployment: &Deployment, gas_limit: u64, fit: GasFit) -> Result<MaxInput> {
+    if let GasFit::MultiCall { chunk_bytes: 0 } = fit {
+        anyhow::bail!("The chunk size must be non-zero");
+    }
+    let measure = |len: usize| -> Result<Option<MaxInput>> {
+        let input = (0..len).map(|i| (i % 255) as u8 + 1).collect::<Vec<_>>();
+        let chunks = match fit {
+            GasFit::SingleCall => vec![input.as_slice()],
+            GasFit::MultiCall { chunk_bytes } => {
+                crate::engine::absorb_chunks(&input, Some(chunk_bytes))
+            }
+        };
+        let thresholds = call_thresholds(deployment, &chunks)?;
+        let measured = MaxInput {
+            len,
+            absorb_calls: chunks.len(),
+            max_gas_limit: thresholds
+                .iter()
+                .map(|t| t.min_gas_limit)
+                .max()
+                .unwrap_or(0),
+            total_gas_limit: thresholds.iter().map(|t| t.min_gas_limit).sum(),
+        };
+        let fits = match fit {
+            GasFit::SingleCall => measured.max_gas_limit <= gas_limit,
+            GasFit::MultiCall { .. } => measured.total_gas_limit <= gas_limit,
+        };
+        Ok(fits.then_some(measured))
+    };
+
+    let Some(mut fitting) = measure(0)? else {
+        anyhow::bail!("Not even an empty input can be hashed under a gas limit of {gas_limit}");
+    };
+    // Double the length until it no longer fits, then bisect between the two.
+    let mut failing = RATE_BYTES;
+    while let Some(measured) = measure(failing)? {
+        fitting = measured;
+        failing *= 2;
+    }
+    while failing - fitting.len > 1 {
+        let len = fitting.len + (failing - fitting.len) / 2;
+        match measure(len)? {
+            Some(measured) => fitting = measured,
+            None => failing = len,
+        }
+    }
+    Ok(fitting)
+}
+
+/// Discovers the out-of-gas threshold of every absorb call of `chunks` and of the squeeze call,
+/// without cross-checking them.
+fn call_thresholds(deployment: &Deployment, chunks: &[&[u8]]) -> Result<Vec<CallThreshold>> {
+    #[cfg(feature = "evm")]
+    {
+        use rand::SeedableRng;
+
+        // No gas limits are drawn without probes.
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+        evm::call_thresholds(deployment, chunks, 0, &mut rng)
+    }
+    #[cfg(not(feature = "evm"))]
+    {
+        let _ = (deployment, chunks);
+        anyhow::bail!("Out-of-gas threshold discovery requires the `evm` feature")
+    }
+}
+
+/// Writes the thresholds as CSV to `writer`, one row per length:
+///
+/// ```text
+/// length,blocks,absorb_gas,absorb_min_gas_limit,squeeze_gas,squeeze_min_gas_limit
+/// ```
+pub fn write_oog_csv(mut writer: impl Write, thresholds: &[OogThreshold]) -> Result<()> {
+    writeln!(
+        writer,
+        "length,blocks,absorb_gas,absorb_min_gas_limit,squeeze_gas,squeeze_min_gas_limit"
+    )?;
+    for threshold in thresholds {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{}",
+            threshold.len,
+            threshold.blocks(),
+            threshold.absorb.gas_used,
+            threshold.absorb.min_gas_limit,
+            threshold.squeeze.gas_used,
+            threshold.squeeze.min_gas_limit
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(feature = "evm")]
+mod evm {
+    use super::CallThreshold;
+    use crate::{
+        engine::Deployment,
+        gas::intrinsic_gas,
+        harness::{absorb_calldata, squeeze_calldata, HarnessDB},
+    };
+    use anyhow::{bail, Result};
+    use rand::Rng;
+    use revm::{primitives::ExecutionResult, EVM};
+
+    /// Discovers the threshold of every absorb call of `chunks` and of the squeeze call, in order,
+    /// in a fresh EVM with the contract deployed as in `deployment`.
+    pub(super) fn call_thresholds<R: Rng>(
+        deployment: &Deployment,
+        chunks: &[&[u8]],
+        probes: usize,
+        rng: &mut R,
+    ) -> Result<Vec<CallThreshold>> {
+        let mut evm = deployment.init_evm()?;
+        let calls = chunks
+            .iter()
+            .map(|chunk| ("absorb", absorb_calldata(chunk)))
+            .chain(std::iter::once(("squeeze", squeeze_calldata())));
+
+        let mut thresholds = Vec::with_capacity(chunks.len() + 1);
+        for (call, calldata) in calls {
+            let intrinsic = intrinsic_gas(&calldata);
+            evm.env.tx.data = calldata.into();
+            let threshold = call_threshold(&mut evm, call, intrinsic)?;
+            for _ in 0..probes {
+                let limit = rng.gen_range(intrinsic..=threshold.min_gas_limit.saturating_mul(2));
+                let succeeds = succeeds(&mut evm, limit)?;
+                if succeeds != (limit >= threshold.min_gas_limit) {
+                    bail!(
+                        "The {call} call {} with a gas limit of {limit}, but its out-of-gas \
+                         threshold is {}",
+                        if succeeds { "succeeded" } else { "failed" },
+                        threshold.min_gas_limit
+                    );
+                }
+            }
+
+            // Execute the call for real, so that the next call sees its state.
+            evm.env.tx.gas_limit = u64::MAX;
+            match evm.transact_commit()? {
+                ExecutionResult::Success { .. } => {}
+                r => bail!("The {call} call failed: {r:?}"),
+            }
+            thresholds.push(threshold);
+        }
+        Ok(thresholds)
+    }
+
+    /// Bisects the smallest gas limit at which the pending call of `evm` succeeds.
+    fn call_threshold(
+        evm: &mut EVM<HarnessDB>,
+        call: &str,
+        intrinsic: u64,
+    ) -> Result<CallThreshold> {
+        evm.env.tx.gas_limit = u64::MAX;
+        let gas_used = match evm.transact()?.result {
+            ExecutionResult::Success { gas_used, .. } => gas_used,
+            r => bail!("The {call} call failed: {r:?}"),
+        };
+
+        // The call fails below its intrinsic gas, and succeeds under an unlimited gas limit.
+        let mut failing = intrinsic - 1;
+        let mut succeeding = gas_used;
+        while !succeeds(evm, succeeding)? {
+            failing = succeeding;
+            succeeding = succeeding.saturating_mul(2);
+        }
+        while succeeding - failing > 1 {
+            let limit = failing + (succeeding - failing) / 2;
+            if succeeds(evm, limit)? {
+                succeeding = limit;
+            } else {
+                failing = limit;
+            }
+        }
+        Ok(CallThreshold {
+            gas_used,
+            min_gas_limit: succeeding,
+        })
+    }
+
+    /// Returns `true` if the pending call of `evm` succeeds under a gas limit of `limit`, without
+    /// committing its state.
+    fn succeeds(evm: &mut EVM<HarnessDB>, limit: u64) -> Result<bool> {
+        evm.env.tx.gas_limit = limit;
+        Ok(match evm.transact() {
+            Ok(result) => result.result.is_success(),
+            // The limit doesn't cover the intrinsic gas.
+            Err(revm::primitives::EVMError::Transaction(_)) => false,
+            Err(e) => bail!("Failed to execute the call: {e:?}"),
+        })
+    }
+}
@@ -0,0 +1,175 @@
+//! An opcode-level gas breakdown of the `StatefulSponge` contract, showing where the `LibKeccak`
+//! implementation spends its gas.
+//!
+//! The [OpcodeGas] inspector attributes the gas of every executed instruction to its opcode. The
+//! permutation is written in pure Yul and never executes `KECCAK256`, so the breakdown covers the
+//! whole implementation. The intrinsic gas of the transactions (the base cost and the calldata) is
+//! not spent by any instruction, and is reported separately by [opcode_table].
+
+use crate::{
+    engine::Deployment,
+    gas::CallGas,
+    harness::{hash_chunks_evm_inspected_metered, HarnessDB},
+};
+use anyhow::Result;
+use revm::{
+    interpreter::{InstructionResult, Interpreter, OPCODE_JUMPMAP},
+    EVMData, Inspector,
+};
+use std::fmt::Write;
+
+/// The gas spent on a single opcode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpcodeCost {
+    /// The opcode.
+    pub opcode: u8,
+    /// The number of times the opcode executed.
+    pub count: u64,
+    /// The total gas spent executing the opcode, including memory expansion.
+    pub gas: u64,
+}
+
+impl OpcodeCost {
+    /// Returns the mnemonic of the opcode, e.g. `MSTORE`.
+    pub fn name(&self) -> &'static str {
+        OPCODE_JUMPMAP[self.opcode as usize].unwrap_or("UNKNOWN")
+    }
+}
+
+/// An [Inspector] that attributes the gas of every executed instruction to its opcode.
+#[derive(Debug, Clone)]
+pub struct OpcodeGas {
+    /// The program counter and opcode of the executing instruction and the gas remaining before
+    /// it, between [Inspector::step] and [Inspector::step_end].
+    pending: Option<(usize, u8, u64)>,
+    /// The gas spent on every opcode, indexed by opcode.
+    costs: [OpcodeCost; 256],
+    /// The gas spent on the instruction at every program counter, indexed by program counter.
+    pc_gas: Vec<u64>,
+}
+
+impl Default for OpcodeGas {
+    fn default() -> Self {
+        Self {
+            pending: None,
+            costs: std::array::from_fn(|opcode| OpcodeCost {
+                opcode: opcode as u8,
+                ..Default::default()
+            }),
+            pc_gas: Vec::new(),
+        }
+    }
+}
+
+impl OpcodeGas {
+    /// Returns the gas spent on every executed opcode, most expensive first.
+    pub fn breakdown(&self) -> Vec<OpcodeCost> {
+        let mut costs = self
+            .costs
+            .iter()
+            .filter(|cost| cost.count > 0)
+            .copied()
+            .collect::<Vec<_>>();
+        costs.sort_by_key(|cost| (std::cmp::Reverse(cost.gas), cost.opcode));
+        costs
+    }
+
+    /// Returns the gas spent on the instruction at every program counter, indexed by program
+    /// counter. Program counters past the last executed instruction are missing.
+    pub fn pc_gas(&self) -> &[u64] {
+        &self.pc_gas
+    }
+
+    /// Returns the total gas spent executing instructions.
+    pub fn total(&self) -> u64 {
+        self.costs.iter().map(|cost| cost.gas).sum()
+    }
+}
+
+impl Inspector<HarnessDB> for OpcodeGas {
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, HarnessDB>,
+    ) -> InstructionResult {
+        self.pending = Some((
+            interp.program_counter(),
+            interp.current_opcode(),
+            interp.gas.remaining(),
+        ));
+        InstructionResult::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, HarnessDB>,
+        eval: InstructionResult,
+    ) -> InstructionResult {
+        if let Some((pc, opcode, remaining)) = self.pending.take() {
+            let gas = remaining.saturating_sub(interp.gas.remaining());
+            let cost = &mut self.costs[opcode as usize];
+            cost.count += 1;
+            cost.gas += gas;
+            if pc >= self.pc_gas.len() {
+                self.pc_gas.resize(pc + 1, 0);
+            }
+            self.pc_gas[pc] += gas;
+        }
+        eval
+    }
+}
+
+/// Hashes `chunks` in a fresh EVM with the contract deployed as in `deployment`, under an
+/// [OpcodeGas] inspector, returning the inspector and the gas used by the calls.
+pub fn profile_opcodes(deployment: &Deployment, chunks: &[&[u8]]) -> Result<(OpcodeGas, CallGas)> {
+    let mut evm = deployment.init_evm()?;
+    let mut inspector = OpcodeGas::default();
+    let (_, gas) = hash_chunks_evm_inspected_metered(&mut evm, chunks, &mut inspector)?;
+    Ok((inspector, gas))
+}
+
+/// Renders the breakdown of `inspector` as a table sorted by gas, with the share of the gas used by
+/// the calls (`gas`) that every opcode accounts for, followed by the intrinsic gas.
+pub fn opcode_table(inspector: &OpcodeGas, gas: CallGas) -> String {
+    let share = |part: u64| part as f64 * 100.0 / gas.total().max(1) as f64;
+
+    let mut table = String::new();
+    writeln!(
+        table,
+        "{:<16} {:>10} {:>12} {:>7}",
+        "opcode", "count", "gas", "share"
+    )
+    .unwrap();
+    for cost in inspector.breakdown() {
+        writeln!(
+            table,
+            "{:<16} {:>10} {:>12} {:>6.2}%",
+            cost.name(),
+            cost.count,
+            cost.gas,
+            share(cost.gas)
+        )
+        .unwrap();
+    }
+    let intrinsic = gas.total().saturating_sub(inspector.total());
+    writeln!(
+        table,
+        "{:<16} {:>10} {:>12} {:>6.2}%",
+        "(intrinsic)",
+        "-",
+        intrinsic,
+        share(intrinsic)
+    )
+    .unwrap();
+    writeln!(
+        table,
+        "{:<16} {:>10} {:>12} {:>6.2}%",
+        "total",
+        "-",
+        gas.total(),
+        100.0
+    )
+    .unwrap();
+    table
+}
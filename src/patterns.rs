@@ -0,0 +1,68 @@
+//! Deterministic byte patterns that historically expose lane and endianness bugs in Keccak
+//! implementations, mixed into campaigns alongside uniformly random fills.
+
+use rand::{seq::SliceRandom, Rng};
+
+/// A structured byte pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// All `0x00` bytes.
+    Zero,
+    /// All `0xFF` bytes.
+    Ones,
+    /// A single random byte, repeated.
+    Repeating,
+    /// The byte index, truncated to a byte.
+    Counting,
+    /// A random 8-byte lane, repeated, so that every lane of the state receives the same word.
+    RepeatingLane,
+    /// All zero bytes except for a single set bit per lane, at a random position.
+    LaneBit,
+}
+
+impl Pattern {
+    /// Every pattern.
+    pub const ALL: [Self; 6] = [
+        Self::Zero,
+        Self::Ones,
+        Self::Repeating,
+        Self::Counting,
+        Self::RepeatingLane,
+        Self::LaneBit,
+    ];
+
+    /// Fills `buf` with the pattern, drawing any random parameters from `rng`.
+    pub fn fill<R: Rng>(self, rng: &mut R, buf: &mut [u8]) {
+        match self {
+            Self::Zero => buf.fill(0x00),
+            Self::Ones => buf.fill(0xFF),
+            Self::Repeating => buf.fill(rng.gen()),
+            Self::Counting => buf
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, byte)| *byte = i as u8),
+            Self::RepeatingLane => {
+                let lane = rng.gen::<[u8; 8]>();
+                buf.iter_mut()
+                    .zip(lane.iter().cycle())
+                    .for_each(|(byte, lane_byte)| *byte = *lane_byte);
+            }
+            Self::LaneBit => {
+                let bit = rng.gen_range(0..64);
+                buf.fill(0x00);
+                buf.iter_mut()
+                    .skip(bit / 8)
+                    .step_by(8)
+                    .for_each(|byte| *byte = 1 << (bit % 8));
+            }
+        }
+    }
+}
+
+/// Fills `buf` with a random [Pattern].
+pub fn fill_pattern<R: Rng>(rng: &mut R, buf: &mut [u8]) {
+    Pattern::ALL
+        .choose(rng)
+        .expect("patterns are not empty")
+        .fill(rng, buf);
+}
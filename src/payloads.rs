@@ -0,0 +1,406 @@
+//! Generators for Ethereum-shaped payloads: RLP-encoded transactions, ABI-encoded calls, Merkle
+//! Patricia Trie nodes, and the OP Stack structures that the dispute game hashes.
+//!
+//! These are the preimages that `LibKeccak` hashes in production, and their lengths cluster very
+//! differently modulo the rate than uniformly random buffers do.
+
+use rand::{seq::SliceRandom, Rng};
+
+/// The kinds of payload that can be generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Payload {
+    /// A signed legacy or EIP-1559 transaction.
+    Transaction,
+    /// ABI-encoded calldata, with static and dynamic arguments.
+    AbiCall,
+    /// A Merkle Patricia Trie branch, extension or leaf node, laid out exactly as `MerkleTrie.sol`
+    /// decodes it.
+    TrieNode,
+    /// An OP Stack deposit transaction (type `0x7E`).
+    DepositTransaction,
+    /// An OP Stack output root preimage: the version, state root, message passer storage root and
+    /// latest block hash.
+    OutputRoot,
+    /// An OP Stack withdrawal hash preimage, as hashed by `Hashing.hashWithdrawal`.
+    Withdrawal,
+}
+
+impl Payload {
+    /// Every kind of payload.
+    pub const ALL: [Self; 6] = [
+        Self::Transaction,
+        Self::AbiCall,
+        Self::TrieNode,
+        Self::DepositTransaction,
+        Self::OutputRoot,
+        Self::Withdrawal,
+    ];
+
+    /// Appends a random payload of this kind to `out`.
+    pub fn generate<R: Rng>(self, rng: &mut R, out: &mut Vec<u8>) {
+        match self {
+            Self::Transaction => transaction(rng, out),
+            Self::AbiCall => abi_call(rng, out),
+            Self::TrieNode => trie_node(rng, out),
+            Self::DepositTransaction => deposit_transaction(rng, out),
+            Self::OutputRoot => output_root(rng, out),
+            Self::Withdrawal => withdrawal(rng, out),
+        }
+    }
+}
+
+/// Replaces `input` with a payload of a random kind out of `kinds`, or of any kind if `kinds` is
+/// empty, truncated to stay strictly below `max_len`.
+pub fn generate_payload<R: Rng>(
+    rng: &mut R,
+    kinds: &[Payload],
+    input: &mut Vec<u8>,
+    max_len: usize,
+) {
+    let kinds = if kinds.is_empty() {
+        &Payload::ALL
+    } else {
+        kinds
+    };
+    input.clear();
+    kinds
+        .choose(rng)
+        .expect("payload kinds are not empty")
+        .generate(rng, input);
+    input.truncate(max_len.saturating_sub(1));
+}
+
+/// Appends a random signed transaction to `out`, either legacy or typed EIP-1559.
+fn transaction<R: Rng>(rng: &mut R, out: &mut Vec<u8>) {
+    let mut fields = Vec::new();
+    let eip1559 = rng.gen_bool(0.5);
+
+    if eip1559 {
+        rlp_uint(&mut fields, rng.gen_range(1..=100_000));
+    }
+    // Nonce, then either the gas price or the priority fee and max fee.
+    rlp_uint(&mut fields, scalar(rng));
+    for _ in 0..(1 + eip1559 as usize) {
+        rlp_uint(&mut fields, scalar(rng));
+    }
+    // Gas limit.
+    rlp_uint(&mut fields, rng.gen_range(21_000..30_000_000));
+    // Recipient, which is empty for contract creations.
+    if rng.gen_bool(0.9) {
+        rlp_bytes(&mut fields, &random_bytes(rng, 20));
+    } else {
+        rlp_bytes(&mut fields, &[]);
+    }
+    // Value.
+    rlp_uint(&mut fields, scalar(rng));
+    // Calldata.
+    let mut data = Vec::new();
+    match rng.gen_range(0..3) {
+        0 => {}
+        1 => abi_call(rng, &mut data),
+        _ => {
+            let len = rng.gen_range(1..512);
+            data = random_bytes(rng, len);
+        }
+    }
+    rlp_bytes(&mut fields, &data);
+
+    if eip1559 {
+        // Access list.
+        let mut access_list = Vec::new();
+        for _ in 0..rng.gen_range(0..3) {
+            let mut entry = Vec::new();
+            rlp_bytes(&mut entry, &random_bytes(rng, 20));
+            let mut slots = Vec::new();
+            for _ in 0..rng.gen_range(0..4) {
+                rlp_bytes(&mut slots, &random_bytes(rng, 32));
+            }
+            rlp_list(&mut entry, &slots);
+            rlp_list(&mut access_list, &entry);
+        }
+        rlp_list(&mut fields, &access_list);
+        // y parity.
+        rlp_uint(&mut fields, rng.gen_range(0..=1));
+    } else {
+        // EIP-155 `v`.
+        rlp_uint(&mut fields, rng.gen_range(1..=100_000) * 2 + 35);
+    }
+    // Signature `r` and `s`.
+    rlp_bytes(&mut fields, &random_bytes(rng, 32));
+    rlp_bytes(&mut fields, &random_bytes(rng, 32));
+
+    if eip1559 {
+        out.push(0x02);
+    }
+    rlp_list(out, &fields);
+}
+
+/// Appends random ABI-encoded calldata to `out`: a selector followed by a mix of static words and
+/// dynamic `bytes` arguments.
+fn abi_call<R: Rng>(rng: &mut R, out: &mut Vec<u8>) {
+    let args = rng.gen_range(0..8);
+    let mut heads = Vec::with_capacity(args * 32);
+    let mut tails = Vec::new();
+
+    for _ in 0..args {
+        let mut word = [0u8; 32];
+        match rng.gen_range(0..5) {
+            // address
+            0 => rng.fill(&mut word[12..]),
+            // uint256
+            1 => word = uint_word(scalar(rng)),
+            // bool
+            2 => word[31] = rng.gen_range(0..=1),
+            // bytes32
+            3 => rng.fill(&mut word[..]),
+            // bytes, encoded in the tail.
+            _ => {
+                word = uint_word((args * 32 + tails.len()) as u64);
+
+                let len = rng.gen_range(0..256);
+                tails.extend_from_slice(&uint_word(len as u64));
+                tails.extend(random_bytes(rng, len));
+                tails.resize(tails.len().next_multiple_of(32), 0);
+            }
+        }
+        heads.extend_from_slice(&word);
+    }
+
+    out.extend(random_bytes(rng, 4));
+    out.extend(heads);
+    out.extend(tails);
+}
+
+/// Appends a random OP Stack deposit transaction to `out`: the `0x7E` type byte followed by the RLP
+/// list of the source hash, sender, recipient, mint, value, gas limit, system flag and calldata.
+fn deposit_transaction<R: Rng>(rng: &mut R, out: &mut Vec<u8>) {
+    let mut fields = Vec::new();
+    rlp_bytes(&mut fields, &random_bytes(rng, 32));
+    rlp_bytes(&mut fields, &random_bytes(rng, 20));
+    // Recipient, which is empty for contract creations.
+    if rng.gen_bool(0.9) {
+        rlp_bytes(&mut fields, &random_bytes(rng, 20));
+    } else {
+        rlp_bytes(&mut fields, &[]);
+    }
+    // Mint and value.
+    rlp_uint(&mut fields, scalar(rng));
+    rlp_uint(&mut fields, scalar(rng));
+    // Gas limit.
+    rlp_uint(&mut fields, rng.gen_range(21_000..30_000_000));
+    // The system transaction flag.
+    rlp_uint(&mut fields, rng.gen_bool(0.1) as u64);
+    // Calldata.
+    let mut data = Vec::new();
+    if rng.gen_bool(0.5) {
+        abi_call(rng, &mut data);
+    }
+    rlp_bytes(&mut fields, &data);
+
+    out.push(0x7E);
+    rlp_list(out, &fields);
+}
+
+/// Appends a random version 0 output root preimage to `out`.
+fn output_root<R: Rng>(rng: &mut R, out: &mut Vec<u8>) {
+    out.extend_from_slice(&[0u8; 32]);
+    out.extend(random_bytes(rng, 3 * 32));
+}
+
+/// Appends a random withdrawal hash preimage to `out`: the ABI encoding of the versioned nonce,
+/// sender, target, value, gas limit and calldata of the withdrawal.
+fn withdrawal<R: Rng>(rng: &mut R, out: &mut Vec<u8>) {
+    // The message version is encoded in the upper two bytes of the nonce.
+    let mut nonce = uint_word(scalar(rng));
+    nonce[1] = rng.gen_range(0..=1);
+    out.extend_from_slice(&nonce);
+
+    for _ in 0..2 {
+        let mut address = [0u8; 32];
+        rng.fill(&mut address[12..]);
+        out.extend_from_slice(&address);
+    }
+    out.extend_from_slice(&uint_word(scalar(rng)));
+    out.extend_from_slice(&uint_word(rng.gen_range(21_000..30_000_000)));
+
+    // The calldata, in the tail after the six head words.
+    let mut data = Vec::new();
+    if rng.gen_bool(0.5) {
+        abi_call(rng, &mut data);
+    }
+    out.extend_from_slice(&uint_word(6 * 32));
+    out.extend_from_slice(&uint_word(data.len() as u64));
+    out.extend(data);
+    out.resize(out.len().next_multiple_of(32), 0);
+}
+
+/// The size of a trie node reference by hash, in bytes. Nodes whose encoding is shorter than this
+/// are embedded in their parent instead.
+const TRIE_HASH_BYTES: usize = 32;
+
+/// Appends a random Merkle Patricia Trie node to `out`.
+fn trie_node<R: Rng>(rng: &mut R, out: &mut Vec<u8>) {
+    match rng.gen_range(0..3) {
+        0 => branch_node(rng, out),
+        1 => extension_node(rng, out),
+        _ => leaf_node(rng, out),
+    }
+}
+
+/// Appends a branch node to `out`: 16 child references, some of them empty, followed by a value
+/// that is usually empty.
+fn branch_node<R: Rng>(rng: &mut R, out: &mut Vec<u8>) {
+    let mut items = Vec::new();
+    for _ in 0..16 {
+        if rng.gen_bool(0.5) {
+            child_reference(rng, &mut items);
+        } else {
+            rlp_bytes(&mut items, &[]);
+        }
+    }
+
+    if rng.gen_bool(0.9) {
+        rlp_bytes(&mut items, &[]);
+    } else {
+        let len = rng.gen_range(1..=32);
+        rlp_bytes(&mut items, &random_bytes(rng, len));
+    }
+    rlp_list(out, &items);
+}
+
+/// Appends an extension node to `out`: a hex-prefix encoded shared path and a child reference.
+fn extension_node<R: Rng>(rng: &mut R, out: &mut Vec<u8>) {
+    let mut items = Vec::new();
+    let nibbles = rng.gen_range(1..64);
+    rlp_bytes(
+        &mut items,
+        &hex_prefix(&random_nibbles(rng, nibbles), false),
+    );
+    child_reference(rng, &mut items);
+    rlp_list(out, &items);
+}
+
+/// Appends a leaf node to `out`: a hex-prefix encoded key remainder and an RLP-encoded account or
+/// storage slot value.
+fn leaf_node<R: Rng>(rng: &mut R, out: &mut Vec<u8>) {
+    let mut items = Vec::new();
+    let nibbles = rng.gen_range(1..=64);
+    rlp_bytes(&mut items, &hex_prefix(&random_nibbles(rng, nibbles), true));
+
+    let mut value = Vec::new();
+    if rng.gen_bool(0.5) {
+        let mut account = Vec::new();
+        rlp_uint(&mut account, scalar(rng));
+        rlp_uint(&mut account, scalar(rng));
+        rlp_bytes(&mut account, &random_bytes(rng, TRIE_HASH_BYTES));
+        rlp_bytes(&mut account, &random_bytes(rng, TRIE_HASH_BYTES));
+        rlp_list(&mut value, &account);
+    } else {
+        // Storage slot values are RLP-encoded with their leading zero bytes stripped.
+        let slot = random_bytes(rng, TRIE_HASH_BYTES);
+        let leading = rng.gen_range(0..TRIE_HASH_BYTES);
+        rlp_bytes(&mut value, &slot[leading..]);
+    }
+    rlp_bytes(&mut items, &value);
+
+    rlp_list(out, &items);
+}
+
+/// Appends a reference to a child node to `items`: usually its hash, but occasionally a short leaf
+/// node that is embedded directly because its encoding is shorter than a hash.
+fn child_reference<R: Rng>(rng: &mut R, items: &mut Vec<u8>) {
+    if rng.gen_bool(0.2) {
+        let mut leaf = Vec::new();
+        let nibbles = rng.gen_range(1..=8);
+        rlp_bytes(&mut leaf, &hex_prefix(&random_nibbles(rng, nibbles), true));
+        let len = rng.gen_range(1..=8);
+        rlp_bytes(&mut leaf, &random_bytes(rng, len));
+
+        let mut node = Vec::new();
+        rlp_list(&mut node, &leaf);
+        if node.len() < TRIE_HASH_BYTES {
+            items.extend(node);
+            return;
+        }
+    }
+    rlp_bytes(items, &random_bytes(rng, TRIE_HASH_BYTES));
+}
+
+/// Hex-prefix encodes a path of nibbles, flagging whether it terminates in a leaf and whether it has
+/// an odd number of nibbles.
+fn hex_prefix(nibbles: &[u8], leaf: bool) -> Vec<u8> {
+    let odd = nibbles.len() % 2 == 1;
+    let flag = (leaf as u8) << 1 | odd as u8;
+
+    let mut encoded = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = match nibbles.split_first() {
+        Some((first, rest)) if odd => {
+            encoded.push(flag << 4 | first);
+            rest
+        }
+        _ => {
+            encoded.push(flag << 4);
+            nibbles
+        }
+    };
+    encoded.extend(rest.chunks_exact(2).map(|pair| pair[0] << 4 | pair[1]));
+    encoded
+}
+
+/// Returns `len` random nibbles.
+fn random_nibbles<R: Rng>(rng: &mut R, len: usize) -> Vec<u8> {
+    (0..len).map(|_| rng.gen_range(0..16)).collect()
+}
+
+/// Returns a random scalar with a random number of significant bytes.
+fn scalar<R: Rng>(rng: &mut R) -> u64 {
+    rng.gen::<u64>() >> rng.gen_range(0..64)
+}
+
+/// Returns the ABI encoding of `value` as a `uint256` word.
+fn uint_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Returns `len` random bytes.
+fn random_bytes<R: Rng>(rng: &mut R, len: usize) -> Vec<u8> {
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+/// Appends the RLP encoding of a byte string to `out`.
+fn rlp_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    if let [byte @ 0x00..=0x7F] = bytes {
+        out.push(*byte);
+    } else {
+        rlp_header(out, 0x80, bytes.len());
+        out.extend_from_slice(bytes);
+    }
+}
+
+/// Appends the RLP encoding of an unsigned integer to `out`.
+fn rlp_uint(out: &mut Vec<u8>, value: u64) {
+    let bytes = value.to_be_bytes();
+    let leading = value.leading_zeros() as usize / 8;
+    rlp_bytes(out, &bytes[leading..]);
+}
+
+/// Appends the RLP encoding of a list to `out`, given the concatenated encodings of its items.
+fn rlp_list(out: &mut Vec<u8>, payload: &[u8]) {
+    rlp_header(out, 0xC0, payload.len());
+    out.extend_from_slice(payload);
+}
+
+/// Appends an RLP string or list header to `out`.
+fn rlp_header(out: &mut Vec<u8>, offset: u8, len: usize) {
+    if len < 56 {
+        out.push(offset + len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let leading = len.leading_zeros() as usize / 8;
+        out.push(offset + 55 + (bytes.len() - leading) as u8);
+        out.extend_from_slice(&bytes[leading..]);
+    }
+}
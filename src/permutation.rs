@@ -0,0 +1,311 @@
+//! A from-scratch implementation of the Keccak-f[1600] permutation and the Keccak256 sponge.
+//!
+//! This module intentionally has no external dependencies, so that the differential does not only
+//! compare crates that share an implementation transitively. The state layout matches
+//! `LibKeccak.StateMatrix`: 25 little-endian lanes indexed by `x + 5 * y`. The [Sponge] can
+//! optionally record the state after every round of every permutation for deeper diffing.
+
+/// The number of rounds in Keccak-f[1600].
+pub const ROUNDS: usize = 24;
+
+/// The rate of the Keccak256 sponge, in bytes.
+pub const RATE_BYTES: usize = 136;
+
+/// The size of a Keccak256 digest, in bytes.
+pub const DIGEST_BYTES: usize = 32;
+
+/// The Keccak-f[1600] state matrix.
+pub type StateMatrix = [u64; 25];
+
+/// The state after each round of a single permutation.
+pub type RoundTrace = [StateMatrix; ROUNDS];
+
+/// The ι step round constants.
+pub const ROUND_CONSTANTS: [u64; ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// The ρ step rotation offsets, indexed by `x + 5 * y`.
+const RHO_OFFSETS: [u32; 25] = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+/// Applies round `round` of Keccak-f[1600] to the state matrix.
+#[allow(clippy::needless_range_loop)]
+pub fn keccak_round(state: &mut StateMatrix, round: usize) {
+    // θ
+    let mut c = [0u64; 5];
+    for x in 0..5 {
+        c[x] = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+    }
+    for x in 0..5 {
+        let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+        for y in 0..5 {
+            state[x + 5 * y] ^= d;
+        }
+    }
+
+    // ρ and π
+    let mut b = [0u64; 25];
+    for x in 0..5 {
+        for y in 0..5 {
+            b[y + 5 * ((2 * x + 3 * y) % 5)] = state[x + 5 * y].rotate_left(RHO_OFFSETS[x + 5 * y]);
+        }
+    }
+
+    // χ
+    for y in 0..5 {
+        for x in 0..5 {
+            state[x + 5 * y] = b[x + 5 * y] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+        }
+    }
+
+    // ι
+    state[0] ^= ROUND_CONSTANTS[round];
+}
+
+/// Applies the full Keccak-f[1600] permutation to the state matrix.
+pub fn keccak_f(state: &mut StateMatrix) {
+    (0..ROUNDS).for_each(|round| keccak_round(state, round));
+}
+
+/// Applies the full Keccak-f[1600] permutation to the state matrix, returning the state after each
+/// round.
+pub fn keccak_f_traced(state: &mut StateMatrix) -> RoundTrace {
+    let mut trace = [[0u64; 25]; ROUNDS];
+    for (round, snapshot) in trace.iter_mut().enumerate() {
+        keccak_round(state, round);
+        *snapshot = *state;
+    }
+    trace
+}
+
+/// A Keccak256 sponge over [keccak_f].
+#[derive(Debug, Clone)]
+pub struct Sponge {
+    /// The state matrix.
+    state: StateMatrix,
+    /// The partially filled block that has not yet been absorbed.
+    buffer: [u8; RATE_BYTES],
+    /// The number of bytes in `buffer`.
+    buffered: usize,
+    /// The round-by-round trace of every permutation, if tracing is enabled.
+    trace: Option<Vec<RoundTrace>>,
+}
+
+impl Default for Sponge {
+    fn default() -> Self {
+        Self {
+            state: [0u64; 25],
+            buffer: [0u8; RATE_BYTES],
+            buffered: 0,
+            trace: None,
+        }
+    }
+}
+
+impl Sponge {
+    /// Creates a new, empty [Sponge].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new, empty [Sponge] that records the state after every round of every permutation.
+    pub fn traced() -> Self {
+        Self {
+            trace: Some(Vec::new()),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the current state matrix.
+    pub fn state(&self) -> &StateMatrix {
+        &self.state
+    }
+
+    /// Returns the round-by-round trace of every permutation so far, if tracing is enabled.
+    pub fn trace(&self) -> Option<&[RoundTrace]> {
+        self.trace.as_deref()
+    }
+
+    /// Absorbs bytes into the sponge, permuting the state for every full block.
+    pub fn absorb(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            let take = input.len().min(RATE_BYTES - self.buffered);
+            self.buffer[self.buffered..self.buffered + take].copy_from_slice(&input[..take]);
+            self.buffered += take;
+            input = &input[take..];
+
+            if self.buffered == RATE_BYTES {
+                self.absorb_block();
+            }
+        }
+    }
+
+    /// Pads the buffered bytes with the `10*1` Keccak padding, absorbs the final block, and returns
+    /// the digest.
+    pub fn squeeze(mut self) -> [u8; DIGEST_BYTES] {
+        self.finalize()
+    }
+
+    /// Like [Sponge::squeeze], but leaves the finalized sponge in place so that its state and trace
+    /// can be inspected afterwards.
+    pub fn finalize(&mut self) -> [u8; DIGEST_BYTES] {
+        self.buffer[self.buffered..].fill(0);
+        self.buffer[self.buffered] ^= 0x01;
+        self.buffer[RATE_BYTES - 1] ^= 0x80;
+        self.absorb_block();
+
+        let mut digest = [0u8; DIGEST_BYTES];
+        for (chunk, lane) in digest.chunks_exact_mut(8).zip(self.state.iter()) {
+            chunk.copy_from_slice(&lane.to_le_bytes());
+        }
+        digest
+    }
+
+    /// XORs the full buffer into the rate portion of the state and permutes it.
+    fn absorb_block(&mut self) {
+        for (lane, chunk) in self.state.iter_mut().zip(self.buffer.chunks_exact(8)) {
+            *lane ^= u64::from_le_bytes(chunk.try_into().expect("chunk is 8 bytes"));
+        }
+        self.buffered = 0;
+
+        match self.trace.as_mut() {
+            Some(trace) => trace.push(keccak_f_traced(&mut self.state)),
+            None => keccak_f(&mut self.state),
+        }
+    }
+}
+
+/// The domain separation bits of Keccak256, which precede the `10*1` padding: none, so the first
+/// padding byte is `0x01`.
+pub const KECCAK_SUFFIX: u8 = 0x01;
+
+/// The domain separation bits of SHAKE (`1111`), merged with the first padding bit into `0x1f`.
+/// SHAKE256 shares the rate of Keccak256.
+pub const SHAKE_SUFFIX: u8 = 0x1f;
+
+/// Applies the `10*1` Keccak padding to `input`, like `LibKeccak.pad`, returning a buffer whose
+/// length is the next multiple of [RATE_BYTES] strictly greater than the input's.
+pub fn pad(input: &[u8]) -> Vec<u8> {
+    pad_with(input, KECCAK_SUFFIX)
+}
+
+/// Like [pad], but with `suffix` as the first padding byte, which carries the domain separation
+/// bits (see [KECCAK_SUFFIX] and [SHAKE_SUFFIX]).
+pub fn pad_with(input: &[u8], suffix: u8) -> Vec<u8> {
+    let padded_len = (input.len() / RATE_BYTES + 1) * RATE_BYTES;
+    let mut padded = input.to_vec();
+    padded.resize(padded_len, 0);
+    padded[input.len()] ^= suffix;
+    padded[padded_len - 1] ^= 0x80;
+    padded
+}
+
+/// Hashes the input bytes with the in-crate Keccak256 sponge.
+pub fn keccak256(input: &[u8]) -> [u8; DIGEST_BYTES] {
+    let mut sponge = Sponge::new();
+    sponge.absorb(input);
+    sponge.squeeze()
+}
+
+/// Absorbs the input bytes, padded with `suffix` (see [pad_with]), into a fresh [Sponge] and
+/// squeezes `len` bytes of output, permuting the state again after every [RATE_BYTES] of it. The
+/// first [DIGEST_BYTES] of the output with [KECCAK_SUFFIX] are the Keccak256 digest.
+pub fn sponge_output(input: &[u8], suffix: u8, len: usize) -> Vec<u8> {
+    let mut sponge = Sponge::new();
+    sponge.absorb(&pad_with(input, suffix));
+    let mut state = *sponge.state();
+
+    let mut output = Vec::with_capacity(len + RATE_BYTES);
+    loop {
+        for lane in &state[..RATE_BYTES / 8] {
+            output.extend_from_slice(&lane.to_le_bytes());
+        }
+        if output.len() >= len {
+            output.truncate(len);
+            return output;
+        }
+        keccak_f(&mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+
+    #[test]
+    fn keccak256_known_answers() {
+        let vectors: [(&[u8], [u8; DIGEST_BYTES]); 5] = [
+            (
+                b"",
+                hex!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+            ),
+            (
+                b"abc",
+                hex!("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"),
+            ),
+            (
+                &[b'a'; RATE_BYTES - 1],
+                hex!("34367dc248bbd832f4e3e69dfaac2f92638bd0bbd18f2912ba4ef454919cf446"),
+            ),
+            (
+                &[b'a'; RATE_BYTES],
+                hex!("a6c4d403279fe3e0af03729caada8374b5ca54d8065329a3ebcaeb4b60aa386e"),
+            ),
+            (
+                &[b'a'; RATE_BYTES + 1],
+                hex!("d869f639c7046b4929fc92a4d988a8b22c55fbadb802c0c66ebcd484f1915f39"),
+            ),
+        ];
+        for (input, digest) in vectors {
+            assert_eq!(keccak256(input), digest, "{} byte input", input.len());
+            assert_eq!(
+                sponge_output(input, KECCAK_SUFFIX, DIGEST_BYTES),
+                digest,
+                "{} byte input",
+                input.len()
+            );
+        }
+    }
+
+    #[test]
+    fn shake256_known_answer() {
+        // Spans two blocks of output, so the permutation of the output phase is covered too.
+        let expected = hex!(
+            "483366601360a8771c6863080cc4114d8db44530f8f1e1ee4f94ea37e78b5739d5a15bef186a5386c75744c0527e1faa"
+            "9f8726e462a12a4feb06bd8801e751e41385141204f329979fd3047a13c5657724ada64d2470157b3cdc288620944d78"
+            "dbcddbd912993f0913f164fb2ce95131a2d09a3e6d51cbfc622720d7a75c6334e8a2d7ec71a7cc29cf0ea610eeff1a58"
+            "8290a53000faa79932becec0bd3cd0b33a7e5d397fed1ada9442b99903f4dcfd8559ed3950faf40fe6f3b5d710ed3b67"
+            "7513771af6bfe119"
+        );
+        assert_eq!(
+            sponge_output(b"abc", SHAKE_SUFFIX, expected.len()),
+            expected
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! [proptest] adapters for the differential property, so downstream crates can embed "`LibKeccak`
+//! agrees with the reference Keccak256 implementations" in their own test suites, with shrinking
+//! handled by proptest.
+//!
+//! ```ignore
+//! use lib_keccak::property::{any_input, prop_keccak_equivalence};
+//! use proptest::proptest;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn libkeccak_matches_reference(input in any_input(1024)) {
+//!         prop_keccak_equivalence(&input)?;
+//!     }
+//! }
+//! ```
+
+use crate::{engine::DiffEngine, hashing::available_backends, permutation::RATE_BYTES};
+use proptest::{
+    collection::vec,
+    prelude::*,
+    test_runner::{Config, TestCaseResult, TestError, TestRunner},
+};
+use std::cell::RefCell;
+
+thread_local! {
+    /// The diff engine backing [prop_keccak_equivalence], reused across cases on each thread.
+    static ENGINE: RefCell<Option<DiffEngine>> = const { RefCell::new(None) };
+}
+
+/// A strategy for uniformly random inputs of fewer than `max_len` bytes.
+pub fn any_input(max_len: usize) -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 0..max_len.max(1))
+}
+
+/// A strategy for random inputs whose lengths sit on or directly next to a multiple of the rate,
+/// up to `max_blocks` blocks, where the `10*1` padding logic is most fragile.
+pub fn boundary_input(max_blocks: usize) -> impl Strategy<Value = Vec<u8>> {
+    (0..=max_blocks, -1isize..=1)
+        .prop_map(|(blocks, offset)| (blocks * RATE_BYTES).saturating_add_signed(offset))
+        .prop_flat_map(|len| vec(any::<u8>(), len))
+}
+
+/// The differential property: hashes `input` with the `StatefulSponge` contract (with the `evm`
+/// feature) and every available reference backend, failing the case if any digests disagree.
+pub fn prop_keccak_equivalence(input: &[u8]) -> TestCaseResult {
+    ENGINE.with(|engine| {
+        let mut engine = engine.borrow_mut();
+        let engine = match engine.as_mut() {
+            Some(engine) => engine,
+            None => engine.insert(
+                DiffEngine::new(&available_backends(), false)
+                    .map_err(|e| TestCaseError::fail(e.to_string()))?,
+            ),
+        };
+
+        let verdict = engine
+            .diff(input)
+            .map_err(|e| TestCaseError::fail(e.to_string()))?;
+        prop_assert!(
+            verdict.is_unanimous(),
+            "Hash mismatch ({})",
+            engine.describe(&verdict)
+        );
+        Ok(())
+    })
+}
+
+/// Runs [prop_keccak_equivalence] over `strategy` outside of the [proptest!] macro, returning the
+/// shrunk failing input on a mismatch.
+pub fn check_keccak_equivalence<S>(config: Config, strategy: S) -> Result<(), TestError<Vec<u8>>>
+where
+    S: Strategy<Value = Vec<u8>>,
+{
+    TestRunner::new(config).run(&strategy, |input| prop_keccak_equivalence(&input))
+}
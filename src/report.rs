@@ -0,0 +1,444 @@
+//! Campaign reports, with mismatches clustered into distinct bug signatures for triage.
+
+use crate::{
+    arbitration::{arbitrate_contracts, Verdict},
+    failure::Failure,
+    gas::GasAnomaly,
+    permutation::RATE_BYTES,
+};
+use anyhow::{Context, Result};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs,
+    path::Path,
+    time::Duration,
+};
+
+/// The number of bytes in a lane of the Keccak state.
+const LANE_BYTES: usize = 8;
+
+/// The number of gas anomalies listed by [Report::gas_anomaly_summary].
+const MAX_LISTED_ANOMALIES: usize = 5;
+
+/// The outcome of a campaign.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// The number of inputs that were diffed.
+    pub diffs: u64,
+    /// The number of regression inputs that were replayed before the campaign (see
+    /// [crate::DiffFuzzer::regressions_dir]), which don't count towards `diffs`.
+    pub regressions: u64,
+    /// The gas used by the contract calls of the diffed inputs.
+    pub gas: GasStats,
+    /// The gas used by the `absorb` calls of the diffed inputs, per input.
+    pub absorb_gas: GasStats,
+    /// The gas used by the `squeeze` call of the diffed inputs.
+    pub squeeze_gas: GasStats,
+    /// The gas used by the contract calls of the variant build, if the campaign diffed one (see
+    /// [crate::DiffFuzzer::variant_code]).
+    pub variant_gas: GasStats,
+    /// The gas used by the contract calls of every build of the build matrix, if the campaign
+    /// diffed one, in the order of [crate::DiffFuzzer::build_matrix].
+    pub matrix_gas: Vec<GasStats>,
+    /// The gas used by the calls of every target, if the campaign diffed any, in the order of
+    /// [crate::DiffFuzzer::targets].
+    pub target_gas: Vec<GasStats>,
+    /// Every mismatch that was found. Outside of keep-going mode, a campaign stops with an error on
+    /// the first mismatch instead.
+    pub failures: Vec<Failure>,
+    /// Every input whose gas was a statistical outlier of its length bucket, if the campaign
+    /// detected gas anomalies (see [crate::DiffFuzzer::gas_anomaly_threshold]).
+    pub gas_anomalies: Vec<GasAnomaly>,
+    /// The wall time that the campaign took.
+    pub elapsed: Duration,
+    /// Whether the campaign was stopped early, e.g. by Ctrl-C.
+    pub interrupted: bool,
+    /// The statistics of every task, ordered by thread.
+    pub threads: Vec<ThreadStats>,
+    /// The number of diffs and the gas of every distinct input length, if the campaign was
+    /// profiled.
+    pub lengths: BTreeMap<usize, LengthProfile>,
+    /// The cumulative number of diffs, sampled over the campaign's wall time, if the campaign was
+    /// profiled.
+    pub timeline: Vec<(Duration, u64)>,
+}
+
+/// The profile of the inputs of a single length.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LengthProfile {
+    /// The number of inputs of the length that were diffed.
+    pub diffs: u64,
+    /// The gas used by the contract calls of the inputs.
+    pub gas: GasStats,
+}
+
+/// Aggregates of the gas used by the contract calls of every diffed input. Empty without the `evm`
+/// feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GasStats {
+    /// The number of inputs whose gas was recorded.
+    pub samples: u64,
+    /// The total gas used.
+    pub total: u64,
+    /// The least gas used by a single input, if any were recorded.
+    pub min: u64,
+    /// The most gas used by a single input.
+    pub max: u64,
+}
+
+impl GasStats {
+    /// Records an input whose contract calls used `gas_used` gas.
+    pub fn record(&mut self, gas_used: u64) {
+        self.min = if self.samples == 0 {
+            gas_used
+        } else {
+            self.min.min(gas_used)
+        };
+        self.max = self.max.max(gas_used);
+        self.total += gas_used;
+        self.samples += 1;
+    }
+
+    /// Merges the gas statistics of another task into these ones.
+    pub fn merge(&mut self, other: GasStats) {
+        if other.samples == 0 {
+            return;
+        }
+        self.min = if self.samples == 0 {
+            other.min
+        } else {
+            self.min.min(other.min)
+        };
+        self.max = self.max.max(other.max);
+        self.total += other.total;
+        self.samples += other.samples;
+    }
+
+    /// Returns the mean gas used per input, if any were recorded.
+    pub fn mean(&self) -> Option<f64> {
+        (self.samples > 0).then(|| self.total as f64 / self.samples as f64)
+    }
+}
+
+/// The statistics of a single fuzzing task.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadStats {
+    /// The task's thread, counted from 1.
+    pub thread: u64,
+    /// The number of inputs that the task diffed.
+    pub diffs: u64,
+    /// The number of mismatches that the task found.
+    pub mismatches: usize,
+    /// The gas used by the contract calls of the task's inputs.
+    pub gas: GasStats,
+    /// The wall time that the task ran for.
+    pub elapsed: Duration,
+}
+
+impl Report {
+    /// Returns `true` if no mismatches were found.
+    pub fn is_clean(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Summarizes the campaign in a single line: the number of diffs, the throughput and the number
+    /// of mismatches.
+    pub fn summary(&self) -> String {
+        let secs = self.elapsed.as_secs_f64();
+        let throughput = if secs > 0.0 {
+            self.diffs as f64 / secs
+        } else {
+            0.0
+        };
+        let regressions = match self.regressions {
+            0 => String::new(),
+            1 => " after replaying 1 regression input".to_string(),
+            regressions => format!(" after replaying {regressions} regression inputs"),
+        };
+        format!(
+            "{}{} diffs in {secs:.1}s ({throughput:.0}/s){regressions}, {} mismatches",
+            if self.interrupted {
+                "Interrupted after "
+            } else {
+                ""
+            },
+            self.diffs,
+            self.failures.len()
+        )
+    }
+
+    /// Characterizes the gas used by the contract calls in a single line, with the least, mean and
+    /// most gas used by the `absorb` and `squeeze` calls of an input, or `None` if no gas was
+    /// recorded.
+    pub fn gas_summary(&self) -> Option<String> {
+        let describe = |gas: &GasStats| {
+            Some(format!(
+                "{} min, {:.0} mean, {} max",
+                gas.min,
+                gas.mean()?,
+                gas.max
+            ))
+        };
+        Some(format!(
+            "Gas per input: absorb {}; squeeze {}; total {}",
+            describe(&self.absorb_gas)?,
+            describe(&self.squeeze_gas)?,
+            describe(&self.gas)?
+        ))
+    }
+
+    /// Returns a summary of the gas used by the variant build, compared to that of the contract, if
+    /// the campaign diffed one.
+    pub fn variant_gas_summary(&self) -> Option<String> {
+        let mean = self.variant_gas.mean()?;
+        Some(format!(
+            "Variant gas per input: {} min, {mean:.0} mean, {} max ({:+.2}% vs. the contract's mean)",
+            self.variant_gas.min,
+            self.variant_gas.max,
+            (mean / self.gas.mean()? - 1.0) * 100.0
+        ))
+    }
+
+    /// Returns a summary of the gas used by every build of the build matrix, labelled with `labels`,
+    /// compared to that of the contract, if the campaign diffed one.
+    pub fn matrix_gas_summary(&self, labels: &[&str]) -> Option<String> {
+        self.labelled_gas_summary("Build matrix gas per input:", &self.matrix_gas, labels)
+    }
+
+    /// Returns a summary of the gas used by every target, labelled with `labels`, compared to that
+    /// of the contract, if the campaign diffed any.
+    pub fn target_gas_summary(&self, labels: &[&str]) -> Option<String> {
+        self.labelled_gas_summary("Target gas per input:", &self.target_gas, labels)
+    }
+
+    /// Returns a summary of the gas of other implementations, titled `title` with every one of
+    /// `gas` labelled with its label in `labels`, compared to that of the contract.
+    fn labelled_gas_summary(
+        &self,
+        title: &str,
+        gas: &[GasStats],
+        labels: &[&str],
+    ) -> Option<String> {
+        let contract_mean = self.gas.mean()?;
+        let mut summary = String::from(title);
+        for (stats, label) in gas.iter().zip(labels) {
+            let Some(mean) = stats.mean() else {
+                continue;
+            };
+            summary.push_str(&format!(
+                "\n- {label}: {} min, {mean:.0} mean, {} max ({:+.2}% vs. the contract's mean)",
+                stats.min,
+                stats.max,
+                (mean / contract_mean - 1.0) * 100.0
+            ));
+        }
+        (!gas.is_empty()).then_some(summary)
+    }
+
+    /// Returns a summary of the gas anomalies of this report, listing the most extreme ones, if it
+    /// has any.
+    pub fn gas_anomaly_summary(&self) -> Option<String> {
+        if self.gas_anomalies.is_empty() {
+            return None;
+        }
+        let mut anomalies = self.gas_anomalies.iter().collect::<Vec<_>>();
+        anomalies.sort_by(|a, b| b.z_score().abs().total_cmp(&a.z_score().abs()));
+        let mut summary = format!("{} gas anomalies:", anomalies.len());
+        for anomaly in anomalies.iter().take(MAX_LISTED_ANOMALIES) {
+            summary.push_str(&format!("\n- {anomaly}"));
+        }
+        if anomalies.len() > MAX_LISTED_ANOMALIES {
+            summary.push_str(&format!(
+                "\n- and {} more",
+                anomalies.len() - MAX_LISTED_ANOMALIES
+            ));
+        }
+        Some(summary)
+    }
+
+    /// Returns the statistics of this report, as those of task `thread` (counted from 1).
+    pub fn thread_stats(&self, thread: u64, elapsed: Duration) -> ThreadStats {
+        ThreadStats {
+            thread,
+            diffs: self.diffs,
+            mismatches: self.failures.len(),
+            gas: self.gas,
+            elapsed,
+        }
+    }
+
+    /// Merges the report of another task into this one.
+    pub fn merge(&mut self, other: Report) {
+        self.diffs += other.diffs;
+        self.regressions += other.regressions;
+        self.gas.merge(other.gas);
+        self.absorb_gas.merge(other.absorb_gas);
+        self.squeeze_gas.merge(other.squeeze_gas);
+        self.variant_gas.merge(other.variant_gas);
+        if self.matrix_gas.len() < other.matrix_gas.len() {
+            self.matrix_gas
+                .resize(other.matrix_gas.len(), GasStats::default());
+        }
+        for (stats, other) in self.matrix_gas.iter_mut().zip(other.matrix_gas) {
+            stats.merge(other);
+        }
+        if self.target_gas.len() < other.target_gas.len() {
+            self.target_gas
+                .resize(other.target_gas.len(), GasStats::default());
+        }
+        for (stats, other) in self.target_gas.iter_mut().zip(other.target_gas) {
+            stats.merge(other);
+        }
+        self.failures.extend(other.failures);
+        self.gas_anomalies.extend(other.gas_anomalies);
+        self.threads.extend(other.threads);
+        for (len, profile) in other.lengths {
+            let entry = self.lengths.entry(len).or_default();
+            entry.diffs += profile.diffs;
+            entry.gas.merge(profile.gas);
+        }
+    }
+
+    /// Groups the failures by their [Signature], most frequent first. Failures keep their
+    /// campaign order within a cluster.
+    pub fn clusters(&self) -> Vec<Cluster<'_>> {
+        let mut clusters: Vec<Cluster<'_>> = Vec::new();
+        for failure in &self.failures {
+            let signature = Signature::of(failure);
+            match clusters
+                .iter_mut()
+                .find(|cluster| cluster.signature == signature)
+            {
+                Some(cluster) => cluster.failures.push(failure),
+                None => clusters.push(Cluster {
+                    signature,
+                    failures: vec![failure],
+                }),
+            }
+        }
+        clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.failures.len()));
+        clusters
+    }
+
+    /// Writes the rendered report to `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_string())
+            .with_context(|| format!("Failed to write report {}", path.display()))
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let clusters = self.clusters();
+        writeln!(
+            f,
+            "{} mismatches in {} diffs, with {} distinct signatures",
+            self.failures.len(),
+            self.diffs,
+            clusters.len()
+        )?;
+        for cluster in &clusters {
+            let example = cluster.failures[0];
+            writeln!(f, "- {}x {}", cluster.failures.len(), cluster.signature)?;
+            writeln!(
+                f,
+                "  first {}: {} byte input ({})",
+                example.context,
+                example.input.len(),
+                example.description
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A group of failures that share a [Signature].
+#[derive(Debug, Clone)]
+pub struct Cluster<'a> {
+    /// The shared signature.
+    pub signature: Signature,
+    /// The failures with the signature.
+    pub failures: Vec<&'a Failure>,
+}
+
+/// The triage signature of a failure. Failures with the same signature are most likely caused by
+/// the same bug.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// The implementations that disagreed with the majority, or empty if there was no majority.
+    pub dissenters: Vec<&'static str>,
+    /// The input length modulo the rate.
+    pub len_mod_rate: usize,
+    /// Which bytes of the first dissenting digest differ from the majority digest, with bit `i`
+    /// set if byte `i` differs.
+    pub diff_mask: u32,
+    /// The first lane of the squeezed state in which the first dissenting digest diverges from the
+    /// majority digest.
+    pub diverging_lane: Option<usize>,
+}
+
+impl Signature {
+    /// Computes the signature of `failure` over the full matrix of digests, including the
+    /// cross-checks.
+    pub fn of(failure: &Failure) -> Self {
+        let (names, digests): (Vec<_>, Vec<_>) = failure
+            .digests
+            .iter()
+            .chain(&failure.cross_checks)
+            .copied()
+            .unzip();
+        let len_mod_rate = failure.input.len() % RATE_BYTES;
+
+        let Verdict::Majority { digest, dissenters } =
+            arbitrate_contracts(&digests, failure.contract_slots)
+        else {
+            return Self {
+                dissenters: Vec::new(),
+                len_mod_rate,
+                diff_mask: 0,
+                diverging_lane: None,
+            };
+        };
+
+        let dissent = digests[dissenters[0]];
+        let diff_mask = (0..digest.len())
+            .filter(|&i| digest[i] != dissent[i])
+            .fold(0, |mask, i| mask | 1 << i);
+        Self {
+            dissenters: dissenters.iter().map(|&i| names[i]).collect(),
+            len_mod_rate,
+            diff_mask,
+            diverging_lane: (diff_mask != 0)
+                .then(|| diff_mask.trailing_zeros() as usize / LANE_BYTES),
+        }
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.dissenters.is_empty() {
+            return write!(f, "no majority, length mod rate {}", self.len_mod_rate);
+        }
+
+        let pattern = (0..32)
+            .map(|i| {
+                if self.diff_mask & 1 << i != 0 {
+                    'x'
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        write!(
+            f,
+            "dissenting: {}, length mod rate {}, digest diff {pattern}",
+            self.dissenters.join(", "),
+            self.len_mod_rate
+        )?;
+        if let Some(lane) = self.diverging_lane {
+            write!(f, ", diverging in lane {lane}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Pluggable campaign reporters, so that output formats compose and library users can inject their
+//! own sinks.
+//!
+//! Every [Reporter] registered in [DiffFuzzer::reporters] is notified when a campaign starts, after
+//! every diffed input, on every mismatch and when the campaign finishes, whether it succeeded or
+//! not. The tasks of a campaign run concurrently, so reporters are shared between them.
+
+use crate::{
+    failure::Failure, html::write_html, junit::write_junit, report::Report, DiffEngine, DiffFuzzer,
+};
+#[cfg(feature = "json")]
+use anyhow::Context;
+use anyhow::Result;
+use std::{fmt::Debug, path::PathBuf};
+#[cfg(feature = "json")]
+use std::{fs::File, io::Write, path::Path, sync::Mutex};
+
+/// A sink for the events of a campaign. Every method does nothing by default.
+pub trait Reporter: Debug + Send + Sync {
+    /// Called when a campaign run by `config` starts.
+    fn on_start(&self, config: &DiffFuzzer) -> Result<()> {
+        let _ = config;
+        Ok(())
+    }
+
+    /// Called after every diffed input, with the engine that diffed it. This is on the hot path of
+    /// the campaign, so it should be cheap.
+    fn on_iteration(&self, input: &[u8], engine: &DiffEngine) {
+        let _ = (input, engine);
+    }
+
+    /// Called on every mismatch, once its stability has been checked, whether or not the campaign
+    /// keeps going.
+    fn on_mismatch(&self, failure: &Failure) -> Result<()> {
+        let _ = failure;
+        Ok(())
+    }
+
+    /// Called when a campaign run by `config` finishes with `outcome`.
+    fn on_finish(&self, config: &DiffFuzzer, outcome: &Result<Report>) -> Result<()> {
+        let _ = (config, outcome);
+        Ok(())
+    }
+}
+
+/// Prints the summary of a campaign and, if it found any mismatches in keep-going mode, its full
+/// report to stderr.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleReporter;
+
+impl Reporter for ConsoleReporter {
+    fn on_finish(&self, config: &DiffFuzzer, outcome: &Result<Report>) -> Result<()> {
+        if let Ok(report) = outcome {
+            eprintln!("{}", report.summary());
+            if let Some(gas) = report.gas_summary() {
+                eprintln!("{gas}");
+            }
+            if let Some(variant) = report.variant_gas_summary() {
+                eprintln!("{variant}");
+            }
+            let labels = config
+                .build_matrix
+                .iter()
+                .map(|build| build.label.as_str())
+                .collect::<Vec<_>>();
+            if let Some(matrix) = report.matrix_gas_summary(&labels) {
+                eprintln!("{matrix}");
+            }
+            let labels = config
+                .targets
+                .iter()
+                .map(|target| target.label.as_str())
+                .collect::<Vec<_>>();
+            if let Some(targets) = report.target_gas_summary(&labels) {
+                eprintln!("{targets}");
+            }
+            if let Some(anomalies) = report.gas_anomaly_summary() {
+                eprintln!("{anomalies}");
+            }
+            if !report.is_clean() {
+                eprint!("{report}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the JSON run report of a campaign to a file when it finishes (see [crate::json]).
+#[cfg(feature = "json")]
+#[derive(Debug, Clone)]
+pub struct JsonReporter {
+    /// The file to write the report to.
+    pub path: PathBuf,
+}
+
+#[cfg(feature = "json")]
+impl Reporter for JsonReporter {
+    fn on_finish(&self, config: &DiffFuzzer, outcome: &Result<Report>) -> Result<()> {
+        use crate::json::{run_json, write_json};
+        write_json(&self.path, &run_json(config, outcome))
+    }
+}
+
+/// Streams every mismatch as a single line of JSON (see [crate::json::failure_json]) as soon as it
+/// is found, so that external watchers can react to failures while the campaign runs.
+#[cfg(feature = "json")]
+pub struct JsonlReporter {
+    /// The name of the stream, for error messages.
+    name: String,
+    /// The stream that the lines are written to.
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+#[cfg(feature = "json")]
+impl JsonlReporter {
+    /// Creates a reporter that streams the mismatches to the file at `path`, replacing it.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create mismatch stream {}", path.display()))?;
+        Ok(Self {
+            name: path.display().to_string(),
+            writer: Mutex::new(Box::new(file)),
+        })
+    }
+
+    /// Creates a reporter that streams the mismatches to stdout.
+    pub fn stdout() -> Self {
+        Self {
+            name: "stdout".to_string(),
+            writer: Mutex::new(Box::new(std::io::stdout())),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl Debug for JsonlReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonlReporter")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "json")]
+impl Reporter for JsonlReporter {
+    fn on_mismatch(&self, failure: &Failure) -> Result<()> {
+        let line = crate::json::failure_json(failure).to_string();
+        let mut writer = self.writer.lock().expect("mismatch stream lock poisoned");
+        writeln!(writer, "{line}")
+            .and_then(|_| writer.flush())
+            .with_context(|| format!("Failed to write mismatch stream {}", self.name))
+    }
+}
+
+/// Writes the JUnit XML report of a campaign to a file when it finishes (see [crate::junit]).
+#[derive(Debug, Clone)]
+pub struct JunitReporter {
+    /// The file to write the report to.
+    pub path: PathBuf,
+}
+
+impl Reporter for JunitReporter {
+    fn on_finish(&self, config: &DiffFuzzer, outcome: &Result<Report>) -> Result<()> {
+        write_junit(&self.path, config, outcome)
+    }
+}
+
+/// Writes the HTML report of a campaign to a file when it finishes (see [crate::html]). Its charts
+/// need the campaign to be profiled (see [DiffFuzzer::profile]).
+#[derive(Debug, Clone)]
+pub struct HtmlReporter {
+    /// The file to write the report to.
+    pub path: PathBuf,
+}
+
+impl Reporter for HtmlReporter {
+    fn on_finish(&self, config: &DiffFuzzer, outcome: &Result<Report>) -> Result<()> {
+        write_html(&self.path, config, outcome)
+    }
+}
@@ -0,0 +1,82 @@
+//! Real-world fuzz inputs sampled from an Ethereum JSON-RPC endpoint.
+
+use alloy_primitives::hex;
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+
+/// Fetches the calldata of every transaction and the data of every log emitted in the latest
+/// `blocks` blocks from the JSON-RPC endpoint at `url`. Each payload is labelled with the hash of
+/// the transaction that it came from. Empty payloads are skipped.
+pub async fn fetch_payloads(url: &str, blocks: u64) -> Result<Vec<(String, Vec<u8>)>> {
+    let client = reqwest::Client::new();
+    let latest = quantity(&call(&client, url, "eth_blockNumber", json!([])).await?)?;
+
+    let mut payloads = Vec::new();
+    for number in latest.saturating_sub(blocks.saturating_sub(1))..=latest {
+        let number = format!("{number:#x}");
+
+        let block = call(&client, url, "eth_getBlockByNumber", json!([number, true])).await?;
+        for tx in array(&block["transactions"], "transactions")? {
+            let hash = string(&tx["hash"], "transaction hash")?;
+            let input = hex::decode(string(&tx["input"], "transaction input")?)?;
+            if !input.is_empty() {
+                payloads.push((format!("calldata of tx {hash}"), input));
+            }
+        }
+
+        let receipts = call(&client, url, "eth_getBlockReceipts", json!([number])).await?;
+        for receipt in array(&receipts, "receipts")? {
+            let hash = string(&receipt["transactionHash"], "transaction hash")?;
+            for (i, log) in array(&receipt["logs"], "logs")?.iter().enumerate() {
+                let data = hex::decode(string(&log["data"], "log data")?)?;
+                if !data.is_empty() {
+                    payloads.push((format!("log {i} of tx {hash}"), data));
+                }
+            }
+        }
+    }
+
+    Ok(payloads)
+}
+
+/// Performs a JSON-RPC call, returning its result.
+async fn call(client: &reqwest::Client, url: &str, method: &str, params: Value) -> Result<Value> {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let mut response = client
+        .post(url)
+        .json(&request)
+        .send()
+        .await
+        .with_context(|| format!("Failed to send {method} request to {url}"))?
+        .error_for_status()?
+        .json::<Value>()
+        .await?;
+
+    if let Some(error) = response.get("error") {
+        bail!("RPC call {method} failed: {error}");
+    }
+    match response.get_mut("result").map(Value::take) {
+        Some(result) if !result.is_null() => Ok(result),
+        _ => bail!("RPC call {method} returned no result"),
+    }
+}
+
+/// Returns the elements of a JSON array, or an error naming the missing `field`.
+fn array<'a>(value: &'a Value, field: &str) -> Result<&'a Vec<Value>> {
+    value
+        .as_array()
+        .with_context(|| format!("RPC response is missing {field}"))
+}
+
+/// Returns a JSON string, or an error naming the missing `field`.
+fn string<'a>(value: &'a Value, field: &str) -> Result<&'a str> {
+    value
+        .as_str()
+        .with_context(|| format!("RPC response is missing {field}"))
+}
+
+/// Parses a hex-encoded JSON-RPC quantity.
+fn quantity(value: &Value) -> Result<u64> {
+    let quantity = string(value, "quantity")?;
+    Ok(u64::from_str_radix(quantity.trim_start_matches("0x"), 16)?)
+}
@@ -0,0 +1,97 @@
+//! Startup self-test that cross-checks the enabled reference backends before a campaign starts.
+
+use crate::{
+    arbitration::{arbitrate, describe},
+    hashing::BackendFactory,
+    permutation::RATE_BYTES,
+};
+use alloy_primitives::hex;
+use anyhow::{bail, Result};
+
+/// Known-answer vectors, as `(input, expected digest)` pairs.
+pub(crate) const KNOWN_ANSWERS: [(&[u8], [u8; 32]); 3] = [
+    (
+        b"",
+        hex!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+    ),
+    (
+        b"abc",
+        hex!("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"),
+    ),
+    (
+        b"The quick brown fox jumps over the lazy dog",
+        hex!("4d741b6f1eb29cb2a9b9911c82f56fa8d73b04959d3d9d222895df6c0b28aa15"),
+    ),
+];
+
+/// The input lengths that the backends are cross-checked at, concentrated around the rate where the
+/// padding logic is most fragile.
+const CROSS_CHECK_LENGTHS: [usize; 10] = [
+    0,
+    1,
+    RATE_BYTES - 1,
+    RATE_BYTES,
+    RATE_BYTES + 1,
+    2 * RATE_BYTES - 1,
+    2 * RATE_BYTES,
+    2 * RATE_BYTES + 1,
+    1000,
+    4096,
+];
+
+/// A byte pattern generator, mapping a byte index to the byte at that index.
+pub(crate) type FillPattern = fn(usize) -> u8;
+
+/// The byte patterns that each cross-check length is filled with. Also used by
+/// [crate::DiffFuzzer::sweep].
+pub(crate) const FILL_PATTERNS: [(&str, FillPattern); 3] = [
+    ("zero", |_| 0x00),
+    ("0xFF", |_| 0xFF),
+    ("counting", |i| i as u8),
+];
+
+/// Verifies that every backend reproduces the known-answer vectors and that all backends agree with
+/// each other on a fixed set of inputs. Returns a diagnostic error naming the offending backends
+/// otherwise, so that campaign results are never produced by a broken reference.
+pub fn self_test(backends: &[BackendFactory]) -> Result<()> {
+    let mut backends = backends.iter().map(|factory| factory()).collect::<Vec<_>>();
+    let mut digests = vec![[0u8; 32]; backends.len()];
+
+    for (input, expected) in KNOWN_ANSWERS {
+        for backend in backends.iter_mut() {
+            let mut digest = [0u8; 32];
+            backend.hash(input, digest.as_mut());
+            if digest != expected {
+                bail!(
+                    "Self-test failed: {} hashed the known-answer vector 0x{} to 0x{}, expected 0x{}",
+                    backend.name(),
+                    hex::encode(input),
+                    hex::encode(digest),
+                    hex::encode(expected)
+                );
+            }
+        }
+    }
+
+    for len in CROSS_CHECK_LENGTHS {
+        for (pattern, fill) in FILL_PATTERNS {
+            let input = (0..len).map(fill).collect::<Vec<_>>();
+            for (backend, digest) in backends.iter_mut().zip(digests.iter_mut()) {
+                backend.hash(&input, digest.as_mut());
+            }
+
+            let verdict = arbitrate(&digests);
+            if verdict.is_unanimous() {
+                continue;
+            }
+
+            let names = backends.iter().map(|b| b.name()).collect::<Vec<_>>();
+            bail!(
+                "Self-test failed: backends disagree on the {len} byte {pattern} input ({})",
+                describe(&verdict, &names, &digests)
+            );
+        }
+    }
+
+    Ok(())
+}
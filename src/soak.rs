@@ -0,0 +1,206 @@
+//! Soak mode, for always-on machines that watch `LibKeccak` releases.
+//!
+//! While a soak campaign runs, its [LiveStats] are periodically dumped as a single JSON object to a
+//! statistics file, which is replaced on every dump so that it always holds the latest figures:
+//!
+//! ```text
+//! {"timestamp":1706745600,"elapsed_secs":300.0,"diffs":1204512,"hashes_per_sec":4015.0,"gas_used":98541235520,"mismatches":0}
+//! ```
+//!
+//! `hashes_per_sec` is the throughput since the previous dump. Every dump is also appended to an
+//! optional log, which is rotated once it grows past a size limit.
+
+use crate::stats::{LiveStats, StatsSnapshot};
+use anyhow::{Context, Result};
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Notify;
+
+/// The settings of soak mode.
+#[derive(Debug, Clone)]
+pub struct Soak {
+    /// The file that the latest statistics are written to.
+    pub stats_file: PathBuf,
+    /// The interval between statistics dumps.
+    pub interval: Duration,
+    /// A log file that every statistics dump is appended to, if set.
+    pub log_file: Option<PathBuf>,
+    /// The size, in bytes, past which the log is rotated.
+    pub max_log_bytes: u64,
+    /// The number of rotated logs that are kept, as `<log>.1` (the most recent) to `<log>.N`.
+    pub max_logs: usize,
+}
+
+impl Soak {
+    /// Creates the settings of a soak campaign that dumps its statistics to `stats_file` every
+    /// five minutes, without a log.
+    pub fn new(stats_file: PathBuf) -> Self {
+        Self {
+            stats_file,
+            interval: Duration::from_secs(5 * 60),
+            log_file: None,
+            max_log_bytes: 10 * 1024 * 1024,
+            max_logs: 5,
+        }
+    }
+}
+
+/// Dumps the statistics of a soak campaign.
+#[derive(Debug)]
+struct StatsWriter {
+    /// The settings of soak mode.
+    soak: Soak,
+    /// The log that every dump is appended to, if any.
+    log: Option<RotatingLog>,
+    /// When the campaign started.
+    started: Instant,
+    /// When the previous dump happened, and the statistics it dumped.
+    last: (Instant, StatsSnapshot),
+}
+
+impl StatsWriter {
+    /// Dumps `stats` to the statistics file and the log.
+    fn dump(&mut self, stats: StatsSnapshot) -> Result<()> {
+        let now = Instant::now();
+        let (last_dump, last_stats) = self.last;
+        let secs = now.duration_since(last_dump).as_secs_f64();
+        let hashes_per_sec = if secs > 0.0 {
+            (stats.diffs - last_stats.diffs) as f64 / secs
+        } else {
+            0.0
+        };
+        self.last = (now, stats);
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let line = format!(
+            "{{\"timestamp\":{timestamp},\"elapsed_secs\":{:.1},\"diffs\":{},\"hashes_per_sec\":{hashes_per_sec:.1},\"gas_used\":{},\"mismatches\":{}}}\n",
+            now.duration_since(self.started).as_secs_f64(),
+            stats.diffs,
+            stats.gas_used,
+            stats.mismatches,
+        );
+
+        // Write the statistics file atomically, so that watchers never read a partial dump.
+        let tmp = self.soak.stats_file.with_extension("tmp");
+        fs::write(&tmp, &line)
+            .with_context(|| format!("Failed to write statistics to {}", tmp.display()))?;
+        fs::rename(&tmp, &self.soak.stats_file).with_context(|| {
+            format!(
+                "Failed to write statistics to {}",
+                self.soak.stats_file.display()
+            )
+        })?;
+
+        if let Some(log) = self.log.as_mut() {
+            log.append(&line)?;
+        }
+        Ok(())
+    }
+}
+
+/// An append-only log that is rotated once it grows past a size limit.
+#[derive(Debug)]
+struct RotatingLog {
+    /// The path of the current log.
+    path: PathBuf,
+    /// The size past which the log is rotated.
+    max_bytes: u64,
+    /// The number of rotated logs that are kept.
+    max_logs: usize,
+    /// The current log, and its size.
+    file: (File, u64),
+}
+
+impl RotatingLog {
+    /// Opens the log at `path`, appending to it if it exists.
+    fn open(path: PathBuf, max_bytes: u64, max_logs: usize) -> Result<Self> {
+        let file = open_log(&path)?;
+        Ok(Self {
+            path,
+            max_bytes,
+            max_logs,
+            file,
+        })
+    }
+
+    /// Appends `line` to the log, rotating it first if the line would take it past the limit.
+    fn append(&mut self, line: &str) -> Result<()> {
+        if self.file.1 > 0 && self.file.1 + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        self.file
+            .0
+            .write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write to log {}", self.path.display()))?;
+        self.file.1 += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts every rotated log up by one, dropping the oldest, and starts a new log.
+    fn rotate(&mut self) -> Result<()> {
+        if self.max_logs == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for i in (1..self.max_logs).rev() {
+                let from = rotated_path(&self.path, i);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, i + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        self.file = open_log(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Opens the log at `path` for appending, returning it along with its current size.
+fn open_log(path: &Path) -> Result<(File, u64)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log {}", path.display()))?;
+    let len = file.metadata()?.len();
+    Ok((file, len))
+}
+
+/// Returns the path of the `i`th most recent rotated log.
+fn rotated_path(path: &Path, i: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{i}"));
+    name.into()
+}
+
+/// Dumps `stats` every `soak.interval` until `done` is notified, and one last time after that.
+pub(crate) async fn soak_task(soak: Soak, stats: Arc<LiveStats>, done: Arc<Notify>) -> Result<()> {
+    let log = soak
+        .log_file
+        .clone()
+        .map(|path| RotatingLog::open(path, soak.max_log_bytes, soak.max_logs))
+        .transpose()?;
+    let started = Instant::now();
+    let mut writer = StatsWriter {
+        soak,
+        log,
+        started,
+        last: (started, stats.snapshot()),
+    };
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(writer.soak.interval) => writer.dump(stats.snapshot())?,
+            _ = done.notified() => return writer.dump(stats.snapshot()),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Per-function gas attribution through the source maps of a Foundry artifact, so that
+//! optimizations can be targeted at the genuinely hot code of `LibKeccak`.
+//!
+//! The gas spent on every program counter (see [OpcodeGas::pc_gas]) is mapped through the
+//! artifact's deployed source map to a source range, and attributed to the innermost Solidity or Yul
+//! function that contains the range, e.g. `LibKeccak.permutation::thetaRhoPi`. Function ranges are
+//! read from the ASTs of the artifacts, so the artifacts must be built with `forge build --ast`.
+//! Instructions that map to no function (e.g. the dispatcher, or compiler-generated code) are
+//! attributed to `(unattributed)`.
+//!
+//! Artifacts are also the most direct way to choose the contract under test (see
+//! [Artifact::read_target]), so that the harness tests exactly what forge built.
+
+use crate::{compile::CompilerSettings, opcodes::OpcodeGas};
+use alloy_primitives::hex;
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+/// The name that gas outside of every function is attributed to.
+const UNATTRIBUTED: &str = "(unattributed)";
+
+/// An entry of a [Solidity source map](https://docs.soliditylang.org/en/latest/internals/source_mappings.html),
+/// mapping an instruction to a range of a source file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceRange {
+    /// The byte offset of the range in the source file.
+    pub start: usize,
+    /// The length of the range, in bytes.
+    pub length: usize,
+    /// The index of the source file, or `None` for compiler-generated code.
+    pub file: Option<usize>,
+}
+
+/// The source range of a Solidity or Yul function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionRange {
+    /// The qualified name of the function, e.g. `LibKeccak.absorb::absorbInner`.
+    pub name: String,
+    /// The range of the function's definition.
+    pub range: SourceRange,
+}
+
+/// A Foundry artifact of a contract, along with the functions of every source that it was compiled
+/// from.
+#[derive(Debug, Clone, Default)]
+pub struct Artifact {
+    /// The deployed bytecode of the contract.
+    pub code: Vec<u8>,
+    /// The creation bytecode of the contract, if the artifact has it.
+    pub creation_code: Option<Vec<u8>>,
+    /// The version of the compiler that built the contract, from the artifact's metadata, if any.
+    pub compiler: Option<String>,
+    /// The optimizer settings that the contract was built with, from the artifact's metadata, if
+    /// any.
+    pub settings: Option<CompilerSettings>,
+    /// The deployed source map, indexed by instruction.
+    pub source_map: Vec<SourceRange>,
+    /// The functions found in the ASTs.
+    pub functions: Vec<FunctionRange>,
+}
+
+impl Artifact {
+    /// Reads the Foundry artifact of the contract at `path`, with the functions of its own AST and
+    /// of the ASTs of the artifacts at `sources`, e.g. those of the libraries that it uses.
+    pub fn read(path: &Path, sources: &[impl AsRef<Path>]) -> Result<Self> {
+        let mut artifact = Self::read_target(path)?;
+        if artifact.source_map.is_empty() {
+            bail!("{} has no deployed source map", path.display());
+        }
+
+        let mut functions = Vec::new();
+        for ast_path in std::iter::once(path).chain(sources.iter().map(AsRef::as_ref)) {
+            let ast = &read_json(ast_path)?["ast"];
+            if ast.is_null() {
+                bail!(
+                    "{} has no AST, build it with `forge build --ast`",
+                    ast_path.display()
+                );
+            }
+            collect_functions(ast, "", &mut functions)?;
+        }
+        artifact.functions = functions;
+        Ok(artifact)
+    }
+
+    /// Reads the Foundry artifact of the contract at `path` for testing the contract: its deployed
+    /// and creation bytecode, its deployed source map if any, and its compiler version and
+    /// settings, without requiring an AST.
+    pub fn read_target(path: &Path) -> Result<Self> {
+        let artifact = read_json(path)?;
+        let bytecode = &artifact["deployedBytecode"];
+        let code = match bytecode["object"].as_str() {
+            Some(code) if !code.trim_start_matches("0x").is_empty() => code,
+            // Interfaces and abstract contracts have empty bytecode.
+            _ => bail!("{} has no deployed bytecode", path.display()),
+        };
+        let creation_code = artifact["bytecode"]["object"]
+            .as_str()
+            .filter(|code| !code.trim_start_matches("0x").is_empty())
+            .map(hex::decode)
+            .transpose()
+            .with_context(|| format!("Invalid creation bytecode in {}", path.display()))?;
+        let settings = &artifact["metadata"]["settings"];
+        let source_map = match bytecode["sourceMap"].as_str() {
+            Some(source_map) => parse_source_map(source_map)?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            code: hex::decode(code)
+                .with_context(|| format!("Invalid deployed bytecode in {}", path.display()))?,
+            creation_code,
+            compiler: artifact["metadata"]["compiler"]["version"]
+                .as_str()
+                .map(str::to_string),
+            settings: settings.is_object().then(|| CompilerSettings {
+                optimizer_runs: settings["optimizer"]["enabled"]
+                    .as_bool()
+                    .unwrap_or_default()
+                    .then(|| settings["optimizer"]["runs"].as_u64())
+                    .flatten(),
+                via_ir: settings["viaIR"].as_bool().unwrap_or_default(),
+            }),
+            source_map,
+            functions: Vec::new(),
+        })
+    }
+
+    /// Describes the compiler version and settings that built the contract, if known, e.g.
+    /// `solc 0.8.25, 200 runs, via-IR`.
+    pub fn build_info(&self) -> Option<String> {
+        match (self.compiler.as_deref(), self.settings) {
+            (Some(compiler), Some(settings)) => Some(format!("solc {compiler}, {settings}")),
+            (Some(compiler), None) => Some(format!("solc {compiler}")),
+            (None, Some(settings)) => Some(settings.to_string()),
+            (None, None) => None,
+        }
+    }
+
+    /// Attributes the gas spent on every program counter by `inspector` to the innermost function
+    /// that contains its instruction's source range, returning the gas of every function, most
+    /// expensive first.
+    pub fn attribute(&self, inspector: &OpcodeGas) -> Vec<(String, u64)> {
+        let mut gas = BTreeMap::<&str, u64>::new();
+        for (index, pc) in instruction_offsets(&self.code).into_iter().enumerate() {
+            let Some(&spent) = inspector.pc_gas().get(pc).filter(|&&spent| spent > 0) else {
+                continue;
+            };
+            let function = self
+                .source_map
+                .get(index)
+                .and_then(|range| self.innermost_function(range));
+            *gas.entry(function.map_or(UNATTRIBUTED, |f| f.name.as_str()))
+                .or_default() += spent;
+        }
+
+        let mut gas = gas
+            .into_iter()
+            .map(|(name, gas)| (name.to_string(), gas))
+            .collect::<Vec<_>>();
+        gas.sort_by_key(|(name, gas)| (std::cmp::Reverse(*gas), name.clone()));
+        gas
+    }
+
+    /// Returns the innermost function whose definition contains `range`.
+    fn innermost_function(&self, range: &SourceRange) -> Option<&FunctionRange> {
+        let file = range.file?;
+        self.functions
+            .iter()
+            .filter(|function| {
+                function.range.file == Some(file)
+                    && function.range.start <= range.start
+                    && range.start + range.length <= function.range.start + function.range.length
+            })
+            .min_by_key(|function| function.range.length)
+    }
+}
+
+/// Renders the gas of every function, as returned by [Artifact::attribute], as a table with the
+/// share of the total gas spent executing instructions that every function accounts for.
+pub fn function_table(gas: &[(String, u64)]) -> String {
+    let total = gas.iter().map(|(_, gas)| gas).sum::<u64>().max(1);
+    let width = gas.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+    let mut table = String::new();
+    writeln!(table, "{:<width$} {:>12} {:>7}", "function", "gas", "share").unwrap();
+    for (name, gas) in gas {
+        let share = *gas as f64 * 100.0 / total as f64;
+        writeln!(table, "{name:<width$} {gas:>12} {share:>6.2}%").unwrap();
+    }
+    table
+}
+
+/// Parses a compressed source map, where every entry is `s:l:f:j:m` and empty or missing fields
+/// inherit the value of the previous entry.
+pub fn parse_source_map(map: &str) -> Result<Vec<SourceRange>> {
+    let mut entries = Vec::new();
+    let (mut start, mut length, mut file) = (0, 0, -1);
+    for (i, entry) in map.split(';').enumerate() {
+        let mut fields = entry.split(':');
+        let mut field = |value: &mut i64| -> Result<()> {
+            match fields.next() {
+                Some(text) if !text.is_empty() => {
+                    *value = text
+                        .parse()
+                        .with_context(|| format!("Invalid source map entry {i}: {entry}"))?;
+                }
+                _ => {}
+            }
+            Ok(())
+        };
+        field(&mut start)?;
+        field(&mut length)?;
+        field(&mut file)?;
+
+        entries.push(SourceRange {
+            start: start.max(0) as usize,
+            length: length.max(0) as usize,
+            file: usize::try_from(file).ok(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Returns the program counter of every instruction of `code`, indexed by instruction.
+pub fn instruction_offsets(code: &[u8]) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        offsets.push(pc);
+        let opcode = code[pc];
+        // PUSH1 (0x60) to PUSH32 (0x7f) are followed by their immediate.
+        pc += match opcode {
+            0x60..=0x7f => (opcode - 0x5f) as usize + 1,
+            _ => 1,
+        };
+    }
+    offsets
+}
+
+/// Collects the functions defined under the AST `node` into `functions`, qualifying their names
+/// with `scope`.
+fn collect_functions(node: &Value, scope: &str, functions: &mut Vec<FunctionRange>) -> Result<()> {
+    let mut scope = scope.to_string();
+    if let Some(node_type) = node["nodeType"].as_str() {
+        let name = node["name"].as_str().unwrap_or_default();
+        let qualified = match scope.as_str() {
+            "" => name.to_string(),
+            _ if node_type == "FunctionDefinition" => format!("{scope}.{name}"),
+            _ => format!("{scope}::{name}"),
+        };
+        match node_type {
+            "ContractDefinition" => scope = name.to_string(),
+            "FunctionDefinition" | "YulFunctionDefinition" => {
+                let src = node["src"].as_str().unwrap_or_default();
+                let Some(range) = parse_source_map(src)?.first().copied() else {
+                    bail!("Function {qualified} has no source range");
+                };
+                functions.push(FunctionRange {
+                    name: qualified.clone(),
+                    range,
+                });
+                scope = qualified;
+            }
+            _ => {}
+        }
+    }
+
+    match node {
+        Value::Object(map) => map
+            .values()
+            .try_for_each(|child| collect_functions(child, &scope, functions)),
+        Value::Array(children) => children
+            .iter()
+            .try_for_each(|child| collect_functions(child, &scope, functions)),
+        _ => Ok(()),
+    }
+}
+
+/// Reads the JSON file at `path`.
+fn read_json(path: &Path) -> Result<Value> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read artifact {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Invalid artifact {}", path.display()))
+}
@@ -0,0 +1,93 @@
+//! The EVM hard forks that the `StatefulSponge` contract can additionally be diffed under, to catch
+//! any accidental reliance on fork-specific behavior.
+//!
+//! The contract always executes under revm's latest spec. Every extra spec runs the contract in its
+//! own EVM, whose digest takes a slot of its own (e.g. `evm@london`) in the arbitration, so a spec
+//! that squeezes a different digest is reported like any other dissenting implementation.
+
+use anyhow::{bail, Result};
+use std::{fmt, str::FromStr};
+
+/// A hard fork that the contract can be executed under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EvmSpec {
+    Istanbul,
+    Berlin,
+    London,
+    Merge,
+    Shanghai,
+    Cancun,
+}
+
+impl EvmSpec {
+    /// Every supported spec, oldest first.
+    pub const ALL: [Self; 6] = [
+        Self::Istanbul,
+        Self::Berlin,
+        Self::London,
+        Self::Merge,
+        Self::Shanghai,
+        Self::Cancun,
+    ];
+
+    /// Returns the lowercase name of the spec, e.g. `london`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Istanbul => "istanbul",
+            Self::Berlin => "berlin",
+            Self::London => "london",
+            Self::Merge => "merge",
+            Self::Shanghai => "shanghai",
+            Self::Cancun => "cancun",
+        }
+    }
+
+    /// Returns the name of the digest slot of the contract under this spec, e.g. `evm@london`.
+    pub fn slot_name(self) -> &'static str {
+        match self {
+            Self::Istanbul => "evm@istanbul",
+            Self::Berlin => "evm@berlin",
+            Self::London => "evm@london",
+            Self::Merge => "evm@merge",
+            Self::Shanghai => "evm@shanghai",
+            Self::Cancun => "evm@cancun",
+        }
+    }
+
+    /// Returns the revm spec of this hard fork.
+    #[cfg(feature = "evm")]
+    pub fn spec_id(self) -> revm::primitives::SpecId {
+        use revm::primitives::SpecId;
+        match self {
+            Self::Istanbul => SpecId::ISTANBUL,
+            Self::Berlin => SpecId::BERLIN,
+            Self::London => SpecId::LONDON,
+            Self::Merge => SpecId::MERGE,
+            Self::Shanghai => SpecId::SHANGHAI,
+            Self::Cancun => SpecId::CANCUN,
+        }
+    }
+}
+
+impl fmt::Display for EvmSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EvmSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        match Self::ALL.into_iter().find(|spec| spec.name() == name) {
+            Some(spec) => Ok(spec),
+            // Paris is the execution layer name of the merge.
+            None if name == "paris" => Ok(Self::Merge),
+            None => bail!(
+                "Unknown EVM spec `{name}`, expected one of {}",
+                Self::ALL.map(Self::name).join(", ")
+            ),
+        }
+    }
+}
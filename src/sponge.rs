@@ -0,0 +1,219 @@
+//! A Rust model of the state of the `StatefulSponge` contract, mirroring call by call what the
+//! contract and `LibKeccak` intend, as the oracle of the stateful test modes and as
+//! documentation-by-code of the expected semantics.
+//!
+//! The contract persists a single Keccak-f[1600] state matrix between calls:
+//!
+//! - `absorb(bytes)` pads its chunk with the `10*1` padding, XORs every block of it into the rate
+//!   portion of the state and permutes the state after each, so every call absorbs whole blocks
+//!   and nothing stays buffered across calls.
+//! - `squeeze()` returns the first four lanes of the state as the digest, without permuting it
+//!   again, and resets the state to zero, so that the next `absorb` starts a fresh sponge rather
+//!   than continuing the squeezed one.
+//! - `reset()`, which `StatefulSponge` doesn't expose but other targets may, resets the state to
+//!   zero without squeezing it, which must leave the contract indistinguishable from a fresh
+//!   deployment.
+//!
+//! A `squeeze` of a fresh sponge thus returns [RESET_DIGEST]. Besides the state, the model buffers
+//! the message that the state absorbed since it was reset: every chunk but the last padded, and
+//! the last one as is. The next squeeze returns the Keccak256 digest of that message, which every
+//! reference backend can compute (see [SpongeModel::intended_digest]).
+
+use crate::{
+    hashing::hash_input_tiny,
+    permutation::{keccak_f, pad, StateMatrix, DIGEST_BYTES, RATE_BYTES},
+};
+use std::fmt;
+
+/// The digest that squeezing the `StatefulSponge` contract returns when nothing was absorbed since
+/// it was deployed or reset by a squeeze: the first lanes of the all-zero state (see
+/// [crate::engine::DiffEngine::with_squeeze_idempotence]).
+pub const RESET_DIGEST: [u8; DIGEST_BYTES] = [0u8; DIGEST_BYTES];
+
+/// A call of the `StatefulSponge` contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpongeCall {
+    /// `absorb(bytes)` with the given chunk.
+    Absorb(Vec<u8>),
+    /// `squeeze()`.
+    Squeeze,
+    /// `reset()`, if the contract exposes it.
+    Reset,
+}
+
+impl fmt::Display for SpongeCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Absorb(chunk) => write!(f, "absorb({} bytes)", chunk.len()),
+            Self::Squeeze => f.write_str("squeeze()"),
+            Self::Reset => f.write_str("reset()"),
+        }
+    }
+}
+
+/// The state of the `StatefulSponge` contract as a plain Rust value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpongeModel {
+    /// The persisted state matrix.
+    state: StateMatrix,
+    /// The message absorbed since the last reset, with every chunk but the last padded.
+    message: Vec<u8>,
+    /// The offset of the last absorbed chunk in `message`, which isn't padded yet, or `None` if
+    /// nothing was absorbed since the last reset.
+    last_chunk: Option<usize>,
+}
+
+impl SpongeModel {
+    /// Creates the model of a freshly deployed contract.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the model of a freshly deployed contract that absorbed each of `chunks` in its own
+    /// call.
+    pub fn absorbing(chunks: &[&[u8]]) -> Self {
+        let mut model = Self::new();
+        for chunk in chunks {
+            model.absorb(chunk);
+        }
+        model
+    }
+
+    /// Returns the persisted state matrix.
+    pub fn state(&self) -> &StateMatrix {
+        &self.state
+    }
+
+    /// Returns the message absorbed since the contract was deployed or last squeezed, whose
+    /// Keccak256 digest the next squeeze returns, or `None` if nothing was absorbed.
+    pub fn message(&self) -> Option<&[u8]> {
+        self.last_chunk.map(|_| self.message.as_slice())
+    }
+
+    /// Consumes the model, returning its message (see [SpongeModel::message]), which is empty if
+    /// nothing was absorbed.
+    pub fn into_message(self) -> Vec<u8> {
+        self.message
+    }
+
+    /// Returns the digest that the next squeeze is intended to return, computed from the message
+    /// by another implementation than the state's: that of the reset state if nothing was
+    /// absorbed, and otherwise the Keccak256 digest of the message.
+    pub fn intended_digest(&self) -> [u8; DIGEST_BYTES] {
+        let Some(message) = self.message() else {
+            return RESET_DIGEST;
+        };
+        let mut digest = [0u8; DIGEST_BYTES];
+        hash_input_tiny(message, digest.as_mut());
+        digest
+    }
+
+    /// Like `absorb`: pads `chunk` with the `10*1` padding and absorbs every block of it, permuting
+    /// the state after each.
+    pub fn absorb(&mut self, chunk: &[u8]) {
+        if let Some(start) = self.last_chunk {
+            let padded = pad(&self.message[start..]);
+            self.message.truncate(start);
+            self.message.extend_from_slice(&padded);
+        }
+        self.last_chunk = Some(self.message.len());
+        self.message.extend_from_slice(chunk);
+
+        for block in pad(chunk).chunks_exact(RATE_BYTES) {
+            for (lane, bytes) in self.state.iter_mut().zip(block.chunks_exact(8)) {
+                *lane ^= u64::from_le_bytes(bytes.try_into().expect("lane is 8 bytes"));
+            }
+            keccak_f(&mut self.state);
+        }
+    }
+
+    /// Like `squeeze`: returns the first four lanes of the state as the digest, and resets the
+    /// model to that of a fresh sponge.
+    pub fn squeeze(&mut self) -> [u8; DIGEST_BYTES] {
+        let mut digest = [0u8; DIGEST_BYTES];
+        for (bytes, lane) in digest.chunks_exact_mut(8).zip(self.state.iter()) {
+            bytes.copy_from_slice(&lane.to_le_bytes());
+        }
+        self.reset();
+        digest
+    }
+
+    /// Like `reset`, if the contract exposes it: discards everything absorbed without squeezing,
+    /// leaving the model of a freshly deployed contract.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Applies `call`, returning the digest if it's a squeeze.
+    pub fn call(&mut self, call: &SpongeCall) -> Option<[u8; DIGEST_BYTES]> {
+        match call {
+            SpongeCall::Absorb(chunk) => {
+                self.absorb(chunk);
+                None
+            }
+            SpongeCall::Squeeze => Some(self.squeeze()),
+            SpongeCall::Reset => {
+                self.reset();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{engine::reference_message, permutation::keccak256};
+
+    /// Chunk schedules with empty and 1 byte chunks, and chunks around the rate.
+    fn schedules() -> Vec<Vec<Vec<u8>>> {
+        vec![
+            vec![vec![]],
+            vec![vec![], vec![]],
+            vec![b"a".to_vec()],
+            vec![vec![], b"abc".to_vec()],
+            vec![b"x".to_vec(), vec![], b"y".to_vec()],
+            vec![vec![0; RATE_BYTES - 1], vec![1], vec![]],
+            vec![
+                vec![7; RATE_BYTES],
+                vec![],
+                vec![9; RATE_BYTES + 1],
+                vec![3],
+            ],
+        ]
+    }
+
+    #[test]
+    fn model_agrees_with_reference_message() {
+        for schedule in schedules() {
+            let chunks = schedule.iter().map(Vec::as_slice).collect::<Vec<_>>();
+            let (last, init) = chunks.split_last().expect("a chunk");
+            let mut expected = init.iter().flat_map(|chunk| pad(chunk)).collect::<Vec<_>>();
+            expected.extend_from_slice(last);
+
+            let mut model = SpongeModel::absorbing(&chunks);
+            assert_eq!(
+                reference_message(&chunks).as_ref(),
+                expected,
+                "{schedule:?}"
+            );
+            assert_eq!(model.message(), Some(expected.as_slice()), "{schedule:?}");
+            assert_eq!(
+                model.intended_digest(),
+                keccak256(&expected),
+                "{schedule:?}"
+            );
+            assert_eq!(model.squeeze(), keccak256(&expected), "{schedule:?}");
+        }
+    }
+
+    #[test]
+    fn squeeze_after_squeeze_returns_reset_digest() {
+        let mut model = SpongeModel::absorbing(&[b"abc", &[], b"d"]);
+        assert_ne!(model.squeeze(), RESET_DIGEST);
+        assert_eq!(model.message(), None);
+        assert_eq!(model.intended_digest(), RESET_DIGEST);
+        assert_eq!(model.squeeze(), RESET_DIGEST);
+        assert_eq!(model, SpongeModel::new());
+    }
+}
@@ -0,0 +1,200 @@
+//! Live campaign statistics, shared by every task of a campaign and readable while it runs.
+
+use crate::failure::Failure;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::Notify;
+
+/// The number of recent mismatches that [LiveStats] keeps the descriptions of.
+const RECENT_FAILURES: usize = 64;
+
+/// Counters that every task of a campaign updates as it goes, unlike its [crate::report::Report],
+/// which is only merged once the tasks finish.
+#[derive(Debug, Default)]
+pub struct LiveStats {
+    /// The number of inputs that were diffed.
+    diffs: AtomicU64,
+    /// The total gas used by the contract calls of every diffed input.
+    gas_used: AtomicU64,
+    /// The distribution of the gas used by the contract calls of every diffed input.
+    gas: GasHistogram,
+    /// The number of mismatches that were found.
+    mismatches: AtomicU64,
+    /// The number of inputs diffed by every registered task, by task number.
+    threads: Mutex<Vec<(u64, Arc<AtomicU64>)>>,
+    /// The descriptions of the most recent mismatches, oldest first.
+    recent_failures: Mutex<VecDeque<String>>,
+}
+
+/// A point-in-time copy of [LiveStats].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    /// The number of inputs that were diffed.
+    pub diffs: u64,
+    /// The total gas used by the contract calls of every diffed input.
+    pub gas_used: u64,
+    /// The number of mismatches that were found.
+    pub mismatches: u64,
+}
+
+impl LiveStats {
+    /// Records a diffed input whose contract calls used `gas_used` gas.
+    pub fn record_diff(&self, gas_used: u64) {
+        self.diffs.fetch_add(1, Ordering::Relaxed);
+        self.gas_used.fetch_add(gas_used, Ordering::Relaxed);
+        if cfg!(feature = "evm") {
+            self.gas.record(gas_used);
+        }
+    }
+
+    /// Records a mismatch.
+    pub fn record_mismatch(&self, failure: &Failure) {
+        self.mismatches.fetch_add(1, Ordering::Relaxed);
+        let mut recent = self.recent_failures.lock().expect("stats lock poisoned");
+        if recent.len() == RECENT_FAILURES {
+            recent.pop_front();
+        }
+        recent.push_back(format!("{}: {}", failure.context, failure.description));
+    }
+
+    /// Returns the counter of the inputs diffed by task `thread`, which the task increments as it
+    /// goes. Registering the same task again returns its existing counter.
+    pub fn register_thread(&self, thread: u64) -> Arc<AtomicU64> {
+        let mut threads = self.threads.lock().expect("stats lock poisoned");
+        if let Some((_, diffs)) = threads.iter().find(|(number, _)| *number == thread) {
+            return diffs.clone();
+        }
+        let diffs = Arc::new(AtomicU64::new(0));
+        threads.push((thread, diffs.clone()));
+        diffs
+    }
+
+    /// Returns the number of inputs diffed by every registered task, by task number.
+    pub fn thread_diffs(&self) -> Vec<(u64, u64)> {
+        let threads = self.threads.lock().expect("stats lock poisoned");
+        let mut diffs = threads
+            .iter()
+            .map(|(thread, diffs)| (*thread, diffs.load(Ordering::Relaxed)))
+            .collect::<Vec<_>>();
+        diffs.sort_unstable();
+        diffs
+    }
+
+    /// Returns the distribution of the gas used per diffed input.
+    pub fn gas(&self) -> &GasHistogram {
+        &self.gas
+    }
+
+    /// Returns the descriptions of the most recent mismatches, newest first.
+    pub fn recent_failures(&self) -> Vec<String> {
+        let recent = self.recent_failures.lock().expect("stats lock poisoned");
+        recent.iter().rev().cloned().collect()
+    }
+
+    /// Returns the current value of every counter.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            diffs: self.diffs.load(Ordering::Relaxed),
+            gas_used: self.gas_used.load(Ordering::Relaxed),
+            mismatches: self.mismatches.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The number of buckets per power of two in a [GasHistogram], as a power of two.
+const GAS_BUCKET_BITS: u32 = 4;
+
+/// An approximate, lock-free histogram of gas amounts. Amounts are bucketed by their leading
+/// [GAS_BUCKET_BITS] + 1 bits, so percentiles are accurate to within 1/16th.
+#[derive(Debug)]
+pub struct GasHistogram {
+    /// The number of amounts in every bucket.
+    buckets: Box<[AtomicU64]>,
+}
+
+impl Default for GasHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: (0..64 << GAS_BUCKET_BITS)
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+        }
+    }
+}
+
+impl GasHistogram {
+    /// Records an amount of gas.
+    pub fn record(&self, gas: u64) {
+        self.buckets[Self::bucket(gas)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the lower bound of the bucket holding the `quantile` (between 0 and 1) of the
+    /// recorded amounts, or `None` if none were recorded.
+    pub fn percentile(&self, quantile: f64) -> Option<u64> {
+        let counts = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+        let total = counts.iter().sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        let bucket = counts.iter().position(|count| {
+            seen += count;
+            seen >= rank
+        })?;
+        Some(Self::lower_bound(bucket))
+    }
+
+    /// Returns the bucket that `gas` falls in.
+    fn bucket(gas: u64) -> usize {
+        let linear = 1 << GAS_BUCKET_BITS;
+        if gas < linear {
+            return gas as usize;
+        }
+        let shift = u64::BITS - gas.leading_zeros() - GAS_BUCKET_BITS - 1;
+        let mantissa = (gas >> shift) - linear;
+        (((shift + 1) << GAS_BUCKET_BITS) as u64 + mantissa) as usize
+    }
+
+    /// Returns the smallest amount of gas in `bucket`.
+    fn lower_bound(bucket: usize) -> u64 {
+        let linear = 1 << GAS_BUCKET_BITS;
+        if bucket < linear {
+            return bucket as u64;
+        }
+        let shift = (bucket >> GAS_BUCKET_BITS) - 1;
+        ((linear + bucket % linear) as u64) << shift
+    }
+}
+
+/// Samples the cumulative number of diffs in `stats` every `interval` until `done` is notified, and
+/// one last time after that. Returns the samples along with the elapsed time at which each was taken.
+pub(crate) async fn sample_timeline(
+    stats: Arc<LiveStats>,
+    interval: Duration,
+    done: Arc<Notify>,
+) -> Vec<(Duration, u64)> {
+    let started = Instant::now();
+    let mut timeline = vec![(Duration::ZERO, stats.snapshot().diffs)];
+    loop {
+        let finished = tokio::select! {
+            _ = tokio::time::sleep(interval) => false,
+            _ = done.notified() => true,
+        };
+        timeline.push((started.elapsed(), stats.snapshot().diffs));
+        if finished {
+            return timeline;
+        }
+    }
+}
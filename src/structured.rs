@@ -0,0 +1,157 @@
+//! Structured fuzz inputs, derived from raw fuzzer bytes with [arbitrary].
+//!
+//! Rather than treating the fuzzer's bytes as the message itself, a [FuzzInput] interprets them as
+//! a length class, a fill pattern and a plan for splitting the message across `absorb` calls. This
+//! concentrates the campaign on rate boundaries, degenerate byte patterns and multi-call absorbs,
+//! which uniformly random buffers rarely hit.
+
+use crate::permutation::RATE_BYTES;
+use arbitrary::Arbitrary;
+
+/// The exclusive upper bound on the block count of [LengthClass::Boundary] lengths.
+const MAX_BOUNDARY_BLOCKS: usize = 16;
+
+/// The exclusive upper bound on [LengthClass::Any] lengths.
+const MAX_LENGTH: usize = 8192;
+
+/// The maximum number of `absorb` calls that a message is split across.
+const MAX_CHUNKS: usize = 16;
+
+/// The length class of a [FuzzInput]'s message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Arbitrary)]
+pub enum LengthClass {
+    /// Shorter than a single block.
+    Short(u8),
+    /// Directly next to or on a multiple of the rate, where the `10*1` padding logic is most
+    /// fragile.
+    Boundary {
+        /// The multiple of the rate, modulo [MAX_BOUNDARY_BLOCKS].
+        blocks: u8,
+        /// The offset from the multiple of the rate.
+        offset: BoundaryOffset,
+    },
+    /// Any length below [MAX_LENGTH].
+    Any(u16),
+}
+
+/// The offset of a [LengthClass::Boundary] length from a multiple of the rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Arbitrary)]
+pub enum BoundaryOffset {
+    /// One byte short of the multiple.
+    Below,
+    /// Exactly on the multiple.
+    At,
+    /// One byte past the multiple.
+    Above,
+}
+
+impl LengthClass {
+    /// Returns the message length described by the class.
+    pub fn resolve(self) -> usize {
+        match self {
+            Self::Short(len) => len as usize % RATE_BYTES,
+            Self::Boundary { blocks, offset } => {
+                let multiple = (blocks as usize % MAX_BOUNDARY_BLOCKS) * RATE_BYTES;
+                match offset {
+                    BoundaryOffset::Below => multiple.saturating_sub(1),
+                    BoundaryOffset::At => multiple,
+                    BoundaryOffset::Above => multiple + 1,
+                }
+            }
+            Self::Any(len) => len as usize % MAX_LENGTH,
+        }
+    }
+}
+
+/// The byte pattern that a [FuzzInput]'s message is filled with.
+#[derive(Debug, Clone, PartialEq, Eq, Arbitrary)]
+pub enum Fill {
+    /// All `0x00` bytes.
+    Zero,
+    /// All `0xFF` bytes.
+    Ones,
+    /// The byte index, truncated to a byte.
+    Counting,
+    /// A single repeated byte.
+    Repeating(u8),
+    /// The given bytes, repeated to the message length. Falls back to [Fill::Zero] if empty.
+    Bytes(Vec<u8>),
+}
+
+impl Fill {
+    /// Returns the byte at index `i` of the pattern.
+    fn byte(&self, i: usize) -> u8 {
+        match self {
+            Self::Zero => 0x00,
+            Self::Ones => 0xFF,
+            Self::Counting => i as u8,
+            Self::Repeating(byte) => *byte,
+            Self::Bytes(bytes) if bytes.is_empty() => 0x00,
+            Self::Bytes(bytes) => bytes[i % bytes.len()],
+        }
+    }
+}
+
+/// How a [FuzzInput]'s message is split across `absorb` calls.
+#[derive(Debug, Clone, PartialEq, Eq, Arbitrary)]
+pub enum ChunkPlan {
+    /// The whole message is absorbed in a single call.
+    Single,
+    /// The message is split into up to [MAX_CHUNKS] calls of equal length, with the remainder
+    /// absorbed in the last call.
+    Even(u8),
+    /// The message is split at the given offsets, modulo the message length plus one. Empty chunks
+    /// are kept, and only the first [MAX_CHUNKS] offsets are used.
+    Cuts(Vec<u16>),
+}
+
+/// A structured fuzz input.
+#[derive(Debug, Clone, PartialEq, Eq, Arbitrary)]
+pub struct FuzzInput {
+    /// The length class of the message.
+    pub length: LengthClass,
+    /// The byte pattern of the message.
+    pub fill: Fill,
+    /// How the message is split across `absorb` calls.
+    pub chunks: ChunkPlan,
+}
+
+impl FuzzInput {
+    /// Returns the length of the message.
+    pub fn message_len(&self) -> usize {
+        self.length.resolve()
+    }
+
+    /// Builds the message.
+    pub fn message(&self) -> Vec<u8> {
+        (0..self.message_len()).map(|i| self.fill.byte(i)).collect()
+    }
+
+    /// Splits `message` into the chunks that are absorbed in separate calls, according to the
+    /// [ChunkPlan]. Always returns at least one chunk.
+    pub fn split<'a>(&self, message: &'a [u8]) -> Vec<&'a [u8]> {
+        let mut cuts = match &self.chunks {
+            ChunkPlan::Single => Vec::new(),
+            ChunkPlan::Even(count) => {
+                let count = 1 + *count as usize % MAX_CHUNKS;
+                let size = message.len() / count;
+                (1..count).map(|i| i * size).collect()
+            }
+            ChunkPlan::Cuts(offsets) => offsets
+                .iter()
+                .take(MAX_CHUNKS - 1)
+                .map(|offset| *offset as usize % (message.len() + 1))
+                .collect(),
+        };
+        cuts.sort_unstable();
+
+        let mut start = 0;
+        let mut chunks = Vec::with_capacity(cuts.len() + 1);
+        for cut in cuts {
+            chunks.push(&message[start..cut]);
+            start = cut;
+        }
+        chunks.push(&message[start..]);
+        chunks
+    }
+}
@@ -0,0 +1,297 @@
+//! A live terminal dashboard of a running campaign, as an alternative to the per-task progress bars.
+//!
+//! The dashboard shows the campaign's progress, the aggregate and per-task throughput, percentiles
+//! of the gas used per input and a feed of the most recent mismatches, all read from the campaign's
+//! [LiveStats]. Pressing `q`, `Esc` or `Ctrl-C` stops the campaign gracefully, and pressing one of
+//! them again exits immediately.
+
+use crate::{report::Report, reporter::Reporter, stats::LiveStats, DiffFuzzer};
+use anyhow::{anyhow, Context, Result};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    widgets::{Block, Gauge, List, Paragraph, Row, Table},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+/// The interval between redraws of the dashboard, which is also how long it waits for key presses.
+const FRAME_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The interval over which the throughput figures are measured.
+const RATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The gas percentiles shown on the dashboard, with their labels.
+const GAS_PERCENTILES: [(&str, f64); 5] = [
+    ("p50", 0.5),
+    ("p90", 0.9),
+    ("p99", 0.99),
+    ("p99.9", 0.999),
+    ("max", 1.0),
+];
+
+/// A live dashboard, as a [Reporter]: it takes over the terminal when a campaign starts, draws
+/// the campaign on the alternate screen from a background thread, and restores the terminal when the
+/// campaign finishes (or the dashboard is dropped). Reporters registered after the dashboard thus
+/// print to the restored terminal.
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    /// The stop flag and the handle of the drawing thread, while the dashboard is shown.
+    drawing: Mutex<Option<Drawing>>,
+}
+
+/// The stop flag and the handle of a dashboard's drawing thread.
+type Drawing = (Arc<AtomicBool>, JoinHandle<Result<()>>);
+
+impl Dashboard {
+    /// Stops the drawing thread, if any, and waits for it to restore the terminal. Returns any
+    /// error that drawing the dashboard failed with.
+    fn stop(&self) -> Result<()> {
+        let drawing = self.drawing.lock().expect("dashboard lock poisoned").take();
+        let Some((done, handle)) = drawing else {
+            return Ok(());
+        };
+        done.store(true, Ordering::Relaxed);
+        handle
+            .join()
+            .map_err(|_| anyhow!("The dashboard thread panicked"))?
+    }
+}
+
+impl Reporter for Dashboard {
+    fn on_start(&self, config: &DiffFuzzer) -> Result<()> {
+        self.stop()?;
+        let terminal = ratatui::try_init().context("Failed to initialize the terminal")?;
+        let done = Arc::new(AtomicBool::new(false));
+        let mut state = State::new(config);
+        let handle = std::thread::spawn({
+            let done = done.clone();
+            move || {
+                let result = state.run(terminal, &done);
+                ratatui::restore();
+                result
+            }
+        });
+        *self.drawing.lock().expect("dashboard lock poisoned") = Some((done, handle));
+        Ok(())
+    }
+
+    fn on_finish(&self, _: &DiffFuzzer, _: &Result<Report>) -> Result<()> {
+        self.stop()
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// The state of the drawing thread.
+struct State {
+    /// The campaign's live statistics.
+    stats: Arc<LiveStats>,
+    /// The campaign's stop flag.
+    stop: Arc<AtomicBool>,
+    /// The total number of diffs of the campaign, or zero if unbounded.
+    diff_count: u64,
+    /// The duration of the campaign, if time-limited.
+    duration: Option<Duration>,
+    /// When the dashboard started.
+    started: Instant,
+    /// When the throughput was last measured, with the total and per-task diffs at that time.
+    last_sample: (Instant, u64, BTreeMap<u64, u64>),
+    /// The aggregate throughput over the last measurement interval, in diffs per second.
+    rate: f64,
+    /// The per-task throughput over the last measurement interval, in diffs per second.
+    thread_rates: BTreeMap<u64, f64>,
+}
+
+impl State {
+    /// Returns the initial state of the dashboard of the campaign run by `config`.
+    fn new(config: &DiffFuzzer) -> Self {
+        let now = Instant::now();
+        let diffs = config.stats.snapshot().diffs;
+        let threads = config.stats.thread_diffs().into_iter().collect();
+        Self {
+            stats: config.stats.clone(),
+            stop: config.stop.clone(),
+            diff_count: config.diff_count,
+            duration: config.duration,
+            started: now,
+            last_sample: (now, diffs, threads),
+            rate: 0.0,
+            thread_rates: BTreeMap::new(),
+        }
+    }
+
+    /// Draws the dashboard every frame and handles key presses until `done` is set.
+    fn run(&mut self, mut terminal: DefaultTerminal, done: &AtomicBool) -> Result<()> {
+        while !done.load(Ordering::Relaxed) {
+            self.measure();
+            terminal.draw(|frame| self.render(frame))?;
+            if !event::poll(FRAME_INTERVAL)? {
+                continue;
+            }
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            let quit = matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                || (key.code == KeyCode::Char('c')
+                    && key.modifiers.contains(KeyModifiers::CONTROL));
+            // The first press stops the campaign, the second exits immediately.
+            if key.kind == KeyEventKind::Press && quit && self.stop.swap(true, Ordering::Relaxed) {
+                ratatui::restore();
+                std::process::exit(130);
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the throughput figures, once per measurement interval.
+    fn measure(&mut self) {
+        let now = Instant::now();
+        let (last, last_diffs, last_threads) = &self.last_sample;
+        if now.duration_since(*last) < RATE_INTERVAL {
+            return;
+        }
+        let secs = now.duration_since(*last).as_secs_f64();
+
+        let diffs = self.stats.snapshot().diffs;
+        let threads = self
+            .stats
+            .thread_diffs()
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        self.rate = (diffs - last_diffs) as f64 / secs;
+        self.thread_rates = threads
+            .iter()
+            .map(|(thread, diffs)| {
+                let last = last_threads.get(thread).copied().unwrap_or_default();
+                (*thread, (diffs - last) as f64 / secs)
+            })
+            .collect();
+        self.last_sample = (now, diffs, threads);
+    }
+
+    /// Draws the dashboard.
+    fn render(&self, frame: &mut Frame) {
+        let [header, middle, feed, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(8),
+            Constraint::Length(12),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [threads, gas] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(middle);
+
+        self.render_progress(frame, header);
+        self.render_threads(frame, threads);
+        self.render_gas(frame, gas);
+        self.render_failures(frame, feed);
+        let hint = if self.stop.load(Ordering::Relaxed) {
+            "Stopping after the current iterations, press q again to exit now"
+        } else {
+            "Press q to stop the campaign"
+        };
+        frame.render_widget(Paragraph::new(hint).dark_gray(), footer);
+    }
+
+    /// Draws the campaign's progress and aggregate statistics.
+    fn render_progress(&self, frame: &mut Frame, area: Rect) {
+        let stats = self.stats.snapshot();
+        let elapsed = self.started.elapsed();
+        let label = format!(
+            "{} diffs | {:.0}/s | {} mismatches | {}s",
+            stats.diffs,
+            self.rate,
+            stats.mismatches,
+            elapsed.as_secs()
+        );
+        let ratio = match self.duration {
+            Some(duration) => elapsed.as_secs_f64() / duration.as_secs_f64(),
+            None if self.diff_count == 0 => 0.0,
+            None => stats.diffs as f64 / self.diff_count as f64,
+        };
+        let color = if stats.mismatches > 0 {
+            Color::Red
+        } else {
+            Color::Green
+        };
+
+        let block = Block::bordered().title(" lib-keccak differential campaign ");
+        if self.diff_count == 0 && self.duration.is_none() {
+            frame.render_widget(Paragraph::new(label).fg(color).block(block), area);
+        } else {
+            let gauge = Gauge::default()
+                .block(block)
+                .gauge_style(Style::new().fg(color))
+                .ratio(ratio.clamp(0.0, 1.0))
+                .label(label);
+            frame.render_widget(gauge, area);
+        }
+    }
+
+    /// Draws the number of diffs and the throughput of every task.
+    fn render_threads(&self, frame: &mut Frame, area: Rect) {
+        let rows = self.last_sample.2.iter().map(|(thread, diffs)| {
+            let rate = self.thread_rates.get(thread).copied().unwrap_or_default();
+            Row::new([
+                format!("Thread {thread}"),
+                diffs.to_string(),
+                format!("{rate:.0}"),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(40),
+                Constraint::Percentage(30),
+                Constraint::Percentage(30),
+            ],
+        )
+        .header(Row::new(["Task", "Diffs", "Diffs/s"]).bold())
+        .block(Block::bordered().title(" Throughput "));
+        frame.render_widget(table, area);
+    }
+
+    /// Draws the percentiles of the gas used per input.
+    fn render_gas(&self, frame: &mut Frame, area: Rect) {
+        let gas = self.stats.gas();
+        let rows = GAS_PERCENTILES.iter().map(|(label, quantile)| {
+            let value = gas
+                .percentile(*quantile)
+                .map_or_else(|| "-".to_string(), |gas| gas.to_string());
+            Row::new([label.to_string(), value])
+        });
+        let table = Table::new(rows, [Constraint::Length(8), Constraint::Min(10)])
+            .header(Row::new(["", "Gas"]).bold())
+            .block(Block::bordered().title(" Gas per input "));
+        frame.render_widget(table, area);
+    }
+
+    /// Draws the feed of the most recent mismatches.
+    fn render_failures(&self, frame: &mut Frame, area: Rect) {
+        let failures = self.stats.recent_failures();
+        let block = Block::bordered().title(" Recent mismatches ");
+        if failures.is_empty() {
+            let none = Paragraph::new("No mismatches so far").dark_gray();
+            frame.render_widget(none.block(block), area);
+        } else {
+            let list = List::new(failures).red().block(block);
+            frame.render_widget(list, area);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Differential testing of the output phase of the sponge, for contracts that support squeezing
+//! repeatedly.
+//!
+//! A contract whose `squeeze` returns the next 32 bytes of the output stream on every call, rather
+//! than resetting the sponge like `StatefulSponge` does, is an extendable-output function. The
+//! concatenation of its successive squeezes must then equal the output stream of the reference
+//! sponge (see [crate::permutation::sponge_output]), which crosses into a permutation of the output
+//! phase after every 136 bytes. The stream is that of Keccak256 padding, whose first 32 bytes are
+//! the Keccak256 digest, or that of SHAKE256, which is cross-checked against the `sha3` crate.
+
+use crate::{
+    permutation::{sponge_output, KECCAK_SUFFIX, RATE_BYTES, SHAKE_SUFFIX},
+    DiffFuzzer,
+};
+use anyhow::{bail, Result};
+use std::{fmt, str::FromStr};
+
+/// The padding of the output stream that the squeezes are compared against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum XofPadding {
+    /// The padding of Keccak256, so that the first squeeze returns the Keccak256 digest.
+    #[default]
+    Keccak,
+    /// The padding of SHAKE256.
+    Shake,
+}
+
+impl XofPadding {
+    /// Every padding.
+    pub const ALL: [Self; 2] = [Self::Keccak, Self::Shake];
+
+    /// Returns the name of the padding on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Keccak => "keccak",
+            Self::Shake => "shake",
+        }
+    }
+
+    /// Returns the first padding byte, with the domain separation bits.
+    pub fn suffix(self) -> u8 {
+        match self {
+            Self::Keccak => KECCAK_SUFFIX,
+            Self::Shake => SHAKE_SUFFIX,
+        }
+    }
+}
+
+impl fmt::Display for XofPadding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for XofPadding {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        match Self::ALL.into_iter().find(|padding| padding.name() == name) {
+            Some(padding) => Ok(padding),
+            None => bail!(
+                "Unknown output padding `{name}`, expected one of {}",
+                Self::ALL.map(Self::name).join(", ")
+            ),
+        }
+    }
+}
+
+/// Returns the first `len` bytes of the output stream of `input` under `padding`. The SHAKE256
+/// stream of the in-crate sponge is checked against that of the `sha3` crate, and the first 32
+/// bytes of the Keccak256 one against tiny-keccak, failing if either disagrees.
+pub fn reference_stream(input: &[u8], padding: XofPadding, len: usize) -> Result<Vec<u8>> {
+    let stream = sponge_output(input, padding.suffix(), len);
+    let mut expected = vec![0u8; len];
+    let checked = match padding {
+        XofPadding::Keccak => {
+            let mut digest = [0u8; 32];
+            crate::hashing::hash_input_tiny(input, &mut digest);
+            let checked = len.min(digest.len());
+            expected[..checked].copy_from_slice(&digest[..checked]);
+            checked
+        }
+        XofPadding::Shake => {
+            use sha3::digest::{ExtendableOutput, Update, XofReader};
+
+            let mut shake = sha3::Shake256::default();
+            shake.update(input);
+            shake.finalize_xof().read(&mut expected);
+            len
+        }
+    };
+    if stream[..checked] != expected[..checked] {
+        bail!(
+            "The in-crate {padding} output stream of the {} byte input disagrees with the reference \
+             crate",
+            input.len()
+        );
+    }
+    Ok(stream)
+}
+
+/// An input that the concatenated squeezes of the contract diverged from the reference stream on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqueezeStreamDivergence {
+    /// The input.
+    pub input: Vec<u8>,
+    /// The index of the first squeeze that diverged, counted from 0.
+    pub squeeze: usize,
+    /// The concatenated squeezes of the contract.
+    pub contract: Vec<u8>,
+    /// The reference stream.
+    pub reference: Vec<u8>,
+}
+
+impl SqueezeStreamDivergence {
+    /// Returns `true` if the divergence lies beyond the first block of output, i.e. past the first
+    /// permutation of the output phase.
+    pub fn beyond_first_block(&self) -> bool {
+        (self.squeeze + 1) * 32 > RATE_BYTES
+    }
+}
+
+/// The outcome of diffing repeated squeezes (see [diff_squeezes]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SqueezeStreamReport {
+    /// The number of inputs that were diffed.
+    pub inputs: u64,
+    /// The number of bytes squeezed from every input.
+    pub stream_bytes: usize,
+    /// The first input that diverged from the reference stream, if any.
+    pub divergence: Option<SqueezeStreamDivergence>,
+}
+
+/// Absorbs each of the first `inputs` inputs of the seeded input stream of `fuzzer` (see
+/// [DiffFuzzer::generate_fresh]) into a fresh deployment of the fuzzer's contract under the latest
+/// spec (see [DiffFuzzer::deployment]) in a single call, squeezes it `squeezes` times, and compares
+/// the concatenated digests against the reference stream under `padding`, stopping at the first
+/// input that diverges. Requires the `evm` feature, and the fuzzer's seed.
+pub fn diff_squeezes(
+    fuzzer: &DiffFuzzer,
+    inputs: u64,
+    squeezes: usize,
+    padding: XofPadding,
+) -> Result<SqueezeStreamReport> {
+    if squeezes == 0 {
+        bail!("At least one squeeze is required per input");
+    }
+    if fuzzer.shards == 0 {
+        bail!("The inputs are dealt over the shards, so at least one shard is required");
+    }
+    #[cfg(feature = "evm")]
+    {
+        use crate::harness::{absorb_evm, squeeze_evm};
+
+        let mut report = SqueezeStreamReport {
+            stream_bytes: squeezes * 32,
+            ..Default::default()
+        };
+        let deployment = fuzzer.deployment();
+        for i in 0..inputs {
+            let input = fuzzer.generate_fresh(i % fuzzer.shards, i / fuzzer.shards)?;
+            let reference = reference_stream(&input, padding, report.stream_bytes)?;
+            let mut evm = deployment.init_evm()?;
+            absorb_evm(&mut evm, &input)?;
+            let mut contract = Vec::with_capacity(report.stream_bytes);
+            for _ in 0..squeezes {
+                contract.extend_from_slice(&squeeze_evm(&mut evm)?);
+            }
+            report.inputs += 1;
+            if let Some(squeeze) = contract
+                .chunks(32)
+                .zip(reference.chunks(32))
+                .position(|(contract, reference)| contract != reference)
+            {
+                report.divergence = Some(SqueezeStreamDivergence {
+                    input,
+                    squeeze,
+                    contract,
+                    reference,
+                });
+                break;
+            }
+        }
+        Ok(report)
+    }
+    #[cfg(not(feature = "evm"))]
+    {
+        let _ = (fuzzer, inputs, padding);
+        bail!("Diffing repeated squeezes requires the `evm` feature")
+    }
+}